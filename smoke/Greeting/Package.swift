@@ -0,0 +1,14 @@
+// swift-tools-version:5.7
+import PackageDescription
+
+let package = Package(
+    name: "Greeting",
+    platforms: [.iOS(.v13)],
+    products: [
+        .library(name: "Greeting", targets: ["Greeting"])
+    ],
+    targets: [
+        .target(name: "Greeting"),
+        .testTarget(name: "GreetingTests", dependencies: ["Greeting"])
+    ]
+)
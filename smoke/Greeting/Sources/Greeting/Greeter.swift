@@ -0,0 +1,16 @@
+public struct Greeter {
+    public let name: String
+
+    public init(name: String) {
+        self.name = name
+    }
+
+    public func greeting() -> String {
+        "Hello, \(name)!"
+    }
+
+    // Left untested, so the sample is never fully covered.
+    public func farewell() -> String {
+        "Goodbye, \(name)!"
+    }
+}
@@ -0,0 +1,8 @@
+import XCTest
+@testable import Greeting
+
+final class GreeterTests: XCTestCase {
+    func testGreeting() {
+        XCTAssertEqual(Greeter(name: "xcreport").greeting(), "Hello, xcreport!")
+    }
+}
@@ -0,0 +1,280 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::path::{Component, Path, PathBuf};
+use indexmap::IndexMap;
+use polars::frame::DataFrame;
+use polars::prelude::DataType;
+use serde::{Deserialize, Serialize};
+
+use crate::data::SquadData;
+use crate::err::XCReportError;
+use crate::events;
+use crate::metadata::RunMetadata;
+use crate::normalize::normalize_squad_name;
+
+/// The catalog entities of a squad in a components file: an entity ref, or a list of them.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum ComponentRefs {
+    One(String),
+    Many(Vec<String>)
+}
+
+/// File names of the catalog descriptor files read by `--ownership-source backstage:<dir>`.
+const CATALOG_FILE_NAMES: [&str; 2] = ["catalog-info.yaml", "catalog-info.yml"];
+/// Annotation with the location of a component's source code, e.g. `url:https://github.com/org/app/tree/main/Features/Payments`.
+const SOURCE_LOCATION_ANNOTATION: &str = "backstage.io/source-location";
+
+/// A component of a catalog-info file, with the fields its ownership is derived from.
+#[derive(Deserialize)]
+struct CatalogComponent {
+    metadata: ComponentMetadata,
+    #[serde(default)]
+    spec: ComponentSpec
+}
+
+#[derive(Deserialize)]
+struct ComponentMetadata {
+    name: String,
+    #[serde(default)]
+    annotations: HashMap<String, String>
+}
+
+#[derive(Deserialize, Default)]
+struct ComponentSpec {
+    owner: Option<String>
+}
+
+/// Coverage metrics of the catalog entities, saved as `backstage.json` for scorecards to read.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Scorecard {
+    run: String,
+    generated_at: String,
+    commit: Option<String>,
+    entities: Vec<EntityMetrics>
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EntityMetrics {
+    entity_ref: String,
+    squad: String,
+    metrics: Metrics
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Metrics {
+    coverage: Option<f64>,
+    covered_lines: Option<i64>,
+    executable_lines: Option<i64>,
+    files: Option<i64>
+}
+
+pub fn parse_components_file(path: &Path) -> Result<IndexMap<String, ComponentRefs>, XCReportError> {
+    let file = std::fs::File::open(path)
+        .map_err(XCReportError::FileIO)?;
+
+    match path.extension().and_then(OsStr::to_str) {
+        Some("json") => serde_json::from_reader(file).map_err(XCReportError::Serde),
+        _ => serde_yaml::from_reader(file).map_err(XCReportError::Yaml)
+    }
+}
+
+/// Writes the metrics of every squad of the summary to each of its catalog entities. Squads without entities
+/// are left out. The summary needs its line totals, as `df::process_report` gives them whatever the `--aggregate`.
+pub fn save_scorecard(
+    summary: &DataFrame,
+    components: &IndexMap<String, ComponentRefs>,
+    metadata: &RunMetadata,
+    path: &Path
+) -> Result<PathBuf, XCReportError> {
+    let components: IndexMap<String, &ComponentRefs> = components
+        .iter()
+        .map(|(squad, refs)| (normalize_squad_name(squad), refs))
+        .collect();
+
+    let squads = summary.column("Squad")
+        .and_then(|c| c.str())
+        .map_err(XCReportError::Polars)?;
+    let coverages = summary.column("Coverage %")
+        .and_then(|c| c.f64())
+        .map_err(XCReportError::Polars)?;
+    let integer_column = |name: &str| {
+        summary.column(name)
+            .and_then(|c| c.cast(&DataType::Int64))
+            .map_err(XCReportError::Polars)
+    };
+    let covered_lines = integer_column("Covered Lines")?;
+    let executable_lines = integer_column("Executable Lines")?;
+    let files = integer_column("Count")?;
+    let covered_lines = covered_lines.i64().map_err(XCReportError::Polars)?;
+    let executable_lines = executable_lines.i64().map_err(XCReportError::Polars)?;
+    let files = files.i64().map_err(XCReportError::Polars)?;
+
+    let mut entities: Vec<EntityMetrics> = vec![];
+
+    for (row, squad) in squads.into_iter().enumerate() {
+        let Some(squad) = squad else {
+            continue
+        };
+        let Some(refs) = components.get(squad) else {
+            continue
+        };
+
+        let refs = match refs {
+            ComponentRefs::One(entity_ref) => vec![entity_ref],
+            ComponentRefs::Many(entity_refs) => entity_refs.iter().collect()
+        };
+
+        for entity_ref in refs {
+            entities.push(EntityMetrics {
+                entity_ref: full_entity_ref(entity_ref),
+                squad: squad.to_string(),
+                metrics: Metrics {
+                    coverage: coverages.get(row),
+                    covered_lines: covered_lines.get(row),
+                    executable_lines: executable_lines.get(row),
+                    files: files.get(row)
+                }
+            });
+        }
+    }
+
+    for squad in components.keys().filter(|squad| !squads.into_iter().flatten().any(|name| name == squad.as_str())) {
+        events::warning(&format!("squad {:?} of the components file is not in the report", squad));
+    }
+
+    let scorecard = Scorecard {
+        run: metadata.identifier.clone(),
+        generated_at: metadata.timestamp.clone(),
+        commit: metadata.commit.clone(),
+        entities
+    };
+    let json = serde_json::to_string_pretty(&scorecard)
+        .map_err(XCReportError::Serde)?;
+
+    std::fs::write(path, json)
+        .map_err(XCReportError::FileIO)?;
+
+    Ok(path.to_path_buf())
+}
+
+/// Reads the squads from the components of the catalog-info files under the directory: the owner of
+/// every component owns the path of its source location annotation, or the directory of its
+/// catalog-info file without one. Deeper paths come first, so nested components own their files.
+pub fn catalog_squads(catalog_dir: &Path) -> Result<Vec<SquadData>, XCReportError> {
+    let mut catalog_files: Vec<PathBuf> = vec![];
+    collect_catalog_files(catalog_dir, &mut catalog_files)?;
+    catalog_files.sort();
+
+    let mut squads_data: Vec<SquadData> = vec![];
+
+    for catalog_file in &catalog_files {
+        let contents = std::fs::read_to_string(catalog_file)
+            .map_err(XCReportError::FileIO)?;
+
+        // A catalog-info file can describe several entities, one per yaml document.
+        for document in serde_yaml::Deserializer::from_str(&contents) {
+            let entity = serde_yaml::Value::deserialize(document)
+                .map_err(XCReportError::Yaml)?;
+            let is_component = entity.get("kind")
+                .and_then(serde_yaml::Value::as_str)
+                .is_some_and(|kind| kind.eq_ignore_ascii_case("Component"));
+            if !is_component {
+                continue
+            }
+
+            let component: CatalogComponent = serde_yaml::from_value(entity)
+                .map_err(XCReportError::Yaml)?;
+            let Some(owner) = &component.spec.owner else {
+                events::warning(&format!("component {:?} of {:?} has no owner, leaving it out", component.metadata.name, catalog_file));
+                continue
+            };
+            let Some(pattern) = component_pattern(&component, catalog_file, catalog_dir) else {
+                events::warning(&format!("component {:?} of {:?} has no source path in the directory, leaving it out", component.metadata.name, catalog_file));
+                continue
+            };
+
+            squads_data.push(SquadData::new(owner_name(owner), pattern, None));
+        }
+    }
+
+    if squads_data.is_empty() {
+        return Err(XCReportError::EmptyCatalog { dir: catalog_dir.to_path_buf() })
+    }
+
+    squads_data.sort_by_key(|squad_data| Reverse(squad_data.file_name().matches('/').count()));
+
+    Ok(squads_data)
+}
+
+fn collect_catalog_files(dir: &Path, catalog_files: &mut Vec<PathBuf>) -> Result<(), XCReportError> {
+    for entry in std::fs::read_dir(dir).map_err(XCReportError::FileIO)? {
+        let path = entry.map_err(XCReportError::FileIO)?.path();
+        let Some(file_name) = path.file_name().and_then(OsStr::to_str) else {
+            continue
+        };
+
+        if file_name.starts_with('.') {
+            continue
+        }
+
+        if path.is_dir() {
+            collect_catalog_files(&path, catalog_files)?;
+        } else if CATALOG_FILE_NAMES.contains(&file_name) {
+            catalog_files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// The path pattern of the component's source code, relative to the catalog directory, e.g. `Features/Payments/`.
+fn component_pattern(component: &CatalogComponent, catalog_file: &Path, catalog_dir: &Path) -> Option<String> {
+    let file_dir = catalog_file.parent()?.strip_prefix(catalog_dir).ok()?;
+
+    let location = component.metadata.annotations.get(SOURCE_LOCATION_ANNOTATION);
+    let path = if let Some(url) = location.and_then(|location| location.strip_prefix("url:")) {
+        // Repository urls have the path after the branch, e.g. `.../tree/main/Features/Payments`.
+        let (_, path) = url.split_once("/tree/").or_else(|| url.split_once("/blob/"))?;
+        PathBuf::from(path.split_once('/')?.1)
+    } else if let Some(dir) = location.and_then(|location| location.strip_prefix("dir:")) {
+        file_dir.join(dir)
+    } else {
+        file_dir.to_path_buf()
+    };
+
+    let mut names: Vec<String> = vec![];
+    for part in path.components() {
+        match part {
+            Component::Normal(name) => names.push(name.to_string_lossy().into_owned()),
+            Component::ParentDir => {
+                names.pop()?;
+            },
+            _ => {}
+        }
+    }
+
+    // A component at the root would own every file.
+    (!names.is_empty()).then(|| format!("{}/", names.join("/")))
+}
+
+/// The name of the owner of an entity ref, `group:default/payments` being `payments`.
+fn owner_name(owner: &str) -> String {
+    owner.trim().rsplit(['/', ':']).next().unwrap_or_default().to_string()
+}
+
+/// Completes a short entity ref the way the catalog does, `payments` being `component:default/payments`.
+fn full_entity_ref(entity_ref: &str) -> String {
+    let entity_ref = entity_ref.trim();
+    let (kind, name) = entity_ref.split_once(':').unwrap_or(("component", entity_ref));
+
+    if name.contains('/') {
+        format!("{}:{}", kind, name)
+    } else {
+        format!("{}:default/{}", kind, name)
+    }
+}
@@ -0,0 +1,79 @@
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use crate::err::{CommandExecutionError, XCReportError};
+use crate::scrub::scrub;
+
+/// The raw output of xcodebuild, saved whatever the formatter makes of it, e.g. for the errors xcpretty
+/// leaves out. Every test run of a run is appended to the same log.
+pub struct BuildLog {
+    writer: Arc<Mutex<Box<dyn Write + Send>>>,
+    /// Compresses the log with `--compress-log`, as a gzip member per test run.
+    gzip: Option<Child>
+}
+
+impl BuildLog {
+    pub fn open(path: &Path, compress: bool) -> Result<Self, XCReportError> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(XCReportError::FileIO)?;
+
+        if !compress {
+            return Ok(BuildLog { writer: Arc::new(Mutex::new(Box::new(file))), gzip: None })
+        }
+
+        let mut gzip = Command::new("gzip")
+            .arg("-c")
+            .stdin(Stdio::piped())
+            .stdout(file)
+            .spawn()
+            .map_err(|e| XCReportError::CommandExecution(CommandExecutionError::Gzip(e)))?;
+        let stdin = gzip.stdin
+            .take()
+            .ok_or(XCReportError::CommandExecution(CommandExecutionError::NonZeroExit { desc: String::from("N/A") }))?;
+
+        Ok(BuildLog { writer: Arc::new(Mutex::new(Box::new(stdin))), gzip: Some(gzip) })
+    }
+
+    /// Copies the output line by line to `destination` and, scrubbed of secrets, to the log, until the
+    /// output ends. The log keeps every line when the destination, e.g. xcpretty, exits early.
+    pub fn tee(&self, output: impl Read + Send + 'static, mut destination: impl Write + Send + 'static) -> JoinHandle<()> {
+        let writer = Arc::clone(&self.writer);
+
+        thread::spawn(move || {
+            let mut output = BufReader::new(output);
+            let mut line: Vec<u8> = vec![];
+
+            while output.read_until(b'\n', &mut line).is_ok_and(|read| read > 0) {
+                if let Ok(mut writer) = writer.lock() {
+                    let _ = writer.write_all(scrub(&String::from_utf8_lossy(&line)).as_bytes());
+                }
+                let _ = destination.write_all(&line);
+                line.clear();
+            }
+        })
+    }
+
+    /// Flushes the log, waiting for gzip to write it out. The tee threads have to be joined first.
+    pub fn finish(self) -> Result<(), XCReportError> {
+        if let Ok(mut writer) = self.writer.lock() {
+            writer.flush()
+                .map_err(XCReportError::FileIO)?;
+        }
+        // Closes the stdin of gzip, the tee threads having ended.
+        drop(self.writer);
+
+        if let Some(mut gzip) = self.gzip {
+            gzip.wait()
+                .map_err(|e| XCReportError::CommandExecution(CommandExecutionError::Gzip(e)))?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,162 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::data::{Target, TargetFile, XCodeBuildReport};
+use crate::err::XCReportError;
+use crate::fs::{home_path, target_cache_path, xcresult_cache_path};
+
+/// Coverage of a target along with a fingerprint of its sources at the time it was measured.
+#[derive(Serialize, Deserialize)]
+pub struct TargetSnapshot {
+    fingerprint: String,
+    target: Target
+}
+
+/// Hashes the paths, sizes and modification times of the target's source files.
+pub fn fingerprint(files: &[TargetFile]) -> String {
+    let mut paths: Vec<&String> = files.iter().map(|f| f.file_path()).collect();
+    paths.sort();
+
+    let mut hasher = Sha256::new();
+
+    for path in paths {
+        hasher.update(path.as_bytes());
+
+        match fs::metadata(path) {
+            Ok(metadata) => {
+                let modified = metadata.modified()
+                    .ok()
+                    .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+                    .map(|d| d.as_nanos())
+                    .unwrap_or_default();
+
+                hasher.update(metadata.len().to_le_bytes());
+                hasher.update(modified.to_le_bytes());
+            },
+            Err(_) => hasher.update(b"missing")
+        }
+    }
+
+    format!("{:x}", hasher.finalize())
+}
+
+pub fn save_target_cache(report: &XCodeBuildReport, identifier: &String) -> Result<PathBuf, XCReportError> {
+    let snapshots: Vec<TargetSnapshot> = report.targets()
+        .iter()
+        .map(|target| TargetSnapshot {
+            fingerprint: fingerprint(target.files()),
+            target: target.clone()
+        })
+        .collect();
+
+    let target_cache_path = target_cache_path(identifier)?;
+    let json = serde_json::to_string(&snapshots)
+        .map_err(XCReportError::Serde)?;
+
+    fs::write(&target_cache_path, json)
+        .map_err(XCReportError::FileIO)?;
+
+    Ok(target_cache_path)
+}
+
+/// Loads the target cache of the most recent run before `identifier`, if any.
+pub fn previous_target_cache(identifier: &String) -> Result<Option<Vec<TargetSnapshot>>, XCReportError> {
+    let mut previous_runs: Vec<String> = fs::read_dir(home_path()?)
+        .map_err(XCReportError::FileIO)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| name < identifier)
+        .collect();
+    previous_runs.sort();
+
+    for previous_run in previous_runs.iter().rev() {
+        let Ok(json) = fs::read_to_string(target_cache_path(previous_run)?) else {
+            continue
+        };
+
+        let snapshots = serde_json::from_str(&json)
+            .map_err(XCReportError::Serde)?;
+
+        return Ok(Some(snapshots))
+    }
+
+    Ok(None)
+}
+
+/// Adds targets that are missing from the report but whose sources did not change since they were
+/// cached, marking their files as stale. Returns the names of the reused targets.
+pub fn reuse_unchanged_targets(report: &mut XCodeBuildReport, snapshots: Vec<TargetSnapshot>) -> Vec<String> {
+    let mut reused: Vec<String> = vec![];
+
+    for snapshot in snapshots {
+        if report.has_target(snapshot.target.name()) {
+            continue
+        }
+
+        if fingerprint(snapshot.target.files()) != snapshot.fingerprint {
+            continue
+        }
+
+        let files = snapshot.target
+            .files()
+            .iter()
+            .cloned()
+            .map(|mut file| {
+                file.mark_stale();
+                file
+            })
+            .collect();
+
+        reused.push(snapshot.target.name().clone());
+        report.push_target(Target::new(snapshot.target.name().clone(), files));
+    }
+
+    reused
+}
+
+/// Identifies a result bundle by its path and the modification times of the bundle and its
+/// `Info.plist`, which Xcode rewrites whenever it writes the bundle.
+fn bundle_key(xcresult_file: &Path) -> Option<String> {
+    let bundle_path = fs::canonicalize(xcresult_file).ok()?;
+    let mut hasher = Sha256::new();
+    hasher.update(bundle_path.to_string_lossy().as_bytes());
+
+    for path in [bundle_path.clone(), bundle_path.join("Info.plist")] {
+        let modified = fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+
+        hasher.update(modified.to_le_bytes());
+    }
+
+    Some(format!("{:x}", hasher.finalize()))
+}
+
+/// The `xccov` coverage JSON of the bundle saved by an earlier run, if the bundle did not change since.
+pub fn cached_coverage_json(xcresult_file: &Path) -> Option<String> {
+    let key = bundle_key(xcresult_file)?;
+
+    fs::read_to_string(xcresult_cache_path(&key).ok()?).ok()
+}
+
+pub fn save_coverage_json(xcresult_file: &Path, json: &str) -> Result<(), XCReportError> {
+    let Some(key) = bundle_key(xcresult_file) else {
+        return Ok(())
+    };
+
+    let cache_path = xcresult_cache_path(&key)?;
+
+    if let Some(cache_dir) = cache_path.parent() {
+        fs::create_dir_all(cache_dir)
+            .map_err(XCReportError::FileIO)?;
+    }
+
+    fs::write(cache_path, json)
+        .map_err(XCReportError::FileIO)
+}
@@ -1,59 +1,798 @@
 use std::ffi::OsStr;
-use std::path::PathBuf;
-use clap::{Parser, Subcommand};
-use crate::err::{FilePathError, XCReportError};
+use std::path::{Path, PathBuf};
+use chrono::NaiveDate;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::{Deserialize, Serialize};
+use crate::err::{DirPathError, FilePathError, XCReportError};
+use crate::render;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 pub struct Cli {
+    /// Optional | TOML config file with default values for the options (defaults to ./xcreport.toml).
+    #[arg(long, global = true, env = "XCREPORT_CONFIG")]
+    config: Option<PathBuf>,
+    /// Optional | File to write progress events to, as JSON lines.
+    #[arg(long, global = true, env = "XCREPORT_EVENTS_FILE")]
+    events_file: Option<PathBuf>,
+    /// Optional | Format errors are printed to stderr in.
+    #[arg(long, global = true, value_enum, default_value_t = ErrorFormat::Text, env = "XCREPORT_ERROR_FORMAT")]
+    error_format: ErrorFormat,
+    /// Optional | Xcode to run, by the path of the app or its version, e.g. 15.2. Sets DEVELOPER_DIR for every tool xcreport runs.
+    #[arg(long, global = true, env = "XCREPORT_XCODE")]
+    xcode: Option<String>,
+    /// Optional | xcrun binary to run instead of the one on PATH, e.g. a wrapper of a custom toolchain.
+    #[arg(long, global = true, value_name = "PATH", env = "XCREPORT_XCRUN_PATH")]
+    xcrun_path: Option<PathBuf>,
+    /// Optional | xcodebuild binary to run instead of the one on PATH.
+    #[arg(long, global = true, value_name = "PATH", env = "XCREPORT_XCODEBUILD_PATH")]
+    xcodebuild_path: Option<PathBuf>,
+    /// Optional | Argument added to the xccov report invocation, e.g. --only-targets. Repeat for several.
+    #[arg(long, global = true, value_name = "ARG", allow_hyphen_values = true, env = "XCREPORT_XCCOV_ARG")]
+    xccov_arg: Vec<String>,
     #[command(subcommand)]
     command: Commands
 }
 
 impl Cli {
-    pub fn command(&self) -> &Commands {
+    pub fn events_file(&self) -> Option<&PathBuf> {
+        self.events_file.as_ref()
+    }
+
+    pub fn error_format(&self) -> ErrorFormat {
+        self.error_format
+    }
+
+    pub fn xcode(&self) -> Option<&String> {
+        self.xcode.as_ref()
+    }
+
+    pub fn xcrun_path(&self) -> Option<&PathBuf> {
+        self.xcrun_path.as_ref()
+    }
+
+    pub fn xcodebuild_path(&self) -> Option<&PathBuf> {
+        self.xcodebuild_path.as_ref()
+    }
+
+    pub fn xccov_args(&self) -> &[String] {
+        &self.xccov_arg
+    }
+
+    pub fn subcommand(&self) -> &Commands {
         &self.command
     }
 }
 
+#[derive(ValueEnum, Clone, Copy)]
+pub enum ErrorFormat {
+    /// A human readable line.
+    Text,
+    /// A JSON object with the category, exit code and message of the error.
+    Json
+}
+
 #[derive(Subcommand)]
 pub enum Commands {
     /// Run tests and generate coverage report
     Run {
-        /// Input csv file to match the test results (Squad and Filepath fields required).
-        #[arg(short, long, value_parser = parse_input_file)]
-        input_file: PathBuf,
+        /// Input csv (Squad and Filepath fields required), yaml or json file to match the test results. Repeat to merge the files of several tribes, later files overriding the patterns of earlier ones. Required unless --ownership-source is given.
+        #[arg(short, long, value_parser = parse_input_file, required_unless_present = "ownership_source", value_delimiter = ',', env = "XCREPORT_INPUT_FILE")]
+        input_file: Vec<PathBuf>,
         /// Path to your xcode project root.
-        #[arg(short, long)]
+        #[arg(short, long, env = "XCREPORT_PROJECT_PATH")]
         project_path: PathBuf,
-        /// Xcodebuild argument - Your workspace name.
-        #[arg(short, long)]
-        workspace: PathBuf,
-        /// Xcodebuild argument - Your scheme name.
-        #[arg(short, long)]
-        scheme: String,
-        /// Xcodebuild argument - Simulator destination.
-        #[arg(short, long)]
-        destination: String,
-        /// Optional | File path to save the generated report.
-        #[arg(short, long, value_parser = parse_output_file)]
-        output_file: Option<PathBuf>
+        /// Optional | Xcodebuild argument - Your workspace name, defaults to the only workspace in the project path.
+        #[arg(short, long, env = "XCREPORT_WORKSPACE")]
+        workspace: Option<PathBuf>,
+        /// Xcodebuild argument - Your scheme name. Repeat to test several schemes and merge their coverage.
+        #[arg(short, long, required = true, env = "XCREPORT_SCHEME")]
+        scheme: Vec<String>,
+        /// Xcodebuild argument - Simulator destination. Required unless --device is given.
+        #[arg(short, long, required_unless_present = "device", conflicts_with = "device", env = "XCREPORT_DESTINATION")]
+        destination: Option<String>,
+        #[command(flatten)]
+        simulator_options: SimulatorOptions,
+        #[command(flatten)]
+        build_options: Box<BuildOptions>,
+        #[command(flatten)]
+        report_options: ReportOptions
     },
     /// Generate coverage report from test result
     Generate {
-        /// Input csv file to match the test results (Squad and Filepath fields required).
-        #[arg(short, long, value_parser = parse_input_file)]
-        input_file: PathBuf,
-        /// Path to the .xcresult file.
-        #[arg(short, long, value_parser = parse_xcresult_file)]
-        xcresult_file: PathBuf,
-        /// Optional | File path to save the generated report.
-        #[arg(short, long, value_parser = parse_output_file)]
+        /// Input csv (Squad and Filepath fields required), yaml or json file to match the test results. Repeat to merge the files of several tribes, later files overriding the patterns of earlier ones. Required unless --ownership-source is given.
+        #[arg(short, long, value_parser = parse_input_file, required_unless_present = "ownership_source", value_delimiter = ',', env = "XCREPORT_INPUT_FILE")]
+        input_file: Vec<PathBuf>,
+        /// Path to the .xcresult file, or a directory of them. Repeat to merge several test results.
+        #[arg(short, long, required = true, value_parser = parse_xcresult_input, env = "XCREPORT_XCRESULT_FILE", value_delimiter = ',')]
+        xcresult_file: Vec<PathBuf>,
+        #[command(flatten)]
+        report_options: ReportOptions
+    },
+    /// Run the tests of a Swift package with swift test and generate coverage report
+    RunSpm {
+        /// Input csv (Squad and Filepath fields required), yaml or json file to match the test results. Repeat to merge the files of several tribes, later files overriding the patterns of earlier ones. Required unless --ownership-source is given.
+        #[arg(short, long, value_parser = parse_input_file, required_unless_present = "ownership_source", value_delimiter = ',', env = "XCREPORT_INPUT_FILE")]
+        input_file: Vec<PathBuf>,
+        /// Optional | Directory of the Package.swift to test.
+        #[arg(short, long, default_value = ".", value_parser = parse_dir, env = "XCREPORT_PACKAGE_PATH")]
+        package_path: PathBuf,
+        #[command(flatten)]
+        report_options: ReportOptions
+    },
+    /// Compare two full reports and write the coverage deltas
+    Compare {
+        /// Full report csv of the baseline run.
+        #[arg(short, long, value_parser = parse_report_file, env = "XCREPORT_BASE")]
+        base: PathBuf,
+        /// Full report csv of the run to compare against the baseline.
+        #[arg(short = 'H', long, value_parser = parse_report_file, env = "XCREPORT_HEAD")]
+        head: PathBuf,
+        /// Optional | Render an HTML diff view of the deltas.
+        #[arg(long, env = "XCREPORT_HTML")]
+        html: bool,
+        #[command(flatten)]
+        rounding: RoundingOptions
+    },
+    /// Run the xcreport commands of the [[pipeline]] tables of the config file one after the other
+    Pipeline,
+    /// Watch a directory and generate a coverage report for every new test result
+    Watch {
+        /// Input csv (Squad and Filepath fields required), yaml or json file to match the test results. Repeat to merge the files of several tribes, later files overriding the patterns of earlier ones. Required unless --ownership-source is given.
+        #[arg(short, long, value_parser = parse_input_file, required_unless_present = "ownership_source", value_delimiter = ',', env = "XCREPORT_INPUT_FILE")]
+        input_file: Vec<PathBuf>,
+        /// Directory to watch for new .xcresult files.
+        #[arg(short, long, value_parser = parse_dir, env = "XCREPORT_DIR")]
+        dir: PathBuf,
+        /// Optional | Seconds without changes before a new .xcresult file is considered complete.
+        #[arg(long, default_value_t = 10, env = "XCREPORT_SETTLE_SECONDS")]
+        settle_seconds: u64,
+        #[command(flatten)]
+        report_options: ReportOptions
+    },
+    /// Write an HTML dashboard with the coverage trend of every squad across all previous runs
+    Dashboard {
+        /// Directory to write the dashboard site to, created if missing.
+        #[arg(short, long, env = "XCREPORT_OUTPUT_DIR")]
+        output_dir: PathBuf,
+        #[command(flatten)]
+        rounding: RoundingOptions
+    },
+    /// Export the coverage of every squad across all previous runs as a long-format table for BI tools
+    History {
+        /// Format of the exported table.
+        #[arg(long, value_enum, env = "XCREPORT_EXPORT")]
+        export: HistoryFormat,
+        /// Directory to write the table to, created if missing.
+        #[arg(long, env = "XCREPORT_OUT")]
+        out: PathBuf,
+        #[command(flatten)]
+        rounding: RoundingOptions
+    },
+    /// List the previous runs with their date, scheme, overall coverage and report path
+    List {
+        /// Optional | Only list the N most recent runs.
+        #[arg(long, value_name = "N", env = "XCREPORT_LIMIT")]
+        limit: Option<usize>
+    },
+    /// Merge the runs of history directories from other machines into ~/.xcreport
+    MergeHistory {
+        /// History directories to merge, e.g. the ~/.xcreport directories of parallel CI jobs saved as artifacts.
+        #[arg(required = true, value_parser = parse_dir)]
+        stores: Vec<PathBuf>
+    },
+    /// Serve the coverage of the latest run as JSON and as a status badge per squad
+    Serve {
+        /// Optional | Address and port to listen on.
+        #[arg(long, default_value = "127.0.0.1:8080", env = "XCREPORT_ADDRESS")]
+        address: String,
+        #[command(flatten)]
+        rounding: RoundingOptions
+    },
+    /// Save or restore the derived data directory, so CI jobs can reuse each other's builds
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction
+    },
+    /// Keep a committed file of coverage floors per squad that runs with --ratchet-file must not drop below
+    Ratchet {
+        #[command(subcommand)]
+        action: RatchetAction
+    },
+    /// Check that the tools and directories xcreport depends on are in place, suggesting fixes
+    Doctor,
+    /// Run a bundled sample package through the whole pipeline to check that xcreport works on this machine
+    Smoke,
+    /// Print the completion script of a shell, e.g. for a package manager to install
+    Completions {
+        /// Shell to print the completions of.
+        #[arg(value_enum)]
+        shell: clap_complete::Shell
+    },
+    /// Generate a squads file with one squad per directory matching a pattern
+    InferOwners {
+        /// Directory patterns relative to --root whose matches become squads, e.g. 'Features/*'.
+        #[arg(long, required = true, num_args = 1.., value_delimiter = ',', env = "XCREPORT_BY_DIRECTORY")]
+        by_directory: Vec<String>,
+        /// Optional | Directory the patterns are relative to.
+        #[arg(long, default_value = ".", value_parser = parse_dir, env = "XCREPORT_ROOT")]
+        root: PathBuf,
+        /// Optional | File path to save the yaml squads file to, printed otherwise.
+        #[arg(short, long, value_parser = parse_output_file, env = "XCREPORT_OUTPUT_FILE")]
         output_file: Option<PathBuf>
+    },
+    /// Convert the squads file into a CODEOWNERS file routing reviews to the GitHub teams of the squads
+    ExportCodeowners {
+        /// Input csv (Squad and Filepath fields required), yaml or json squads file, as passed to run. Repeat to merge the files of several tribes, later files overriding the patterns of earlier ones. Required unless --ownership-source is given.
+        #[arg(short, long, value_parser = parse_input_file, required_unless_present = "ownership_source", value_delimiter = ',', env = "XCREPORT_INPUT_FILE")]
+        input_file: Vec<PathBuf>,
+        /// Yaml or json file mapping every squad to its GitHub team, or a list of teams and users, e.g. 'Payments: "@org/payments"'.
+        #[arg(long, value_parser = parse_teams_file, env = "XCREPORT_TEAMS_FILE")]
+        teams_file: PathBuf,
+        /// Optional | File path to write the CODEOWNERS file to, e.g. .github/CODEOWNERS, replacing it. Printed otherwise.
+        #[arg(short, long, env = "XCREPORT_OUTPUT_FILE")]
+        output_file: Option<PathBuf>,
+        #[command(flatten)]
+        input_options: InputOptions
+    },
+    /// Check that every pattern of the squads file matches files of the repository, without duplicates or conflicting owners
+    ValidateMapping {
+        /// Input csv (Squad and Filepath fields required), yaml or json squads file, as passed to run. Repeat to merge the files of several tribes, later files overriding the patterns of earlier ones. Required unless --ownership-source is given.
+        #[arg(short, long, value_parser = parse_input_file, required_unless_present = "ownership_source", value_delimiter = ',', env = "XCREPORT_INPUT_FILE")]
+        input_file: Vec<PathBuf>,
+        /// Optional | Root of the repository whose files the patterns should match.
+        #[arg(long, default_value = ".", value_parser = parse_dir, env = "XCREPORT_REPO")]
+        repo: PathBuf,
+        #[command(flatten)]
+        input_options: InputOptions
+    },
+    /// Print xcodebuild arguments splitting the test suites into shards of similar duration
+    ShardPlan {
+        /// Number of shards to split the test suites into.
+        #[arg(long, value_parser = clap::value_parser!(u32).range(1..), env = "XCREPORT_SHARDS")]
+        shards: u32,
+        /// Optional | Number of most recent runs with test results whose durations are averaged.
+        #[arg(long, default_value_t = 5, env = "XCREPORT_HISTORY_RUNS")]
+        history_runs: usize,
+        /// Optional | Format the plan is printed in.
+        #[arg(long, value_enum, default_value_t = ShardPlanFormat::Text, env = "XCREPORT_SHARD_PLAN_FORMAT")]
+        format: ShardPlanFormat
+    },
+    /// Upload the line coverage of test results to Codecov or Coveralls
+    Upload {
+        /// Path to the .xcresult file, or a directory of them. Repeat to merge several test results.
+        #[arg(short, long, required = true, value_parser = parse_xcresult_input, env = "XCREPORT_XCRESULT_FILE", value_delimiter = ',')]
+        xcresult_file: Vec<PathBuf>,
+        /// Coverage service to upload to.
+        #[arg(long, value_enum, env = "XCREPORT_SERVICE")]
+        service: UploadService,
+        /// Optional | Upload token of the repository, defaults to CODECOV_TOKEN or COVERALLS_REPO_TOKEN.
+        #[arg(long, hide_env_values = true, env = "XCREPORT_UPLOAD_TOKEN")]
+        token: Option<String>,
+        /// Optional | Commit the coverage belongs to, defaults to the checked out one.
+        #[arg(long, env = "XCREPORT_COMMIT")]
+        commit: Option<String>,
+        /// Optional | Branch the coverage belongs to, defaults to the checked out one.
+        #[arg(long, env = "XCREPORT_BRANCH")]
+        branch: Option<String>,
+        /// Optional | Build machine path of the repository, stripped from file paths to make them repo-relative.
+        #[arg(long, env = "XCREPORT_REPO_ROOT")]
+        repo_root: Option<PathBuf>,
+        /// Optional | Base URL of a self-hosted service, defaults to the public one.
+        #[arg(long, env = "XCREPORT_SERVICE_URL")]
+        service_url: Option<String>
+    },
+    /// Publish the coverage of a run to a code review service
+    Publish {
+        #[command(subcommand)]
+        target: PublishTarget
+    },
+    /// Recompute the reports of a run from its saved inputs and check they match the saved reports
+    Verify {
+        /// Identifier of the run, the name of its directory under ~/.xcreport.
+        identifier: String
+    },
+    /// Open the HTML report of the latest or a given run in the browser, or its csv summary
+    Open {
+        /// Optional | Identifier of the run to open, defaults to the latest run.
+        identifier: Option<String>,
+        /// Optional | Open the csv summary even when the run has an HTML report.
+        #[arg(long, env = "XCREPORT_CSV")]
+        csv: bool,
+        /// Optional | Reveal the report in Finder instead of opening it.
+        #[arg(long, env = "XCREPORT_REVEAL")]
+        reveal: bool
+    },
+    /// Browse the squads and files of a full report in the terminal
+    View {
+        /// Optional | Identifier of the run to browse, defaults to the latest run with a full report.
+        identifier: Option<String>,
+        /// Optional | Full report csv to browse instead of the one of a run.
+        #[arg(short, long, conflicts_with = "identifier", value_parser = parse_existing_file, env = "XCREPORT_FULL_REPORT")]
+        full_report: Option<PathBuf>
     }
 }
 
-fn parse_file(arg: &str, extension: &str) -> Result<PathBuf, XCReportError> {
+impl Commands {
+    /// Report options of the commands generating a coverage report.
+    pub fn report_options(&self) -> Option<&ReportOptions> {
+        match self {
+            Commands::Run { report_options, .. }
+            | Commands::Generate { report_options, .. }
+            | Commands::RunSpm { report_options, .. }
+            | Commands::Watch { report_options, .. } => Some(report_options),
+            _ => None
+        }
+    }
+}
+
+#[derive(Args, Clone)]
+pub struct BuildOptions {
+    /// Optional | Derived data directory to build in, defaults to ~/.xcreport/derived_data.
+    #[arg(long, conflicts_with = "use_project_derived_data", env = "XCREPORT_DERIVED_DATA_PATH")]
+    pub derived_data_path: Option<PathBuf>,
+    /// Optional | Build in the project's regular DerivedData without cleaning it, reusing its build cache.
+    #[arg(long, env = "XCREPORT_USE_PROJECT_DERIVED_DATA")]
+    pub use_project_derived_data: bool,
+    /// Optional | File name of the xcpretty HTML test report, saved in the run directory.
+    #[arg(long, default_value = "xcpretty_report.html", value_parser = parse_file_name, env = "XCREPORT_TEST_REPORT_NAME")]
+    pub test_report_name: String,
+    /// Optional | Build configuration to test, e.g. Debug or Release, the one of the scheme's test action by default.
+    #[arg(long, value_name = "NAME", env = "XCREPORT_CONFIGURATION")]
+    pub configuration: Option<String>,
+    /// Optional | Rerun the tests with another build configuration after the run, e.g. Release, saving the coverage of both per squad side by side to configurations.csv.
+    #[arg(long, value_name = "NAME", conflicts_with = "budget", env = "XCREPORT_COMPARE_CONFIGURATION")]
+    pub compare_configuration: Option<String>,
+    /// Optional | Gzip the raw xcodebuild output saved to the run directory, as xcodebuild.log.gz instead of xcodebuild.log.
+    #[arg(long, env = "XCREPORT_COMPRESS_LOG")]
+    pub compress_log: bool,
+    /// Optional | Minutes after which xcodebuild is killed and the run fails.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..), env = "XCREPORT_TIMEOUT")]
+    pub timeout: Option<u64>,
+    /// Optional | Directory to extract the attachments of failed tests and the diagnostics of the run to when tests fail.
+    #[arg(long, value_name = "DIR", env = "XCREPORT_EXTRACT_ATTACHMENTS")]
+    pub extract_attachments: Option<PathBuf>,
+    /// Optional | Test bundle, suite or test to run, e.g. AppTests/LoginTests, all by default. Repeat or separate with commas.
+    #[arg(long, value_name = "IDENTIFIER", value_delimiter = ',', env = "XCREPORT_ONLY_TESTING")]
+    pub only_testing: Vec<String>,
+    /// Optional | Test bundle, suite or test to leave out, e.g. AppUITests. Repeat or separate with commas.
+    #[arg(long, value_name = "IDENTIFIER", value_delimiter = ',', env = "XCREPORT_SKIP_TESTING")]
+    pub skip_testing: Vec<String>,
+    /// Optional | Minutes of tests to run, picking the suites testing the --changed-files first by their durations in the last runs. The coverage is an estimate.
+    #[arg(long, value_name = "MINUTES", value_parser = clap::value_parser!(u64).range(1..), conflicts_with = "only_testing", env = "XCREPORT_BUDGET")]
+    pub budget: Option<u64>,
+    /// Optional | Rerun the unit and the UI test bundles separately after the run, saving the coverage each kind reaches per squad to test_split.csv.
+    #[arg(long, conflicts_with_all = ["only_testing", "budget"], env = "XCREPORT_SPLIT_BY_TEST_TARGET")]
+    pub split_by_test_target: bool,
+    /// Optional | Build setting passed to xcodebuild, e.g. SWIFT_TREAT_WARNINGS_AS_ERRORS=NO. Repeat or separate with commas.
+    #[arg(long, value_name = "KEY=VALUE", value_delimiter = ',', value_parser = parse_build_setting, env = "XCREPORT_BUILD_SETTING")]
+    pub build_setting: Vec<(String, String)>
+}
+
+#[derive(Args)]
+pub struct ReportOptions {
+    /// Optional | File path to save the generated report.
+    #[arg(short, long, value_parser = parse_output_file, env = "XCREPORT_OUTPUT_FILE")]
+    pub output_file: Option<PathBuf>,
+    /// Optional | Format of the --output-file: csv, json, html or markdown. Picked by its extension by default, csv otherwise.
+    #[arg(long, value_name = "FORMAT", requires = "output_file", value_parser = parse_output_format, env = "XCREPORT_OUTPUT_FORMAT")]
+    pub output_format: Option<String>,
+    /// Optional | Also copy the reports, metadata and test reports of the run into this directory under stable names, for CI systems archiving one directory.
+    #[arg(long, env = "XCREPORT_ARTIFACTS_DIR")]
+    pub artifacts_dir: Option<PathBuf>,
+    /// Optional | How the result is printed: text for people, or json for a single JSON object on stdout, with the progress moved to stderr.
+    #[arg(long, value_name = "FORMAT", value_enum, default_value_t = PrintSummary::Text, env = "XCREPORT_PRINT_SUMMARY")]
+    pub print_summary: PrintSummary,
+    /// Optional | Render an HTML report with annotated source for uncovered lines.
+    #[arg(long, env = "XCREPORT_HTML")]
+    pub html: bool,
+    /// Optional | Write an Excel workbook with a summary sheet and the files of every squad on its own sheet.
+    #[arg(long, env = "XCREPORT_XLSX")]
+    pub xlsx: bool,
+    /// Optional | Yaml or json file mapping squads to their Backstage catalog entities, e.g. 'Payments: component:default/payments', for a backstage.json of coverage metrics per entity.
+    #[arg(long, value_parser = parse_tags_file, env = "XCREPORT_BACKSTAGE_COMPONENTS")]
+    pub backstage_components: Option<PathBuf>,
+    /// Optional | Add executed test counts per squad to the report.
+    #[arg(long, env = "XCREPORT_TEST_METRICS")]
+    pub test_metrics: bool,
+    /// Optional | File listing path patterns to exclude from the report, one per line.
+    #[arg(long, value_parser = parse_existing_file, env = "XCREPORT_IGNORE_FILE")]
+    pub ignore_file: Option<PathBuf>,
+    /// Optional | Don't read the sources of the report for `// xcreport:ignore-file` and `// xcreport:ignore-start` markers.
+    #[arg(long, env = "XCREPORT_NO_SOURCE_MARKERS")]
+    pub no_source_markers: bool,
+    /// Optional | Build targets to count, e.g. App or Networking.framework, all by default. Repeat or separate with commas.
+    #[arg(long, value_delimiter = ',', env = "XCREPORT_INCLUDE_TARGET")]
+    pub include_target: Vec<String>,
+    /// Optional | Build targets left out of the reports, e.g. test bundles or third-party frameworks. Repeat or separate with commas.
+    #[arg(long, value_delimiter = ',', env = "XCREPORT_EXCLUDE_TARGET")]
+    pub exclude_target: Vec<String>,
+    /// Optional | Leave the files with fewer executable lines out of the report.
+    #[arg(long, value_name = "N", env = "XCREPORT_MIN_EXECUTABLE_LINES")]
+    pub min_executable_lines: Option<u32>,
+    /// Optional | Glob of the file paths to report, e.g. 'Features/**', all by default. '*' stays within a directory, '**' spans several. Repeat or separate with commas.
+    #[arg(long, value_name = "GLOB", value_delimiter = ',', env = "XCREPORT_INCLUDE_PATH")]
+    pub include_path: Vec<String>,
+    /// Optional | Glob of the file paths left out of the report, e.g. 'Vendor/' or '*Mock*.swift'. Repeat or separate with commas.
+    #[arg(long, value_name = "GLOB", value_delimiter = ',', env = "XCREPORT_EXCLUDE_PATH")]
+    pub exclude_path: Vec<String>,
+    /// Optional | Code generators whose output stays in the report instead of being excluded.
+    #[arg(long, value_enum, value_delimiter = ',', env = "XCREPORT_KEEP_GENERATED")]
+    pub keep_generated: Vec<Generator>,
+    /// Optional | Coverage metrics of the summary report, the first one being checked by the gate.
+    #[arg(long, value_enum, default_value_t = Aggregate::Lines, env = "XCREPORT_AGGREGATE")]
+    pub aggregate: Aggregate,
+    /// Optional | Line coverage % from which a file counts as covered for the files metric.
+    #[arg(long, default_value_t = 80.0, env = "XCREPORT_FILE_THRESHOLD")]
+    pub file_threshold: f64,
+    /// Optional | List the N files with the most uncovered lines, overall and per squad.
+    #[arg(long, value_name = "N", env = "XCREPORT_TOP_UNCOVERED")]
+    pub top_uncovered: Option<u32>,
+    /// Optional | Leave the files with all lines covered out of full_report.csv, saving them to fully_covered.csv instead.
+    #[arg(long, env = "XCREPORT_HIDE_FULLY_COVERED")]
+    pub hide_fully_covered: bool,
+    /// Optional | Add the standard deviation of every squad's coverage % over the last N runs, this one included.
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(2..), env = "XCREPORT_VOLATILITY_RUNS")]
+    pub volatility_runs: Option<u32>,
+    /// Optional | Also save the line totals of every build target to targets.csv.
+    #[arg(long, env = "XCREPORT_TARGETS_REPORT")]
+    pub targets_report: bool,
+    /// Optional | Squad name of the files matching no squad.
+    #[arg(long, default_value = "N/A", env = "XCREPORT_UNOWNED_NAME")]
+    pub unowned_name: String,
+    /// Optional | Yaml or json file mapping squad names to the other names they go by in the input, tags and components files, e.g. 'Payments: [payments-team, PAY]'.
+    #[arg(long, value_parser = parse_tags_file, env = "XCREPORT_SQUAD_ALIASES")]
+    pub squad_aliases: Option<PathBuf>,
+    /// Optional | Match the path patterns of the input file regardless of case, as on the default macOS file system.
+    #[arg(long, env = "XCREPORT_IGNORE_PATH_CASE")]
+    pub ignore_path_case: bool,
+    /// Optional | Build machine path of the repository, stripped from file paths to make them repo-relative.
+    #[arg(long, env = "XCREPORT_REPO_ROOT")]
+    pub repo_root: Option<PathBuf>,
+    /// Optional | Yaml or json file mapping business domains to their squads, for a per-domain report.
+    #[arg(long, value_parser = parse_tags_file, env = "XCREPORT_TAGS_FILE")]
+    pub tags_file: Option<PathBuf>,
+    /// Optional | Also roll the coverage up per tribe, given by a Tribe column or tribes nesting squads in the input file, or per directory.
+    #[arg(long, value_enum, default_value_t = GroupBy::Squad, env = "XCREPORT_GROUP_BY")]
+    pub group_by: GroupBy,
+    /// Optional | Directory levels of the path prefixes with --group-by path-prefix, e.g. 2 for Sources/Payments.
+    #[arg(long, value_name = "N", default_value_t = 2, value_parser = clap::value_parser!(u32).range(1..), env = "XCREPORT_DEPTH")]
+    pub depth: u32,
+    /// Optional | File listing the changed file paths one per line, or a git ref to diff against, e.g. origin/main, for a patch coverage report.
+    #[arg(long, value_name = "FILE|BASE", env = "XCREPORT_CHANGED_FILES")]
+    pub changed_files: Option<String>,
+    /// Optional | Reuse coverage of targets missing from the test result whose sources are unchanged since the previous run.
+    #[arg(long, env = "XCREPORT_REUSE_UNCHANGED_TARGETS")]
+    pub reuse_unchanged_targets: bool,
+    /// Optional | Tool used to read the coverage out of the .xcresult file.
+    #[arg(long, value_enum, default_value_t = Backend::Xccov, env = "XCREPORT_BACKEND")]
+    pub backend: Backend,
+    /// Optional | Read the coverage out of result bundles again instead of reusing what earlier runs read from them.
+    #[arg(long, env = "XCREPORT_NO_XCRESULT_CACHE")]
+    pub no_xcresult_cache: bool,
+    #[command(flatten)]
+    pub input_options: InputOptions,
+    #[command(flatten)]
+    pub rounding: RoundingOptions,
+    #[command(flatten)]
+    pub gate: GateOptions,
+    #[command(flatten)]
+    pub github: GitHubOptions,
+    #[command(flatten)]
+    pub notify: NotifyOptions,
+    #[command(flatten)]
+    pub hooks: HookOptions
+}
+
+/// Where the squads are read from, for ownership kept in other tools.
+#[derive(Args)]
+pub struct InputOptions {
+    /// Optional | Read the squads from another source instead of the input file: 'backstage:<dir>' for the owners of the components in the catalog-info.yaml files under the directory.
+    #[arg(long, value_name = "SOURCE", value_parser = parse_ownership_source, conflicts_with = "input_file", env = "XCREPORT_OWNERSHIP_SOURCE")]
+    pub ownership_source: Option<OwnershipSource>,
+    /// Optional | Header of the squad column of a csv input file, e.g. 'Team' for an export of another tool.
+    #[arg(long, default_value = "Squad", env = "XCREPORT_SQUAD_COLUMN")]
+    pub squad_column: String,
+    /// Optional | Header of the path pattern column of a csv input file, e.g. 'Path'.
+    #[arg(long, default_value = "Filepath", env = "XCREPORT_PATH_COLUMN")]
+    pub path_column: String
+}
+
+#[derive(Clone)]
+pub enum OwnershipSource {
+    /// Directory of Backstage catalog-info files.
+    Backstage(PathBuf)
+}
+
+#[derive(Args, Clone, Copy, Serialize, Deserialize)]
+pub struct RoundingOptions {
+    /// Optional | Decimal places coverage percentages are rounded to.
+    #[arg(long, default_value_t = 2, env = "XCREPORT_PRECISION")]
+    pub precision: u32,
+    /// Optional | How coverage percentages exactly halfway between two values are rounded.
+    #[arg(long, value_enum, default_value_t = RoundingPolicy::HalfUp, env = "XCREPORT_ROUNDING")]
+    pub rounding: RoundingPolicy
+}
+
+impl Default for RoundingOptions {
+    fn default() -> Self {
+        RoundingOptions { precision: 2, rounding: RoundingPolicy::HalfUp }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Serialize, Deserialize)]
+pub enum RoundingPolicy {
+    /// Round halves away from zero, 12.345 becomes 12.35.
+    HalfUp,
+    /// Round halves to the nearest even digit, 12.345 becomes 12.34.
+    HalfEven
+}
+
+#[derive(ValueEnum, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Aggregate {
+    /// Coverage % of the squad's executable lines.
+    Lines,
+    /// Share of the squad's files whose line coverage is at least --file-threshold.
+    Files,
+    /// Both, the lines coverage % being checked by the gate.
+    Both
+}
+
+#[derive(ValueEnum, Clone, Copy, PartialEq)]
+pub enum PrintSummary {
+    /// The summary table and the paths of the reports.
+    Text,
+    /// A JSON object with the overall coverage, the numbers of every squad and the paths of the reports.
+    Json
+}
+
+#[derive(ValueEnum, Clone, Copy, PartialEq)]
+pub enum GroupBy {
+    /// The squad report only.
+    Squad,
+    /// A tribe report next to the squad report.
+    Tribe,
+    /// A report per directory next to the squad report, whatever squads the files belong to.
+    PathPrefix
+}
+
+#[derive(Args)]
+pub struct SimulatorOptions {
+    /// Optional | Name of the simulator to test on instead of a --destination, e.g. "iPhone 15".
+    #[arg(long, env = "XCREPORT_DEVICE")]
+    pub device: Option<String>,
+    /// Optional | OS version of the --device simulator, e.g. 17.2, the newest installed runtime by default.
+    #[arg(long, requires = "device", env = "XCREPORT_OS")]
+    pub os: Option<String>,
+    /// Optional | Boot the simulator before testing, shutting it down after the run unless it was already booted.
+    #[arg(long, env = "XCREPORT_BOOT_SIMULATOR")]
+    pub boot_simulator: bool
+}
+
+#[derive(Args)]
+pub struct GateOptions {
+    /// Optional | Fail when any squad's coverage % is below this value.
+    #[arg(long, env = "XCREPORT_MIN_COVERAGE")]
+    pub min_coverage: Option<f64>,
+    /// Optional | Date (YYYY-MM-DD) from which --min-coverage rises by --ramp-per-month every month.
+    #[arg(long, requires = "min_coverage", env = "XCREPORT_RAMP_START")]
+    pub ramp_start: Option<NaiveDate>,
+    /// Optional | Percentage points added to --min-coverage for every full month since --ramp-start.
+    #[arg(long, default_value_t = 0.0, env = "XCREPORT_RAMP_PER_MONTH")]
+    pub ramp_per_month: f64,
+    /// Optional | Highest coverage % the ramp rises to.
+    #[arg(long, env = "XCREPORT_RAMP_TARGET")]
+    pub ramp_target: Option<f64>,
+    /// Optional | Leave the files matching no squad out of the --min-coverage gate.
+    #[arg(long, env = "XCREPORT_GATE_SKIP_UNOWNED")]
+    pub gate_skip_unowned: bool,
+    /// Optional | Fail when the files matching no squad hold more than this % of all executable lines.
+    #[arg(long, env = "XCREPORT_MAX_UNOWNED_SHARE")]
+    pub max_unowned_share: Option<f64>,
+    /// Optional | Executable deciding whether the run passes, given the summary report as JSON on stdin. Repeat or separate with commas.
+    #[arg(long, value_delimiter = ',', env = "XCREPORT_GATE_COMMAND")]
+    pub gate_command: Vec<PathBuf>,
+    /// Optional | Fail when a squad's coverage % drops below its floor in this file, written by ratchet update, by more than the file's tolerance.
+    #[arg(long, env = "XCREPORT_RATCHET_FILE")]
+    pub ratchet_file: Option<PathBuf>,
+    /// Optional | Whether coverage below the gate fails the run or is only reported.
+    #[arg(long, value_enum, default_value_t = ThresholdMode::Fail, env = "XCREPORT_THRESHOLD_MODE")]
+    pub threshold_mode: ThresholdMode
+}
+
+#[derive(ValueEnum, Clone, Copy, PartialEq)]
+pub enum ThresholdMode {
+    /// Fail the run.
+    Fail,
+    /// Print the violations as warnings and highlight them in the HTML report, without failing the run.
+    Warn
+}
+
+#[derive(Args)]
+pub struct GitHubOptions {
+    /// Optional | Label the pull request with coverage:ok or coverage:regression depending on the gate outcome.
+    #[arg(long, requires = "min_coverage", env = "XCREPORT_GITHUB_LABELS")]
+    pub github_labels: bool,
+    /// Optional | Comment the coverage of every squad on the pull request, with a collapsed list of its files below --file-threshold.
+    #[arg(long, env = "XCREPORT_GITHUB_COMMENT")]
+    pub github_comment: bool,
+    /// Optional | Full report csv the comment shows the changes against, defaults to the previous run.
+    #[arg(long, requires = "github_comment", value_parser = parse_report_file, env = "XCREPORT_GITHUB_COMMENT_BASE")]
+    pub github_comment_base: Option<PathBuf>,
+    /// Optional | Create a GitHub check run annotating the uncovered lines of --changed-files, shown inline in the pull request diff.
+    #[arg(long, requires = "changed_files", env = "XCREPORT_GITHUB_CHECKS")]
+    pub github_checks: bool,
+    /// Optional | Commit the check run is created on, defaults to the head of the pull request in GitHub Actions or the checked out one.
+    #[arg(long, requires = "github_checks", env = "XCREPORT_GITHUB_SHA")]
+    pub github_sha: Option<String>,
+    /// Optional | Repository (owner/name) of the pull request, defaults to $GITHUB_REPOSITORY.
+    #[arg(long, env = "XCREPORT_GITHUB_REPOSITORY")]
+    pub github_repository: Option<String>,
+    /// Optional | Number of the pull request, defaults to the one in $GITHUB_REF.
+    #[arg(long, env = "XCREPORT_GITHUB_PR")]
+    pub github_pr: Option<u64>,
+    /// Optional | Token used to call the GitHub API, defaults to $GITHUB_TOKEN.
+    #[arg(long, hide_env_values = true, env = "XCREPORT_GITHUB_TOKEN")]
+    pub github_token: Option<String>,
+    /// Optional | Base URL of the GitHub API, for GitHub Enterprise.
+    #[arg(long, default_value = "https://api.github.com", env = "XCREPORT_GITHUB_API_URL")]
+    pub github_api_url: String
+}
+
+#[derive(Args)]
+pub struct NotifyOptions {
+    /// Optional | Slack or Teams incoming webhook URL to post the coverage of every run to.
+    #[arg(long, hide_env_values = true, env = "XCREPORT_NOTIFY_WEBHOOK")]
+    pub notify_webhook: Option<String>,
+    /// Optional | Chat service the webhook belongs to.
+    #[arg(long, value_enum, default_value_t = NotifyFormat::Slack, env = "XCREPORT_NOTIFY_FORMAT")]
+    pub notify_format: NotifyFormat,
+    /// Optional | Only notify when a squad's coverage moved by at least this many points since the previous run, or the gate failed.
+    #[arg(long, requires = "notify_webhook", env = "XCREPORT_NOTIFY_MIN_CHANGE")]
+    pub notify_min_change: Option<f64>
+}
+
+/// Shell commands run at points of the run, e.g. `on_report_generated = ["./scripts/archive.sh"]` in the config file.
+#[derive(Args)]
+pub struct HookOptions {
+    /// Optional | Shell command run once the reports are written, given their paths in XCREPORT_HOOK_* environment variables. Repeat, or put one command per line in the environment variable.
+    #[arg(long, value_name = "COMMAND", value_delimiter = '\n', env = "XCREPORT_ON_REPORT_GENERATED")]
+    pub on_report_generated: Vec<String>,
+    /// Optional | Shell command run when the coverage gate fails, given the paths of the reports and the violation in XCREPORT_HOOK_* environment variables. Repeat, or put one command per line in the environment variable.
+    #[arg(long, value_name = "COMMAND", value_delimiter = '\n', env = "XCREPORT_ON_THRESHOLD_VIOLATION")]
+    pub on_threshold_violation: Vec<String>
+}
+
+#[derive(Subcommand)]
+pub enum PublishTarget {
+    /// Comment the coverage of every squad on the merge request and print the overall coverage for GitLab's coverage regex
+    Gitlab(GitLabOptions)
+}
+
+#[derive(Args)]
+pub struct GitLabOptions {
+    /// Optional | Identifier of the run to publish, defaults to the latest run.
+    pub identifier: Option<String>,
+    /// Optional | Full report csv the note shows the changes against, defaults to the run before.
+    #[arg(long, value_parser = parse_report_file, env = "XCREPORT_GITLAB_BASE")]
+    pub base: Option<PathBuf>,
+    /// Optional | Line coverage % below which the files of a squad are listed in the note.
+    #[arg(long, default_value_t = 80.0, env = "XCREPORT_FILE_THRESHOLD")]
+    pub file_threshold: f64,
+    /// Optional | ID or path of the project, defaults to $CI_PROJECT_ID.
+    #[arg(long, env = "XCREPORT_GITLAB_PROJECT")]
+    pub gitlab_project: Option<String>,
+    /// Optional | IID of the merge request, defaults to $CI_MERGE_REQUEST_IID. Without one only the coverage is printed.
+    #[arg(long, env = "XCREPORT_GITLAB_MR")]
+    pub gitlab_mr: Option<u64>,
+    /// Optional | Access token with the api scope used to post the note, defaults to $GITLAB_TOKEN.
+    #[arg(long, hide_env_values = true, env = "XCREPORT_GITLAB_TOKEN")]
+    pub gitlab_token: Option<String>,
+    /// Optional | Base URL of the GitLab API, defaults to $CI_API_V4_URL or https://gitlab.com/api/v4.
+    #[arg(long, env = "XCREPORT_GITLAB_API_URL")]
+    pub gitlab_api_url: Option<String>,
+    #[command(flatten)]
+    pub rounding: RoundingOptions
+}
+
+#[derive(Subcommand)]
+pub enum CacheAction {
+    /// Archive the derived data directory under the cache key
+    Save(CacheOptions),
+    /// Replace the derived data directory with the archive of the cache key, if there is one
+    Restore(CacheOptions)
+}
+
+#[derive(Subcommand)]
+pub enum RatchetAction {
+    /// Raise the floors of the ratchet file to the coverage of a run, never lowering them
+    Update(RatchetOptions)
+}
+
+#[derive(Args)]
+pub struct RatchetOptions {
+    /// Optional | Identifier of the run whose coverage is ratcheted, defaults to the latest run.
+    pub identifier: Option<String>,
+    /// Optional | Ratchet file to create or update, to be committed with the project.
+    #[arg(long, default_value = "coverage-ratchet.toml", env = "XCREPORT_RATCHET_FILE")]
+    pub ratchet_file: PathBuf,
+    /// Optional | Percentage points a squad may drop below its floor before a run fails, kept in the file [default: 0.1 for a new file].
+    #[arg(long, env = "XCREPORT_RATCHET_TOLERANCE")]
+    pub tolerance: Option<f64>
+}
+
+#[derive(Args)]
+pub struct CacheOptions {
+    /// Optional | Cache key, defaults to a hash of the lockfiles of the project and the Xcode version.
+    #[arg(long, env = "XCREPORT_CACHE_KEY")]
+    pub key: Option<String>,
+    /// Optional | Project root searched for Podfile.lock, Package.resolved and Cartfile.resolved files for the default key.
+    #[arg(short, long, default_value = ".", value_parser = parse_dir, env = "XCREPORT_PROJECT_PATH")]
+    pub project_path: PathBuf,
+    /// Optional | Derived data directory to archive or replace, defaults to ~/.xcreport/derived_data.
+    #[arg(long, env = "XCREPORT_DERIVED_DATA_PATH")]
+    pub derived_data_path: Option<PathBuf>,
+    /// Optional | Directory the archives are kept in, e.g. one the CI caches between jobs, defaults to ~/.xcreport/derived_data_cache.
+    #[arg(long, env = "XCREPORT_CACHE_DIR")]
+    pub cache_dir: Option<PathBuf>
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+pub enum ShardPlanFormat {
+    /// The arguments of every shard on a line of its own.
+    Text,
+    /// An array of shards with their arguments and estimated duration.
+    Json
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+pub enum HistoryFormat {
+    /// `trends.csv`.
+    Csv,
+    /// `trends.parquet`.
+    Parquet
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+pub enum NotifyFormat {
+    Slack,
+    Teams
+}
+
+/// Code generators whose output is excluded from reports by default.
+#[derive(ValueEnum, Clone, Copy, PartialEq)]
+pub enum Generator {
+    /// R.swift resources, `R.generated.swift`.
+    RSwift,
+    /// SwiftGen templates, `*+Generated.swift` and files in `SwiftGen` directories.
+    Swiftgen,
+    /// Sourcery templates, `*.generated.swift`.
+    Sourcery,
+    /// Xcode asset and string catalog symbols.
+    XcodeSymbols,
+    /// Core Data NSManagedObject subclasses.
+    CoreData,
+    /// SwiftProtobuf and gRPC messages and services.
+    Protobuf,
+    /// Apollo GraphQL operations.
+    Apollo
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+pub enum UploadService {
+    Codecov,
+    Coveralls
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+pub enum Backend {
+    /// Read the coverage with `xccov view --report`.
+    Xccov,
+    /// Export the coverage report with `xcresulttool` first, for bundles `xccov` can't read directly.
+    Xcresulttool
+}
+
+fn parse_file(arg: &str, extensions: &[&str]) -> Result<PathBuf, XCReportError> {
     let path = PathBuf::from(arg);
     let path_exists = path.try_exists().unwrap_or_default();
 
@@ -61,7 +800,7 @@ fn parse_file(arg: &str, extension: &str) -> Result<PathBuf, XCReportError> {
         return Err(XCReportError::FilePath(FilePathError::NotFound))
     }
 
-    if path.extension() != Some(OsStr::new(extension)) {
+    if !extensions.iter().any(|extension| path.extension() == Some(OsStr::new(extension))) {
         let extension = path.extension()
             .unwrap_or(OsStr::new("N/A"))
             .to_os_string()
@@ -74,12 +813,83 @@ fn parse_file(arg: &str, extension: &str) -> Result<PathBuf, XCReportError> {
     Ok(path)
 }
 
-fn parse_xcresult_file(arg: &str) -> Result<PathBuf, XCReportError> {
-    parse_file(arg, "xcresult")
+fn parse_existing_file(arg: &str) -> Result<PathBuf, XCReportError> {
+    let path = PathBuf::from(arg);
+
+    if !path.try_exists().unwrap_or_default() {
+        return Err(XCReportError::FilePath(FilePathError::NotFound))
+    }
+
+    Ok(path)
+}
+
+fn parse_dir(arg: &str) -> Result<PathBuf, XCReportError> {
+    let path = PathBuf::from(arg);
+
+    if !path.is_dir() {
+        return Err(XCReportError::DirPath(DirPathError::NotFound))
+    }
+
+    Ok(path)
+}
+
+fn parse_file_name(arg: &str) -> Result<String, XCReportError> {
+    let path = Path::new(arg);
+
+    if path.file_name() != Some(OsStr::new(arg)) {
+        return Err(XCReportError::FilePath(FilePathError::InvalidName { name: arg.to_string() }))
+    }
+
+    Ok(arg.to_string())
+}
+
+fn parse_xcresult_input(arg: &str) -> Result<PathBuf, XCReportError> {
+    let path = PathBuf::from(arg);
+
+    if path.is_dir() && path.extension() != Some(OsStr::new("xcresult")) {
+        return Ok(path)
+    }
+
+    parse_file(arg, &["xcresult"])
+}
+
+fn parse_build_setting(arg: &str) -> Result<(String, String), XCReportError> {
+    match arg.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => Ok((key.trim().to_string(), value.to_string())),
+        _ => Err(XCReportError::InvalidBuildSetting { setting: arg.to_string() })
+    }
 }
 
 fn parse_input_file(arg: &str) -> Result<PathBuf, XCReportError> {
-    parse_file(arg, "csv")
+    parse_file(arg, &["csv", "yaml", "yml", "json"])
+}
+
+fn parse_ownership_source(arg: &str) -> Result<OwnershipSource, XCReportError> {
+    match arg.split_once(':') {
+        Some(("backstage", dir)) => Ok(OwnershipSource::Backstage(parse_dir(dir)?)),
+        _ => Err(XCReportError::UnknownOwnershipSource { value: arg.to_string() })
+    }
+}
+
+fn parse_tags_file(arg: &str) -> Result<PathBuf, XCReportError> {
+    parse_file(arg, &["yaml", "yml", "json"])
+}
+
+fn parse_teams_file(arg: &str) -> Result<PathBuf, XCReportError> {
+    parse_file(arg, &["yaml", "yml", "json"])
+}
+
+fn parse_report_file(arg: &str) -> Result<PathBuf, XCReportError> {
+    parse_file(arg, &["csv"])
+}
+
+fn parse_output_format(arg: &str) -> Result<String, XCReportError> {
+    let registry = render::Registry::default();
+
+    match registry.get(arg) {
+        Some(renderer) => Ok(renderer.name().to_string()),
+        None => Err(XCReportError::UnknownFormat { name: arg.to_string(), formats: registry.names().join(", ") })
+    }
 }
 
 fn parse_output_file(arg: &str) -> Result<PathBuf, XCReportError> {
@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use polars::prelude::*;
+
+use crate::cli::RoundingOptions;
+use crate::df;
+use crate::err::XCReportError;
+
+/// Hidden first line of the comment, to find and update it on later runs.
+pub const COMMENT_MARKER: &str = "<!-- xcreport coverage -->";
+
+/// Files listed per squad, keeping the comment well below GitHub's 65536 character limit.
+const MAX_FILES_PER_SQUAD: usize = 50;
+
+/// A file of the full report below the file threshold.
+struct UncoveredFile {
+    path: String,
+    coverage: f64,
+    change: Option<f64>
+}
+
+/// Renders the pull request comment: a table of the squads followed by a collapsed section per squad
+/// listing its files below `file_threshold`, with the changes since `base` when given.
+pub fn coverage_comment(
+    head: &DataFrame,
+    base: Option<&DataFrame>,
+    file_threshold: f64,
+    rounding: &RoundingOptions
+) -> Result<String, XCReportError> {
+    let precision = rounding.precision as usize;
+
+    let head_squads = squad_coverages(head, rounding)?;
+    let base_squads: HashMap<String, f64> = match base {
+        Some(base) => squad_coverages(base, rounding)?.into_iter().collect(),
+        None => HashMap::new()
+    };
+    let base_files: HashMap<String, f64> = match base {
+        Some(base) => file_coverages(base)?.into_iter().map(|(path, _, coverage)| (path, coverage)).collect(),
+        None => HashMap::new()
+    };
+
+    let mut uncovered_files: HashMap<String, Vec<UncoveredFile>> = HashMap::new();
+    for (path, squad, coverage) in file_coverages(head)? {
+        if coverage >= file_threshold {
+            continue
+        }
+
+        let change = base_files.get(&path).map(|base_coverage| df::round_value(coverage - base_coverage, rounding));
+        let coverage = df::round_value(coverage, rounding);
+        uncovered_files.entry(squad).or_default().push(UncoveredFile { path, coverage, change });
+    }
+
+    let mut comment = format!("{}\n### Coverage by squad\n\n", COMMENT_MARKER);
+    let _ = writeln!(comment, "| Squad | Coverage % | Change |\n|---|---:|---:|");
+    let squad_changes: Vec<String> = head_squads
+        .iter()
+        .map(|(squad, coverage)| {
+            let change = base.map(|_| base_squads.get(squad).map(|base_coverage| df::round_value(coverage - base_coverage, rounding)));
+            format_change(change, precision)
+        })
+        .collect();
+
+    for ((squad, coverage), change) in head_squads.iter().zip(&squad_changes) {
+        let _ = writeln!(comment, "| {} | {:.*}% | {} |", escape(squad), precision, coverage, change);
+    }
+
+    for ((squad, coverage), change) in head_squads.iter().zip(&squad_changes) {
+        let Some(files) = uncovered_files.get_mut(squad) else {
+            continue
+        };
+        files.sort_by(|a, b| a.coverage.total_cmp(&b.coverage).then_with(|| a.path.cmp(&b.path)));
+
+        let change = if change.is_empty() { String::new() } else { format!(" ({})", change) };
+        let _ = write!(
+            comment,
+            "\n<details><summary><b>{}</b> {:.*}%{}, {} file(s) below {}%</summary>\n\n| File | Coverage % | Change |\n|---|---:|---:|\n",
+            escape(squad), precision, coverage, change, files.len(), file_threshold
+        );
+
+        for file in files.iter().take(MAX_FILES_PER_SQUAD) {
+            let change = format_change(base.map(|_| file.change), precision);
+            let _ = writeln!(comment, "| `{}` | {:.*}% | {} |", file.path.replace('`', "'"), precision, file.coverage, change);
+        }
+        if files.len() > MAX_FILES_PER_SQUAD {
+            let _ = writeln!(comment, "\n…and {} more, see the full report.", files.len() - MAX_FILES_PER_SQUAD);
+        }
+
+        comment.push_str("\n</details>\n");
+    }
+
+    Ok(comment)
+}
+
+/// `+1.20` for a change, `new` for something missing from the base and nothing without a base.
+fn format_change(change: Option<Option<f64>>, precision: usize) -> String {
+    match change {
+        Some(Some(change)) => format!("{:+.*}", precision, change),
+        Some(None) => String::from("new"),
+        None => String::new()
+    }
+}
+
+/// Coverage % of every squad, in the order of the summary report.
+fn squad_coverages(full_report: &DataFrame, rounding: &RoundingOptions) -> Result<Vec<(String, f64)>, XCReportError> {
+    let summary = df::process_report(full_report, rounding)?;
+    let squads = summary.column("Squad")
+        .and_then(|c| c.str())
+        .map_err(XCReportError::Polars)?;
+    let coverages = summary.column("Coverage %")
+        .and_then(|c| c.f64())
+        .map_err(XCReportError::Polars)?;
+
+    let coverages = squads
+        .into_iter()
+        .zip(coverages)
+        .filter_map(|(squad, coverage)| Some((squad?.to_string(), coverage?)))
+        .collect();
+
+    Ok(coverages)
+}
+
+/// Path, squad and line coverage % of every file.
+fn file_coverages(full_report: &DataFrame) -> Result<Vec<(String, String, f64)>, XCReportError> {
+    let paths = full_report.column("Filepath")
+        .and_then(|c| c.str())
+        .map_err(XCReportError::Polars)?;
+    let squads = full_report.column("Squad")
+        .and_then(|c| c.str())
+        .map_err(XCReportError::Polars)?;
+    let coverages = full_report.column("Line Coverage")
+        .and_then(|c| c.cast(&DataType::Float64))
+        .map_err(XCReportError::Polars)?;
+    let coverages = coverages.f64().map_err(XCReportError::Polars)?;
+
+    let files = paths
+        .into_iter()
+        .zip(squads)
+        .zip(coverages)
+        .filter_map(|((path, squad), coverage)| Some((path?.to_string(), squad?.to_string(), coverage? * 100.0)))
+        .collect();
+
+    Ok(files)
+}
+
+/// Keeps squad names from breaking the markdown table.
+fn escape(text: &str) -> String {
+    text.replace('|', "\\|").replace('<', "&lt;")
+}
@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use clap::{ArgAction, ArgMatches, Command, CommandFactory};
+use clap::parser::ValueSource;
+use toml::{Table, Value};
+
+use crate::cli::Cli;
+use crate::err::{FilePathError, XCReportError};
+
+const DEFAULT_CONFIG_FILE: &str = "xcreport.toml";
+const CONFIG_ENV: &str = "XCREPORT_CONFIG";
+/// Table of the config file with the environment of xcodebuild, xcrun and the other child processes.
+const ENV_TABLE: &str = "env";
+
+/// Finds the config file from `--config`, `XCREPORT_CONFIG` or `./xcreport.toml`, in that order.
+///
+/// This runs before the command line is parsed, since the config provides defaults for it.
+pub fn find_config_file() -> Option<PathBuf> {
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            return args.next().map(PathBuf::from)
+        }
+
+        if let Some(path) = arg.strip_prefix("--config=") {
+            return Some(PathBuf::from(path))
+        }
+    }
+
+    if let Ok(path) = std::env::var(CONFIG_ENV) {
+        return Some(PathBuf::from(path))
+    }
+
+    let default_path = PathBuf::from(DEFAULT_CONFIG_FILE);
+    default_path.is_file().then_some(default_path)
+}
+
+pub fn load_config(path: &PathBuf) -> Result<Table, XCReportError> {
+    if !path.try_exists().unwrap_or_default() {
+        return Err(XCReportError::FilePath(FilePathError::NotFound))
+    }
+
+    let contents = std::fs::read_to_string(path)
+        .map_err(XCReportError::FileIO)?;
+
+    contents.parse::<Table>()
+        .map_err(XCReportError::Config)
+}
+
+/// The command line with the values of the config file added for the options it leaves unset. The
+/// top-level keys apply to every command, overridden by the keys of the table of the command run, e.g.
+/// `[export-codeowners]`, and of its subcommand, e.g. `[publish.gitlab]`. Options given on the command
+/// line or in their `XCREPORT_*` environment variable keep their value.
+///
+/// Only the command being parsed gets the values, so they don't leak into the environment of the
+/// hooks and the other child processes.
+pub fn config_args(config: &Table, args: Vec<OsString>) -> Vec<OsString> {
+    let command = Cli::command();
+    // Help, version and unknown arguments are left for the parse of the full command line to report.
+    let Ok(matches) = command.clone().ignore_errors(true).try_get_matches_from(&args) else {
+        return args
+    };
+
+    let mut values = table_values(config);
+    let mut config_args = option_args(&command, &matches, &values);
+
+    let (mut leaf_command, mut leaf_matches, mut table) = (&command, &matches, Some(config));
+    let mut has_subcommand = false;
+    while let Some((name, sub_matches)) = leaf_matches.subcommand() {
+        let Some(sub_command) = leaf_command.find_subcommand(name) else {
+            break
+        };
+
+        table = table.and_then(|table| command_table(table, name));
+        if let Some(table) = table {
+            values.extend(table_values(table));
+        }
+        (leaf_command, leaf_matches, has_subcommand) = (sub_command, sub_matches, true);
+    }
+
+    // The root only has the global options, which the leaf command accepts too.
+    if has_subcommand {
+        config_args.extend(option_args(leaf_command, leaf_matches, &values));
+    }
+
+    let mut args = args;
+    let end = args.iter().position(|arg| arg == "--").unwrap_or(args.len());
+    args.splice(end..end, config_args);
+    args
+}
+
+/// The table of the config file for a command, by its name, e.g. `[export-codeowners]` or `[export_codeowners]`.
+fn command_table<'a>(table: &'a Table, name: &str) -> Option<&'a Table> {
+    match table.get(name).or_else(|| table.get(&name.replace('-', "_"))) {
+        Some(Value::Table(table)) => Some(table),
+        _ => None
+    }
+}
+
+/// The option values of a table by their snake case key, leaving out the nested tables.
+fn table_values(table: &Table) -> HashMap<String, &Value> {
+    table.iter()
+        .filter(|(_, value)| !value.is_table())
+        .map(|(key, value)| (key.replace('-', "_"), value))
+        .collect()
+}
+
+/// The `--option=value` arguments of the options of a command that are unset on the command line and in the environment.
+fn option_args(command: &Command, matches: &ArgMatches, values: &HashMap<String, &Value>) -> Vec<OsString> {
+    command.get_arguments()
+        .filter(|arg| !matches!(matches.value_source(arg.get_id().as_str()), Some(ValueSource::CommandLine | ValueSource::EnvVariable)))
+        .filter_map(|arg| Some((arg.get_long()?, values.get(arg.get_id().as_str())?, arg.get_action())))
+        .flat_map(|(long, value, action)| option_values(long, value, action))
+        .collect()
+}
+
+/// The arguments setting an option to a config value: one per item of an array, and the bare flag for `true`.
+fn option_values(long: &str, value: &Value, action: &ArgAction) -> Vec<OsString> {
+    match (value, action) {
+        (Value::Boolean(set), ArgAction::SetTrue) => set.then(|| OsString::from(format!("--{}", long))).into_iter().collect(),
+        (Value::Array(values), _) => values.iter().flat_map(|value| option_values(long, value, action)).collect(),
+        (value, _) => env_value(value).map(|value| OsString::from(format!("--{}={}", long, value))).into_iter().collect()
+    }
+}
+
+/// Sets the variables of the `[env]` table, e.g. `DEVELOPER_DIR` to pick the Xcode, on xcreport's
+/// own environment so every child process inherits them. Variables already set are kept.
+pub fn apply_config_env(config: &Table) {
+    let Some(Value::Table(env)) = config.get(ENV_TABLE) else {
+        return
+    };
+
+    for (name, value) in env {
+        if std::env::var_os(name).is_some() {
+            continue
+        }
+
+        if let Some(env_value) = env_value(value) {
+            std::env::set_var(name, env_value);
+        }
+    }
+}
+
+/// The value as an environment variable, with the items of an array joined by commas.
+fn env_value(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Integer(i) => Some(i.to_string()),
+        Value::Float(f) => Some(f.to_string()),
+        Value::Boolean(b) => Some(b.to_string()),
+        Value::Array(values) => {
+            let values: Option<Vec<String>> = values.iter().map(env_value).collect();
+            values.map(|values| values.join(","))
+        },
+        Value::Datetime(d) => Some(d.to_string()),
+        Value::Table(_) => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::cli::Commands;
+
+    fn parse(config: &Table, args: &[&str]) -> Cli {
+        let args = args.iter().map(OsString::from).collect();
+        Cli::try_parse_from(config_args(config, args)).unwrap()
+    }
+
+    #[test]
+    fn test_config_args_give_each_command_the_values_of_its_table() {
+        let dir = std::env::temp_dir().join(format!("xcreport-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("squads.yaml"), "").unwrap();
+        std::fs::write(dir.join("teams.yaml"), "").unwrap();
+
+        let config: Table = format!(r#"
+            input_file = "{dir}/squads.yaml"
+
+            [run]
+            project_path = "{dir}"
+            scheme = ["App", "Core"]
+            destination = "platform=iOS Simulator,name=iPhone 15"
+            output_file = "{dir}/report.csv"
+            on_report_generated = ["echo report, generated", "echo done"]
+
+            [export-codeowners]
+            teams_file = "{dir}/teams.yaml"
+            output_file = "{dir}/CODEOWNERS"
+        "#, dir = dir.display()).parse().unwrap();
+
+        let cli = parse(&config, &["xcreport", "run", "--scheme", "Feed"]);
+        let Commands::Run { input_file, scheme, report_options, .. } = cli.subcommand() else {
+            panic!("expected the run command")
+        };
+        assert_eq!(input_file, &vec![dir.join("squads.yaml")]);
+        assert_eq!(scheme, &vec![String::from("Feed")]);
+        assert_eq!(report_options.output_file.as_deref(), Some(dir.join("report.csv").as_path()));
+        assert_eq!(report_options.hooks.on_report_generated, vec!["echo report, generated", "echo done"]);
+
+        // The CODEOWNERS file already exists, which export-codeowners replaces but run refuses to.
+        std::fs::write(dir.join("CODEOWNERS"), "").unwrap();
+        let cli = parse(&config, &["xcreport", "export-codeowners"]);
+        let Commands::ExportCodeowners { input_file, output_file, .. } = cli.subcommand() else {
+            panic!("expected the export-codeowners command")
+        };
+        assert_eq!(input_file, &vec![dir.join("squads.yaml")]);
+        assert_eq!(output_file.as_deref(), Some(dir.join("CODEOWNERS").as_path()));
+
+        assert!(std::env::var_os("XCREPORT_OUTPUT_FILE").is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
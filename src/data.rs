@@ -1,55 +1,348 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Default)]
 pub struct XCodeBuildReport {
     targets: Vec<Target>
 }
 
 impl XCodeBuildReport {
-    pub fn get_all_files(&self) -> Vec<&TargetFile> {
+    /// Files of the targets the filter selects.
+    pub fn get_all_files(&self, filter: &TargetFilter) -> Vec<&TargetFile> {
         self.targets
             .iter()
+            .filter(|t| filter.selects(&t.name))
             .flat_map(|t| &t.files)
             .collect()
     }
+
+    /// Combines reports of several result bundles, keeping the best covered entry of every file until
+    /// `merge_line_coverage` counts the lines any bundle covered.
+    pub fn merge(reports: Vec<XCodeBuildReport>) -> XCodeBuildReport {
+        let mut targets: Vec<Target> = vec![];
+
+        for target in reports.into_iter().flat_map(|r| r.targets) {
+            match targets.iter_mut().find(|t| t.name == target.name) {
+                Some(existing) => existing.merge(target),
+                None => targets.push(target)
+            }
+        }
+
+        XCodeBuildReport { targets }
+    }
+
+    pub fn targets(&self) -> &[Target] {
+        &self.targets
+    }
+
+    pub fn has_target(&self, name: &str) -> bool {
+        self.targets.iter().any(|t| t.name == name)
+    }
+
+    pub fn push_target(&mut self, target: Target) {
+        self.targets.push(target)
+    }
+
+    /// Files of every target, whose totals are then summed over the files instead of taken from xccov.
+    pub fn files_mut(&mut self) -> impl Iterator<Item = &mut TargetFile> {
+        self.targets.iter_mut().flat_map(|t| {
+            t.covered_lines = None;
+            t.executable_lines = None;
+            &mut t.files
+        })
+    }
+
+    /// Recounts the lines of every file from the line coverage of all result bundles, a line being covered
+    /// when any bundle hit it. Files missing from the line coverage keep their counts.
+    pub fn merge_line_coverage(&mut self, archives: Vec<HashMap<String, Vec<LineCoverage>>>) {
+        let mut file_bundles: HashMap<String, Vec<Vec<LineCoverage>>> = HashMap::new();
+        for (path, lines) in archives.into_iter().flatten() {
+            file_bundles.entry(path).or_default().push(lines);
+        }
+        let file_lines: HashMap<String, Vec<LineCoverage>> = file_bundles
+            .into_iter()
+            .map(|(path, bundles)| (path, LineCoverage::merge(bundles)))
+            .collect();
+
+        // A file compiled into several targets, e.g. an app and its extension, is counted in each.
+        for file in self.files_mut() {
+            if let Some(lines) = file_lines.get(&file.path) {
+                file.count_lines(lines);
+            }
+        }
+    }
+
+    /// Makes the paths of files under `root` relative to it, leaving other paths untouched.
+    pub fn relativize_paths(&mut self, root: &Path) {
+        self.targets
+            .iter_mut()
+            .flat_map(|t| &mut t.files)
+            .for_each(|file| file.relativize_path(root));
+    }
 }
 
-#[derive(Deserialize, Debug)]
+/// Build targets whose files count towards the reports, by name with or without the extension,
+/// e.g. `Networking.framework` or `Networking`.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct TargetFilter {
+    /// Only these targets when not empty.
+    pub include: Vec<String>,
+    pub exclude: Vec<String>
+}
+
+impl TargetFilter {
+    pub fn selects(&self, target_name: &str) -> bool {
+        let matches = |name: &String| names_target(name, target_name);
+
+        (self.include.is_empty() || self.include.iter().any(matches)) && !self.exclude.iter().any(matches)
+    }
+
+    /// Names of the filter matching none of the targets, most likely misspelled.
+    pub fn unknown_names<'a>(&'a self, report: &XCodeBuildReport) -> Vec<&'a String> {
+        self.include
+            .iter()
+            .chain(&self.exclude)
+            .filter(|name| !report.targets.iter().any(|t| names_target(name, &t.name)))
+            .collect()
+    }
+}
+
+fn names_target(name: &str, target_name: &str) -> bool {
+    name == target_name || Path::new(target_name).file_stem().is_some_and(|stem| stem == name)
+}
+
+/// Files counting towards the reports, by their size and by path globs.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct FileFilter {
+    pub min_executable_lines: Option<u32>,
+    /// Only paths matching one of these globs when not empty.
+    pub include_paths: Vec<String>,
+    pub exclude_paths: Vec<String>
+}
+
+impl FileFilter {
+    pub fn is_empty(&self) -> bool {
+        self.min_executable_lines.is_none() && self.include_paths.is_empty() && self.exclude_paths.is_empty()
+    }
+
+    pub fn keeps(&self, path: &str, executable_lines: u64) -> bool {
+        let matches = |glob: &String| glob_match(glob, path);
+
+        self.min_executable_lines.is_none_or(|min| executable_lines >= u64::from(min))
+            && (self.include_paths.is_empty() || self.include_paths.iter().any(matches))
+            && !self.exclude_paths.iter().any(matches)
+    }
+}
+
+/// Whether the glob matches the path: `*` and `?` within a path component, `**` across components.
+/// Globs match at any directory level unless they start with `/`, and a trailing `/` matches everything below.
+pub fn glob_match(glob: &str, path: &str) -> bool {
+    let mut glob = match glob.strip_prefix('/') {
+        Some(anchored) => anchored.to_string(),
+        None => format!("**/{}", glob)
+    };
+    if glob.ends_with('/') {
+        glob.push_str("**");
+    }
+
+    glob_match_bytes(glob.as_bytes(), path.trim_start_matches('/').as_bytes())
+}
+
+fn glob_match_bytes(glob: &[u8], path: &[u8]) -> bool {
+    match glob {
+        [] => path.is_empty(),
+        [b'*', b'*', b'/', rest @ ..] => {
+            glob_match_bytes(rest, path)
+                || (0..path.len()).any(|i| path[i] == b'/' && glob_match_bytes(rest, &path[i + 1..]))
+        },
+        [b'*', b'*', rest @ ..] => (0..=path.len()).any(|i| glob_match_bytes(rest, &path[i..])),
+        [b'*', rest @ ..] => {
+            (0..=path.len())
+                .take_while(|&i| !path[..i].contains(&b'/'))
+                .any(|i| glob_match_bytes(rest, &path[i..]))
+        },
+        [b'?', rest @ ..] => path.first().is_some_and(|&c| c != b'/') && glob_match_bytes(rest, &path[1..]),
+        [c, rest @ ..] => path.first() == Some(c) && glob_match_bytes(rest, &path[1..])
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Target {
+    name: String,
+    #[serde(rename(deserialize = "coveredLines"), alias = "covered_lines", default)]
+    covered_lines: Option<usize>,
+    #[serde(rename(deserialize = "executableLines"), alias = "executable_lines", default)]
+    executable_lines: Option<usize>,
+    #[serde(default)]
     files: Vec<TargetFile>
 }
 
+impl Target {
+    pub fn new(name: String, files: Vec<TargetFile>) -> Self {
+        Target { name, covered_lines: None, executable_lines: None, files }
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn files(&self) -> &[TargetFile] {
+        &self.files
+    }
+
+    /// Covered lines of the target as reported by xccov, summed over its files once merged with
+    /// the target of another bundle.
+    pub fn covered_lines(&self) -> usize {
+        self.covered_lines.unwrap_or_else(|| self.files.iter().map(|f| f.covered_lines).sum())
+    }
+
+    pub fn executable_lines(&self) -> usize {
+        self.executable_lines.unwrap_or_else(|| self.files.iter().map(|f| f.executable_lines).sum())
+    }
+
+    fn merge(&mut self, other: Target) {
+        let mut indices: HashMap<String, usize> = self.files
+            .iter()
+            .enumerate()
+            .map(|(index, f)| (f.path.clone(), index))
+            .collect();
+
+        for file in other.files {
+            match indices.get(&file.path) {
+                Some(&index) if self.files[index].covered_lines < file.covered_lines => self.files[index] = file,
+                Some(_) => {},
+                None => {
+                    indices.insert(file.path.clone(), self.files.len());
+                    self.files.push(file);
+                }
+            }
+        }
+
+        self.covered_lines = None;
+        self.executable_lines = None;
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(from = "ReportedFile")]
 pub struct TargetFile {
     path: String,
-    #[serde(rename(deserialize = "coveredLines"))]
     covered_lines: usize,
-    #[serde(rename(deserialize = "executableLines"))]
     executable_lines: usize,
-    #[serde(rename(deserialize = "lineCoverage"))]
     line_coverage: f32,
-    squad_name: Option<String>
+    squad_name: Option<String>,
+    stale: bool
+}
+
+/// A file as xccov reports it, or as xcreport saved it.
+#[derive(Deserialize)]
+struct ReportedFile {
+    path: String,
+    #[serde(rename = "coveredLines", alias = "covered_lines")]
+    covered_lines: usize,
+    #[serde(rename = "executableLines", alias = "executable_lines")]
+    executable_lines: usize,
+    /// Left out by the xccov of newer Xcode versions for some bundles, computed from the line counts then.
+    #[serde(rename = "lineCoverage", alias = "line_coverage", default)]
+    line_coverage: Option<f32>,
+    #[serde(default)]
+    squad_name: Option<String>,
+    #[serde(default)]
+    stale: bool
+}
+
+impl From<ReportedFile> for TargetFile {
+    fn from(file: ReportedFile) -> Self {
+        let mut target_file = TargetFile::new(file.path, file.covered_lines, file.executable_lines);
+        if let Some(line_coverage) = file.line_coverage {
+            target_file.line_coverage = line_coverage;
+        }
+        target_file.squad_name = file.squad_name;
+        target_file.stale = file.stale;
+
+        target_file
+    }
 }
 
 impl TargetFile {
+    pub fn new(path: String, covered_lines: usize, executable_lines: usize) -> Self {
+        let line_coverage = if executable_lines == 0 { 0.0 } else { covered_lines as f32 / executable_lines as f32 };
+
+        TargetFile { path, covered_lines, executable_lines, line_coverage, squad_name: None, stale: false }
+    }
 
     pub fn file_path(&self) -> &String {
         &self.path
     }
+
+    pub fn covered_lines(&self) -> usize {
+        self.covered_lines
+    }
+
+    pub fn executable_lines(&self) -> usize {
+        self.executable_lines
+    }
+
+    pub fn squad_name(&self) -> Option<&String> {
+        self.squad_name.as_ref()
+    }
+
     pub fn set_squad_name(&mut self, name: String) {
         self.squad_name = Some(name)
     }
+
+    /// Sets the counts of the file to the ones of its line coverage.
+    fn count_lines(&mut self, lines: &[LineCoverage]) {
+        let (executable_lines, covered_lines) = lines
+            .iter()
+            .filter_map(LineCoverage::hits)
+            .fold((0, 0), |(executable, covered), hits| (executable + 1, covered + usize::from(hits > 0)));
+
+        self.executable_lines = executable_lines;
+        self.covered_lines = covered_lines;
+        self.line_coverage = if executable_lines == 0 { 0.0 } else { covered_lines as f32 / executable_lines as f32 };
+    }
+
+    /// Leaves lines out of the counts of the file, e.g. lines in ignored regions.
+    pub fn exclude_lines(&mut self, executable_lines: usize, covered_lines: usize) {
+        self.executable_lines = self.executable_lines.saturating_sub(executable_lines);
+        self.covered_lines = self.covered_lines.saturating_sub(covered_lines).min(self.executable_lines);
+        self.line_coverage = if self.executable_lines == 0 {
+            0.0
+        } else {
+            self.covered_lines as f32 / self.executable_lines as f32
+        };
+    }
+
+    pub fn relativize_path(&mut self, root: &Path) {
+        if let Ok(relative_path) = Path::new(&self.path).strip_prefix(root) {
+            self.path = relative_path.to_string_lossy().into_owned();
+        }
+    }
+
+    /// Marks the coverage as reused from a previous run instead of measured in this one.
+    pub fn mark_stale(&mut self) {
+        self.stale = true
+    }
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct SquadData {
-    #[serde(rename(deserialize = "Squad"))]
+    #[serde(rename(deserialize = "Squad"), alias = "squad_name")]
     squad_name: String,
-    #[serde(rename(deserialize = "Filepath"))]
-    file_path: String
+    #[serde(rename(deserialize = "Filepath"), alias = "file_path")]
+    file_path: String,
+    #[serde(rename(deserialize = "Tribe"), alias = "tribe", default)]
+    tribe: Option<String>
 }
 
 impl SquadData {
+    pub fn new(squad_name: String, file_path: String, tribe: Option<String>) -> Self {
+        SquadData { squad_name, file_path, tribe }
+    }
+
     pub fn file_name(&self) -> &String {
         &self.file_path
     }
@@ -57,4 +350,265 @@ impl SquadData {
     pub fn squad_name(&self) -> &String {
         &self.squad_name
     }
-}
\ No newline at end of file
+
+    pub fn set_squad_name(&mut self, name: String) {
+        self.squad_name = name
+    }
+
+    pub fn tribe(&self) -> Option<&String> {
+        self.tribe.as_ref()
+    }
+}
+
+/// The path patterns of a squad in a yaml or json input file, or the squads of a tribe nesting them.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum Ownership {
+    Patterns(Vec<String>),
+    Tribe(IndexMap<String, Vec<String>>)
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct LineCoverage {
+    line: usize,
+    #[serde(rename(deserialize = "isExecutable"))]
+    is_executable: bool,
+    #[serde(rename(deserialize = "executionCount"))]
+    execution_count: Option<usize>
+}
+
+impl LineCoverage {
+    /// Combines line coverage of the same file from several result bundles, keeping the max hits per line.
+    pub fn merge(bundles: Vec<Vec<LineCoverage>>) -> Vec<LineCoverage> {
+        let mut merged: BTreeMap<usize, LineCoverage> = BTreeMap::new();
+
+        for line in bundles.into_iter().flatten() {
+            match merged.get_mut(&line.line) {
+                Some(existing) => {
+                    existing.is_executable |= line.is_executable;
+                    existing.execution_count = existing.execution_count.max(line.execution_count);
+                },
+                None => {
+                    merged.insert(line.line, line);
+                }
+            }
+        }
+
+        merged.into_values().collect()
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Hit count of the line, `None` when the line is not executable.
+    pub fn hits(&self) -> Option<usize> {
+        if self.is_executable {
+            Some(self.execution_count.unwrap_or_default())
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TestResults {
+    #[serde(rename(deserialize = "testNodes"))]
+    test_nodes: Vec<TestNode>
+}
+
+impl TestResults {
+    /// Number of executed test cases per test bundle.
+    pub fn tests_per_target(&self) -> Vec<(&String, usize)> {
+        let mut targets: Vec<&TestNode> = vec![];
+        self.test_nodes
+            .iter()
+            .for_each(|node| node.collect_bundles(&mut targets));
+
+        targets
+            .into_iter()
+            .map(|target| (&target.name, target.test_case_count()))
+            .collect()
+    }
+
+    /// Names of the test bundles, with whether they hold UI tests rather than unit tests.
+    pub fn test_bundles(&self) -> Vec<(&String, bool)> {
+        let mut bundles: Vec<&TestNode> = vec![];
+        self.test_nodes
+            .iter()
+            .for_each(|node| node.collect_bundles(&mut bundles));
+
+        bundles
+            .into_iter()
+            .map(|bundle| (&bundle.name, bundle.node_type.starts_with("UI")))
+            .collect()
+    }
+
+    /// Failed test cases, in the order of the result bundle.
+    pub fn failed_tests(&self) -> Vec<FailedTest<'_>> {
+        let mut bundles: Vec<&TestNode> = vec![];
+        self.test_nodes
+            .iter()
+            .for_each(|node| node.collect_bundles(&mut bundles));
+
+        let mut failed_tests: Vec<FailedTest> = vec![];
+        for bundle in bundles {
+            bundle.collect_failures(&bundle.name, &bundle.name, &mut failed_tests);
+        }
+
+        failed_tests
+    }
+}
+
+impl TestResults {
+    /// Summed test case durations in seconds per `Bundle/Suite` identifier, as `-only-testing` takes them.
+    pub fn suite_durations(&self) -> Vec<(String, f64)> {
+        let mut bundles: Vec<&TestNode> = vec![];
+        self.test_nodes
+            .iter()
+            .for_each(|node| node.collect_bundles(&mut bundles));
+
+        let mut durations: Vec<(String, f64)> = vec![];
+        for bundle in bundles {
+            bundle.collect_durations(&bundle.name, &bundle.name, &mut durations);
+        }
+
+        durations
+    }
+}
+
+pub struct FailedTest<'a> {
+    pub bundle: &'a String,
+    pub suite: &'a String,
+    pub name: &'a String
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TestNode {
+    name: String,
+    #[serde(rename(deserialize = "nodeType"))]
+    node_type: String,
+    result: Option<String>,
+    /// Formatted like `1m 2.5s`, with the decimal separator of the locale.
+    duration: Option<String>,
+    /// Only reported by recent versions of xcresulttool.
+    #[serde(rename(deserialize = "durationInSeconds"))]
+    duration_in_seconds: Option<f64>,
+    #[serde(default)]
+    children: Vec<TestNode>
+}
+
+impl TestNode {
+    fn is_bundle(&self) -> bool {
+        self.node_type.ends_with("test bundle")
+    }
+
+    fn is_test_case(&self) -> bool {
+        self.node_type == "Test Case"
+    }
+
+    fn collect_bundles<'a>(&'a self, bundles: &mut Vec<&'a TestNode>) {
+        if self.is_bundle() {
+            bundles.push(self);
+        } else {
+            self.children
+                .iter()
+                .for_each(|child| child.collect_bundles(bundles));
+        }
+    }
+
+    fn collect_failures<'a>(&'a self, bundle: &'a String, suite: &'a String, failed_tests: &mut Vec<FailedTest<'a>>) {
+        if self.is_test_case() {
+            if self.result.as_deref() == Some("Failed") {
+                failed_tests.push(FailedTest { bundle, suite, name: &self.name });
+            }
+            return
+        }
+
+        let suite = if self.node_type == "Test Suite" { &self.name } else { suite };
+        self.children
+            .iter()
+            .for_each(|child| child.collect_failures(bundle, suite, failed_tests));
+    }
+
+    fn collect_durations(&self, bundle: &String, suite: &String, durations: &mut Vec<(String, f64)>) {
+        if self.is_test_case() {
+            let identifier = format!("{}/{}", bundle, suite);
+            let seconds = self.seconds().unwrap_or_default();
+
+            match durations.iter_mut().find(|(suite, _)| *suite == identifier) {
+                Some((_, total)) => *total += seconds,
+                None => durations.push((identifier, seconds))
+            }
+            return
+        }
+
+        let suite = if self.node_type == "Test Suite" { &self.name } else { suite };
+        self.children
+            .iter()
+            .for_each(|child| child.collect_durations(bundle, suite, durations));
+    }
+
+    fn seconds(&self) -> Option<f64> {
+        if self.duration_in_seconds.is_some() {
+            return self.duration_in_seconds
+        }
+
+        self.duration
+            .as_deref()?
+            .split_whitespace()
+            .map(|part| {
+                let part = part.replace(',', ".");
+                let (value, factor) = if let Some(hours) = part.strip_suffix('h') {
+                    (hours.to_string(), 3600.0)
+                } else if let Some(minutes) = part.strip_suffix('m') {
+                    (minutes.to_string(), 60.0)
+                } else {
+                    (part.trim_end_matches('s').to_string(), 1.0)
+                };
+                value.parse::<f64>().ok().map(|value| value * factor)
+            })
+            .sum()
+    }
+
+    fn test_case_count(&self) -> usize {
+        if self.is_test_case() {
+            return 1
+        }
+
+        self.children
+            .iter()
+            .map(|child| child.test_case_count())
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(line: usize, execution_count: Option<usize>) -> LineCoverage {
+        LineCoverage { line, is_executable: execution_count.is_some(), execution_count }
+    }
+
+    #[test]
+    fn test_merge_line_coverage_counts_a_file_in_every_target_it_is_in() {
+        let mut report = XCodeBuildReport {
+            targets: vec![
+                Target::new(String::from("App"), vec![TargetFile::new(String::from("Shared/Model.swift"), 1, 3)]),
+                Target::new(String::from("Widget"), vec![TargetFile::new(String::from("Shared/Model.swift"), 0, 3)])
+            ]
+        };
+        let path = String::from("Shared/Model.swift");
+
+        report.merge_line_coverage(vec![
+            HashMap::from([(path.clone(), vec![line(1, Some(1)), line(2, Some(0)), line(3, Some(0))])]),
+            HashMap::from([(path.clone(), vec![line(1, Some(0)), line(2, Some(2)), line(3, Some(0))])])
+        ]);
+
+        for target in report.targets() {
+            let file = &target.files[0];
+            assert_eq!((file.covered_lines(), file.executable_lines()), (2, 3), "{}", target.name);
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use sha2::{Digest, Sha256};
+
+use crate::cli::CacheOptions;
+use crate::err::{CommandExecutionError, XCReportError};
+use crate::fs::{derived_data_cache_path, derived_data_path, safe_file_name};
+use crate::metadata;
+
+/// Files pinning the dependencies of a project, hashed into the default cache key.
+const LOCKFILES: [&str; 3] = ["Podfile.lock", "Package.resolved", "Cartfile.resolved"];
+
+/// Directory levels below the project path searched for lockfiles.
+const MAX_SEARCH_DEPTH: usize = 4;
+
+/// Directories of fetched dependencies and build products, whose own lockfiles don't pin anything of the project.
+const SKIPPED_DIRS: [&str; 4] = ["Pods", "Carthage", "DerivedData", "node_modules"];
+
+/// Archives the derived data directory as `<key>.tar` in the cache directory, replacing any previous archive of the key.
+pub fn save(options: &CacheOptions) -> Result<(), XCReportError> {
+    let key = cache_key(options);
+    let derived_data_path = options_derived_data_path(options)?;
+    let archive_path = archive_path(options, &key)?;
+
+    if !derived_data_path.is_dir() {
+        println!("No derived data at {:?} to cache", derived_data_path);
+        return Ok(())
+    }
+
+    if let Some(cache_dir) = archive_path.parent() {
+        std::fs::create_dir_all(cache_dir)
+            .map_err(XCReportError::FileIO)?;
+    }
+
+    // Written next to the archive and renamed, so a job restoring concurrently never extracts half an archive.
+    let partial_path = archive_path.with_extension("tar.partial");
+    tar(&[OsStr::new("-cf"), partial_path.as_os_str(), OsStr::new("-C"), derived_data_path.as_os_str(), OsStr::new(".")])?;
+    std::fs::rename(&partial_path, &archive_path)
+        .map_err(XCReportError::FileIO)?;
+
+    println!("Saved derived data under key {} to {:?}", key, archive_path);
+
+    Ok(())
+}
+
+/// Replaces the derived data directory with the archive of the key. A missing archive is a cache miss, not an error,
+/// so the build simply starts cold.
+pub fn restore(options: &CacheOptions) -> Result<(), XCReportError> {
+    let key = cache_key(options);
+    let derived_data_path = options_derived_data_path(options)?;
+    let archive_path = archive_path(options, &key)?;
+
+    if !archive_path.is_file() {
+        println!("No derived data cached under key {}", key);
+        return Ok(())
+    }
+
+    if derived_data_path.exists() {
+        std::fs::remove_dir_all(&derived_data_path)
+            .map_err(XCReportError::FileIO)?;
+    }
+    std::fs::create_dir_all(&derived_data_path)
+        .map_err(XCReportError::FileIO)?;
+
+    tar(&[OsStr::new("-xf"), archive_path.as_os_str(), OsStr::new("-C"), derived_data_path.as_os_str()])?;
+
+    println!("Restored derived data under key {} to {:?}", key, derived_data_path);
+
+    Ok(())
+}
+
+/// The given key, or one derived from the lockfiles of the project and the Xcode version, so a cache is only reused
+/// by builds with the same dependencies and toolchain.
+fn cache_key(options: &CacheOptions) -> String {
+    if let Some(key) = &options.key {
+        return key.clone()
+    }
+
+    let mut lockfiles: Vec<PathBuf> = vec![];
+    collect_lockfiles(&options.project_path, 0, &mut lockfiles);
+    lockfiles.sort();
+
+    let mut hasher = Sha256::new();
+
+    for lockfile in &lockfiles {
+        let relative_path = lockfile.strip_prefix(&options.project_path).unwrap_or(lockfile);
+        hasher.update(relative_path.to_string_lossy().as_bytes());
+
+        match std::fs::read(lockfile) {
+            Ok(contents) => hasher.update(contents),
+            Err(_) => hasher.update(b"missing")
+        }
+    }
+
+    hasher.update(metadata::xcode_version(&options.project_path).unwrap_or_default().as_bytes());
+
+    let digest = format!("{:x}", hasher.finalize());
+
+    format!("derived-data-{}", &digest[..16])
+}
+
+fn collect_lockfiles(dir: &Path, depth: usize, lockfiles: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return
+    };
+
+    for path in entries.filter_map(|entry| entry.ok().map(|e| e.path())) {
+        let Some(name) = path.file_name().and_then(OsStr::to_str) else {
+            continue
+        };
+
+        if path.is_file() && LOCKFILES.contains(&name) {
+            lockfiles.push(path);
+        } else if path.is_dir() && depth < MAX_SEARCH_DEPTH && !SKIPPED_DIRS.contains(&name) && !name.starts_with('.') {
+            // Xcode keeps the Package.resolved of a project in `<name>.xcodeproj/project.xcworkspace/xcshareddata/swiftpm`.
+            collect_lockfiles(&path, depth + 1, lockfiles);
+        }
+    }
+}
+
+fn options_derived_data_path(options: &CacheOptions) -> Result<PathBuf, XCReportError> {
+    match &options.derived_data_path {
+        Some(derived_data_path) => Ok(derived_data_path.clone()),
+        None => derived_data_path()
+    }
+}
+
+fn archive_path(options: &CacheOptions, key: &str) -> Result<PathBuf, XCReportError> {
+    let cache_dir = match &options.cache_dir {
+        Some(cache_dir) => cache_dir.clone(),
+        None => derived_data_cache_path()?
+    };
+
+    Ok(cache_dir.join(format!("{}.tar", safe_file_name(key))))
+}
+
+fn tar(args: &[&OsStr]) -> Result<(), XCReportError> {
+    let output = Command::new("tar")
+        .args(args)
+        .output()
+        .map_err(|e| XCReportError::CommandExecution(CommandExecutionError::Tar(e)))?;
+
+    if !output.status.success() {
+        return Err(XCReportError::CommandExecution(CommandExecutionError::NonZeroExit {
+            desc: String::from_utf8_lossy(&output.stderr).trim().to_string()
+        }))
+    }
+
+    Ok(())
+}
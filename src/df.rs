@@ -1,14 +1,57 @@
-use std::path::PathBuf;
-use std::ops::{Div, Mul};
+use std::path::{Path, PathBuf};
+use std::ops::{Div, Mul, Sub};
 use polars::frame::DataFrame;
 use polars::prelude::*;
 
+use crate::cli::{Aggregate, HistoryFormat, RoundingOptions, RoundingPolicy};
+use crate::data::{FileFilter, Target, TargetFilter, XCodeBuildReport};
 use crate::err::XCReportError;
-use crate::fs::{full_report_path, report_path};
+use crate::exclude::Exclusion;
+use crate::fs::{configuration_report_path, domain_report_path, exclusions_path, file_delta_path, full_report_path, fully_covered_path, patch_report_path, path_prefix_report_path, report_path, squad_delta_path, targets_report_path, test_split_path, top_uncovered_path, tribe_report_path};
+use crate::render::{self, CsvRenderer, Registry};
+
+/// Drops the files the filter leaves out of the report.
+fn filter_files(report: DataFrame, filter: &FileFilter) -> Result<DataFrame, XCReportError> {
+    if filter.is_empty() {
+        return Ok(report)
+    }
+
+    let paths = report.column("path")
+        .and_then(|c| c.str())
+        .map_err(XCReportError::Polars)?;
+    let executable_lines = report.column("executable_lines")
+        .and_then(|c| c.cast(&DataType::UInt64))
+        .map_err(XCReportError::Polars)?;
+    let executable_lines = executable_lines.u64()
+        .map_err(XCReportError::Polars)?;
+
+    let mask: BooleanChunked = paths
+        .into_iter()
+        .zip(executable_lines)
+        .map(|(path, lines)| filter.keeps(path.unwrap_or_default(), lines.unwrap_or_default()))
+        .collect();
+
+    report.filter(&mask)
+        .map_err(XCReportError::Polars)
+}
+
+pub fn process_full_report(report: DataFrame, filter: &FileFilter) -> Result<DataFrame, XCReportError> {
+    let report = filter_files(report, filter)?;
+
+    let has_stale_files = report
+        .column("stale")
+        .ok()
+        .and_then(|stale| stale.bool().ok())
+        .map(|stale| stale.any())
+        .unwrap_or_default();
+
+    let report = if has_stale_files {
+        report.lazy().rename(["stale"], ["Stale"])
+    } else {
+        report.lazy().drop_columns(["stale"])
+    };
 
-pub fn process_full_report(report: DataFrame) -> Result<DataFrame, XCReportError> {
     report
-        .lazy()
         .sort_by_exprs(
             vec![col("squad_name")],
             vec![false],
@@ -39,22 +82,298 @@ pub fn process_full_report(report: DataFrame) -> Result<DataFrame, XCReportError
         .map_err(XCReportError::Polars)
 }
 
-pub fn process_report(report: &DataFrame) -> Result<DataFrame, XCReportError> {
+pub fn process_report(report: &DataFrame, rounding: &RoundingOptions) -> Result<DataFrame, XCReportError> {
+    summarize(report, "Squad", rounding)
+}
+
+/// Coverage per value of the `group` column, e.g. the domains of the tags, from squad and group
+/// pairs. Squads without a group are grouped under N/A.
+pub fn process_group_report(report: &DataFrame, squad_groups: &[(String, String)], group: &str, rounding: &RoundingOptions) -> Result<DataFrame, XCReportError> {
+    let (squads, groups): (Vec<&str>, Vec<&str>) = squad_groups
+        .iter()
+        .map(|(squad, group)| (squad.as_str(), group.as_str()))
+        .unzip();
+    let squad_groups = DataFrame::new(vec![Series::new("Squad", squads), Series::new(group, groups)])
+        .map_err(XCReportError::Polars)?;
+
+    let report = report.clone()
+        .lazy()
+        .left_join(squad_groups.lazy(), col("Squad"), col("Squad"))
+        .with_column(
+            col(group)
+                .fill_null(Expr::Literal(LiteralValue::String(String::from("N/A"))))
+        )
+        .collect()
+        .map_err(XCReportError::Polars)?;
+
+    summarize(&report, group, rounding)
+}
+
+/// Coverage per directory, the first `depth` directories of every file path, whatever squads the
+/// files belong to. Files closer to the root count towards their own directory.
+pub fn process_path_prefix_report(report: &DataFrame, depth: u32, rounding: &RoundingOptions) -> Result<DataFrame, XCReportError> {
+    let paths = report.column("Filepath")
+        .and_then(|c| c.str())
+        .map_err(XCReportError::Polars)?;
+
+    let prefixes: Vec<String> = paths
+        .into_iter()
+        .map(|path| path_prefix(path.unwrap_or_default(), depth as usize))
+        .collect();
+
+    let mut report = report.clone();
+    report.with_column(Series::new("Path Prefix", prefixes))
+        .map_err(XCReportError::Polars)?;
+
+    summarize(&report, "Path Prefix", rounding)
+}
+
+/// Coverage per squad of the changed files only. A changed path matches the files whose path is
+/// the same or ends with it, so repo-relative changes match the absolute paths of the coverage.
+pub fn process_patch_report(report: &DataFrame, changed_files: &[String], rounding: &RoundingOptions) -> Result<DataFrame, XCReportError> {
+    let paths = report.column("Filepath")
+        .and_then(|c| c.str())
+        .map_err(XCReportError::Polars)?;
+
+    let changed: BooleanChunked = paths
+        .into_iter()
+        .map(|path| {
+            let path = path.unwrap_or_default();
+            changed_files
+                .iter()
+                .any(|changed_file| path == changed_file || path.ends_with(&format!("/{}", changed_file)))
+        })
+        .collect();
+
+    let report = report.filter(&changed)
+        .map_err(XCReportError::Polars)?;
+
+    summarize(&report, "Squad", rounding)
+}
+
+/// Coverage % of all lines of the summary, `None` without executable lines.
+pub fn total_coverage(summary: &DataFrame) -> Result<Option<f64>, XCReportError> {
+    let total = |column: &str| -> Result<f64, XCReportError> {
+        let values = summary.column(column)
+            .and_then(|c| c.cast(&DataType::Float64))
+            .map_err(XCReportError::Polars)?;
+        let values = values.f64().map_err(XCReportError::Polars)?;
+
+        Ok(values.sum().unwrap_or_default())
+    };
+
+    let executable_lines = total("Executable Lines")?;
+    if executable_lines == 0.0 {
+        return Ok(None)
+    }
+
+    Ok(Some(total("Covered Lines")? * 100.0 / executable_lines))
+}
+
+/// The first `depth` directories of the path, e.g. `Sources/Payments` for
+/// `Sources/Payments/Cart/CartView.swift` and a depth of 2.
+fn path_prefix(path: &str, depth: usize) -> String {
+    let Some((directory, _)) = path.rsplit_once('/') else {
+        return String::from(".")
+    };
+
+    let absolute = directory.starts_with('/');
+    let prefix = directory
+        .split('/')
+        .filter(|component| !component.is_empty())
+        .take(depth)
+        .collect::<Vec<&str>>()
+        .join("/");
+
+    if absolute { format!("/{}", prefix) } else { prefix }
+}
+
+/// Sums the line counts of the full report per value of the `group` column.
+fn summarize(report: &DataFrame, group: &str, rounding: &RoundingOptions) -> Result<DataFrame, XCReportError> {
+    let mut aggregations = vec![
+        count(),
+        col("Covered Lines").sum(),
+        col("Executable Lines").sum()
+    ];
+
+    if report.column("Stale").is_ok() {
+        aggregations.push(col("Stale").sum().alias("Stale Files"));
+    }
+
     report.clone()
         .lazy()
-        .group_by(["Squad"])
+        .group_by([group])
+        .agg(aggregations)
+        .with_column(
+            round(
+                col("Covered Lines")
+                    .cast(DataType::Float64)
+                    .div(col("Executable Lines"))
+                    .mul(Expr::Literal(LiteralValue::Float64(100_f64))),
+                rounding
+            )
+            .alias("Coverage %")
+        )
+        .sort_by_exprs(
+            vec![col(group)],
+            vec![false],
+            true,
+            true
+        )
+        .with_column(
+            col(group)
+                .fill_null(Expr::Literal(LiteralValue::String(String::from("N/A"))))
+        )
+        .rename(["count"], ["Count"])
+        .collect()
+        .map_err(XCReportError::Polars)
+}
+
+/// Adds how many of every squad's files reach `file_threshold` line coverage to the summary, keeping
+/// only the columns of the aggregate metrics.
+pub fn aggregate_report(
+    summary: DataFrame,
+    report: &DataFrame,
+    aggregate: Aggregate,
+    file_threshold: f64,
+    rounding: &RoundingOptions
+) -> Result<DataFrame, XCReportError> {
+    if aggregate == Aggregate::Lines {
+        return Ok(summary)
+    }
+
+    let files_above_threshold = report.clone()
+        .lazy()
+        .group_by([col("Squad")])
         .agg([
-            count(),
-            col("Covered Lines").sum(),
-            col("Executable Lines").sum()
-        ])
+            col("Line Coverage")
+                .mul(Expr::Literal(LiteralValue::Float64(100_f64)))
+                .gt_eq(Expr::Literal(LiteralValue::Float64(file_threshold)))
+                .cast(DataType::UInt32)
+                .sum()
+                .alias("Files Above Threshold")
+        ]);
+
+    let summary = summary
+        .lazy()
+        .left_join(files_above_threshold, col("Squad"), col("Squad"))
         .with_column(
-            col("Covered Lines")
+            round(
+                col("Files Above Threshold")
+                    .cast(DataType::Float64)
+                    .div(col("Count").cast(DataType::Float64))
+                    .mul(Expr::Literal(LiteralValue::Float64(100_f64))),
+                rounding
+            )
+            .alias("Files Above Threshold %")
+        );
+
+    let summary = if aggregate == Aggregate::Files {
+        summary.drop_columns(["Covered Lines", "Executable Lines", "Coverage %"])
+    } else {
+        summary
+    };
+
+    summary
+        .collect()
+        .map_err(XCReportError::Polars)
+}
+
+pub fn add_test_metrics(report: DataFrame, squad_tests: DataFrame, rounding: &RoundingOptions) -> Result<DataFrame, XCReportError> {
+    report
+        .lazy()
+        .left_join(squad_tests.lazy(), col("Squad"), col("Squad"))
+        .with_column(
+            col("Tests")
+                .fill_null(Expr::Literal(LiteralValue::UInt64(0)))
+        )
+        .with_column(
+            round(
+                col("Tests")
+                    .cast(DataType::Float64)
+                    .div(col("Executable Lines").cast(DataType::Float64).div(Expr::Literal(LiteralValue::Float64(1000_f64)))),
+                rounding
+            )
+            .alias("Tests per KLoC")
+        )
+        .collect()
+        .map_err(XCReportError::Polars)
+}
+
+/// Coverage % every squad reaches with its unit tests alone and with its UI tests alone, from the
+/// summaries of the whole run and of the reruns of either kind, along with the coverage % only the
+/// UI tests reach, which cutting them would lose.
+pub fn test_split_report(summary: &DataFrame, unit_summary: &DataFrame, ui_summary: &DataFrame, rounding: &RoundingOptions) -> Result<DataFrame, XCReportError> {
+    let covered_lines = |summary: &DataFrame, alias: &str| -> LazyFrame {
+        summary.clone()
+            .lazy()
+            .select([col("Squad"), col("Covered Lines").alias(alias)])
+    };
+    let percentage = |covered: Expr| -> Expr {
+        round(
+            covered
                 .cast(DataType::Float64)
-                .div(col("Executable Lines"))
-                .mul(Expr::Literal(LiteralValue::Float64(100_f64)))
-                .round(2)
-                .alias("Coverage %")
+                .div(col("Executable Lines").cast(DataType::Float64))
+                .mul(Expr::Literal(LiteralValue::Float64(100_f64))),
+            rounding
+        )
+    };
+
+    summary.clone()
+        .lazy()
+        .left_join(covered_lines(unit_summary, "Unit Covered Lines"), col("Squad"), col("Squad"))
+        .left_join(covered_lines(ui_summary, "UI Covered Lines"), col("Squad"), col("Squad"))
+        .with_columns([
+            percentage(col("Unit Covered Lines").cast(DataType::Float64).fill_null(Expr::Literal(LiteralValue::Float64(0_f64)))).alias("Unit Tests Coverage %"),
+            percentage(col("UI Covered Lines").cast(DataType::Float64).fill_null(Expr::Literal(LiteralValue::Float64(0_f64)))).alias("UI Tests Coverage %"),
+            percentage(
+                col("Covered Lines").cast(DataType::Float64)
+                    .sub(col("Unit Covered Lines").cast(DataType::Float64).fill_null(Expr::Literal(LiteralValue::Float64(0_f64))))
+            ).alias("UI Only Coverage %")
+        ])
+        .select([
+            col("Squad"),
+            col("Executable Lines"),
+            col("Coverage %"),
+            col("Unit Tests Coverage %"),
+            col("UI Tests Coverage %"),
+            col("UI Only Coverage %")
+        ])
+        .collect()
+        .map_err(XCReportError::Polars)
+}
+
+/// Executable lines and coverage % of every squad side by side for two build configurations, e.g.
+/// Debug and Release, which may strip different code, with the change from the first to the second.
+pub fn configuration_report(
+    summary: &DataFrame,
+    other_summary: &DataFrame,
+    configuration: &str,
+    other_configuration: &str,
+    rounding: &RoundingOptions
+) -> Result<DataFrame, XCReportError> {
+    let coverage = format!("{} Coverage %", configuration);
+    let other_coverage = format!("{} Coverage %", other_configuration);
+    let columns = |summary: &DataFrame, configuration: &str, coverage: &str| -> LazyFrame {
+        summary.clone()
+            .lazy()
+            .select([
+                col("Squad"),
+                col("Executable Lines").alias(&format!("{} Executable Lines", configuration)),
+                col("Coverage %").alias(coverage)
+            ])
+    };
+
+    columns(summary, configuration, &coverage)
+        .join(
+            columns(other_summary, other_configuration, &other_coverage),
+            [col("Squad")],
+            [col("Squad")],
+            JoinArgs::new(JoinType::Outer { coalesce: true })
+        )
+        .with_column(
+            round(col(&other_coverage).sub(col(&coverage)), rounding)
+                .alias("Delta")
         )
         .sort_by_exprs(
             vec![col("Squad")],
@@ -62,15 +381,286 @@ pub fn process_report(report: &DataFrame) -> Result<DataFrame, XCReportError> {
             true,
             true
         )
+        .collect()
+        .map_err(XCReportError::Polars)
+}
+
+/// Adds the standard deviation of every squad's coverage % over its previous runs and this one,
+/// left empty for squads with fewer than two runs.
+pub fn add_volatility(mut report: DataFrame, squad_history: &[(String, Vec<f64>)], rounding: &RoundingOptions) -> Result<DataFrame, XCReportError> {
+    let squads = report.column("Squad")
+        .and_then(|c| c.str())
+        .map_err(XCReportError::Polars)?;
+    let coverages = report.column("Coverage %")
+        .and_then(|c| c.f64())
+        .map_err(XCReportError::Polars)?;
+
+    let volatility: Vec<Option<f64>> = squads
+        .into_iter()
+        .zip(coverages)
+        .map(|(squad, coverage)| {
+            let mut values: Vec<f64> = squad_history
+                .iter()
+                .find(|(history_squad, _)| Some(history_squad.as_str()) == squad)
+                .map(|(_, values)| values.clone())
+                .unwrap_or_default();
+            values.extend(coverage);
+
+            if values.len() < 2 {
+                return None
+            }
+
+            let mean = values.iter().sum::<f64>() / values.len() as f64;
+            let variance = values.iter().map(|value| (value - mean).powi(2)).sum::<f64>() / values.len() as f64;
+            Some(round_value(variance.sqrt(), rounding))
+        })
+        .collect();
+
+    report
+        .with_column(Series::new("Volatility", volatility))
+        .map_err(XCReportError::Polars)?;
+
+    Ok(report)
+}
+
+/// The `n` files with the most uncovered lines of all squads, under the `All` scope, followed by the `n`
+/// files with the most uncovered lines of every squad, under the squad's scope.
+pub fn top_uncovered(report: &DataFrame, n: u32, rounding: &RoundingOptions) -> Result<DataFrame, XCReportError> {
+    let files = report.clone()
+        .lazy()
+        .filter(col("Covered Lines").lt(col("Executable Lines")))
+        .select([
+            col("Squad"),
+            col("Filepath"),
+            col("Executable Lines").sub(col("Covered Lines")).alias("Uncovered Lines"),
+            col("Executable Lines"),
+            round(col("Line Coverage").mul(Expr::Literal(LiteralValue::Float64(100_f64))), rounding).alias("Coverage %")
+        ])
+        .sort_by_exprs(
+            vec![col("Uncovered Lines"), col("Filepath")],
+            vec![true, false],
+            true,
+            true
+        );
+
+    let overall = files.clone()
+        .limit(n)
+        .select([Expr::Literal(LiteralValue::String(String::from("All"))).alias("Scope"), col("*")]);
+
+    let per_squad = files
+        .group_by_stable([col("Squad")])
+        .head(Some(n as usize))
+        .sort_by_exprs(
+            vec![col("Squad"), col("Uncovered Lines"), col("Filepath")],
+            vec![false, true, false],
+            true,
+            true
+        )
+        .select([col("Squad").alias("Scope"), col("*")]);
+
+    concat([overall, per_squad], UnionArgs::default())
+        .and_then(|top| top.collect())
+        .map_err(XCReportError::Polars)
+}
+
+/// Line totals of every build target the filter selects, straight from the coverage without
+/// squads or exclusions.
+pub fn targets_report(coverage: &XCodeBuildReport, filter: &TargetFilter, rounding: &RoundingOptions) -> Result<DataFrame, XCReportError> {
+    let targets: Vec<&Target> = coverage.targets()
+        .iter()
+        .filter(|target| filter.selects(target.name()))
+        .collect();
+
+    let names: Vec<&str> = targets.iter().map(|target| target.name().as_str()).collect();
+    let files: Vec<u64> = targets.iter().map(|target| target.files().len() as u64).collect();
+    let covered_lines: Vec<u64> = targets.iter().map(|target| target.covered_lines() as u64).collect();
+    let executable_lines: Vec<u64> = targets.iter().map(|target| target.executable_lines() as u64).collect();
+
+    df!(
+        "Target" => names,
+        "Files" => files,
+        "Covered Lines" => covered_lines,
+        "Executable Lines" => executable_lines
+    )
+        .map_err(XCReportError::Polars)?
+        .lazy()
+        .with_column(
+            round(
+                col("Covered Lines")
+                    .cast(DataType::Float64)
+                    .div(col("Executable Lines").cast(DataType::Float64))
+                    .mul(Expr::Literal(LiteralValue::Float64(100_f64))),
+                rounding
+            )
+                .alias("Coverage %")
+        )
+        .collect()
+        .map_err(XCReportError::Polars)
+}
+
+pub fn read_report_csv(path: &Path) -> Result<DataFrame, XCReportError> {
+    CsvReader::from_path(path)
+        .map_err(XCReportError::Polars)?
+        .has_header(true)
+        .finish()
+        .map_err(XCReportError::Polars)
+}
+
+/// The full report of a run, with the files `--hide-fully-covered` moved to `fully_covered.csv` put back.
+pub fn read_full_report(identifier: &String) -> Result<DataFrame, XCReportError> {
+    let report = read_report_csv(&full_report_path(identifier)?)?;
+
+    let fully_covered_path = fully_covered_path(identifier)?;
+    if !fully_covered_path.is_file() {
+        return Ok(report)
+    }
+
+    let fully_covered = read_report_csv(&fully_covered_path)?;
+    // Without rows, the columns of a csv are read as strings and can't be stacked.
+    if report.height() == 0 {
+        return Ok(fully_covered)
+    }
+
+    report.vstack(&fully_covered)
+        .map_err(XCReportError::Polars)
+}
+
+/// Splits the full report into the files with uncovered lines, files without executable lines included,
+/// and the files with all lines covered.
+pub fn split_fully_covered(report: &DataFrame) -> Result<(DataFrame, DataFrame), XCReportError> {
+    let is_fully_covered = col("Covered Lines")
+        .eq(col("Executable Lines"))
+        .and(col("Executable Lines").gt(lit(0)));
+
+    let actionable = report.clone()
+        .lazy()
+        .filter(is_fully_covered.clone().not())
+        .collect()
+        .map_err(XCReportError::Polars)?;
+    let fully_covered = report.clone()
+        .lazy()
+        .filter(is_fully_covered)
+        .collect()
+        .map_err(XCReportError::Polars)?;
+
+    Ok((actionable, fully_covered))
+}
+
+/// Coverage % of every squad in both full reports, with the change between them.
+pub fn squad_delta(base: &DataFrame, head: &DataFrame, rounding: &RoundingOptions) -> Result<DataFrame, XCReportError> {
+    let base = process_report(base, rounding)?
+        .lazy()
+        .select([col("Squad"), col("Coverage %").alias("Base Coverage %")]);
+    let head = process_report(head, rounding)?
+        .lazy()
+        .select([col("Squad"), col("Coverage %").alias("Head Coverage %")]);
+
+    base
+        .join(head, [col("Squad")], [col("Squad")], JoinArgs::new(JoinType::Outer { coalesce: true }))
         .with_column(
-            col("Squad")
-                .fill_null(Expr::Literal(LiteralValue::String(String::from("N/A"))))
+            round(col("Head Coverage %").sub(col("Base Coverage %")), rounding)
+                .alias("Delta")
+        )
+        .sort_by_exprs(
+            vec![col("Squad")],
+            vec![false],
+            true,
+            true
+        )
+        .collect()
+        .map_err(XCReportError::Polars)
+}
+
+/// Line coverage % of files that changed, appeared or disappeared between both full reports.
+pub fn file_delta(base: &DataFrame, head: &DataFrame, rounding: &RoundingOptions) -> Result<DataFrame, XCReportError> {
+    let base = base.clone()
+        .lazy()
+        .select([
+            col("Filepath"),
+            col("Squad").alias("Base Squad"),
+            round(col("Line Coverage").mul(Expr::Literal(LiteralValue::Float64(100_f64))), rounding).alias("Base Coverage %")
+        ]);
+    let head = head.clone()
+        .lazy()
+        .select([
+            col("Filepath"),
+            col("Squad"),
+            round(col("Line Coverage").mul(Expr::Literal(LiteralValue::Float64(100_f64))), rounding).alias("Head Coverage %")
+        ]);
+
+    base
+        .join(head, [col("Filepath")], [col("Filepath")], JoinArgs::new(JoinType::Outer { coalesce: true }))
+        .select([
+            col("Filepath"),
+            when(col("Squad").is_null())
+                .then(col("Base Squad"))
+                .otherwise(col("Squad"))
+                .alias("Squad"),
+            col("Base Coverage %"),
+            col("Head Coverage %"),
+            round(col("Head Coverage %").sub(col("Base Coverage %")), rounding)
+                .alias("Delta")
+        ])
+        .filter(
+            col("Delta").neq(Expr::Literal(LiteralValue::Float64(0_f64)))
+                .or(col("Delta").is_null())
+        )
+        .sort_by_exprs(
+            vec![col("Squad"), col("Filepath")],
+            vec![false, false],
+            true,
+            true
         )
-        .rename(["count"], ["Count"])
         .collect()
         .map_err(XCReportError::Polars)
 }
 
+/// Rounds the expression to the configured precision, halves according to the configured policy.
+fn round(expr: Expr, rounding: &RoundingOptions) -> Expr {
+    let rounding = *rounding;
+
+    match rounding.rounding {
+        RoundingPolicy::HalfUp => expr.round(rounding.precision),
+        RoundingPolicy::HalfEven => expr.map(
+            move |series| {
+                let rounded = series.f64()?
+                    .apply_values(|value| round_value(value, &rounding));
+                Ok(Some(rounded.into_series()))
+            },
+            GetOutput::same_type()
+        )
+    }
+}
+
+pub fn round_value(value: f64, rounding: &RoundingOptions) -> f64 {
+    let multiplier = 10_f64.powi(rounding.precision as i32);
+    let scaled = value * multiplier;
+
+    let rounded = match rounding.rounding {
+        RoundingPolicy::HalfUp => scaled.round(),
+        RoundingPolicy::HalfEven if (scaled - scaled.trunc()).abs() == 0.5 => 2.0 * (scaled / 2.0).round(),
+        RoundingPolicy::HalfEven => scaled.round()
+    };
+
+    rounded / multiplier
+}
+
+pub fn save_squad_delta(df: &mut DataFrame, identifier: &String) -> Result<PathBuf, XCReportError> {
+    let squad_delta_path = squad_delta_path(identifier)?;
+
+    save_dataframe_csv(df, &squad_delta_path)?;
+
+    Ok(squad_delta_path)
+}
+
+pub fn save_file_delta(df: &mut DataFrame, identifier: &String) -> Result<PathBuf, XCReportError> {
+    let file_delta_path = file_delta_path(identifier)?;
+
+    save_dataframe_csv(df, &file_delta_path)?;
+
+    Ok(file_delta_path)
+}
+
 pub fn save_full_report(df: &mut DataFrame, identifier: &String) -> Result<PathBuf, XCReportError> {
     let full_report_path = full_report_path(identifier)?;
 
@@ -79,6 +669,117 @@ pub fn save_full_report(df: &mut DataFrame, identifier: &String) -> Result<PathB
     Ok(full_report_path)
 }
 
+pub fn save_domain_report(df: &mut DataFrame, identifier: &String) -> Result<PathBuf, XCReportError> {
+    let domain_report_path = domain_report_path(identifier)?;
+
+    save_dataframe_csv(df, &domain_report_path)?;
+
+    Ok(domain_report_path)
+}
+
+pub fn save_tribe_report(df: &mut DataFrame, identifier: &String) -> Result<PathBuf, XCReportError> {
+    let tribe_report_path = tribe_report_path(identifier)?;
+
+    save_dataframe_csv(df, &tribe_report_path)?;
+
+    Ok(tribe_report_path)
+}
+
+pub fn save_targets_report(df: &mut DataFrame, identifier: &String) -> Result<PathBuf, XCReportError> {
+    let targets_report_path = targets_report_path(identifier)?;
+
+    save_dataframe_csv(df, &targets_report_path)?;
+
+    Ok(targets_report_path)
+}
+
+pub fn save_path_prefix_report(df: &mut DataFrame, identifier: &String) -> Result<PathBuf, XCReportError> {
+    let path_prefix_report_path = path_prefix_report_path(identifier)?;
+
+    save_dataframe_csv(df, &path_prefix_report_path)?;
+
+    Ok(path_prefix_report_path)
+}
+
+pub fn save_fully_covered(df: &mut DataFrame, identifier: &String) -> Result<PathBuf, XCReportError> {
+    let fully_covered_path = fully_covered_path(identifier)?;
+
+    save_dataframe_csv(df, &fully_covered_path)?;
+
+    Ok(fully_covered_path)
+}
+
+pub fn save_patch_report(df: &mut DataFrame, identifier: &String) -> Result<PathBuf, XCReportError> {
+    let patch_report_path = patch_report_path(identifier)?;
+
+    save_dataframe_csv(df, &patch_report_path)?;
+
+    Ok(patch_report_path)
+}
+
+pub fn save_test_split_report(df: &mut DataFrame, identifier: &String) -> Result<PathBuf, XCReportError> {
+    let test_split_path = test_split_path(identifier)?;
+
+    save_dataframe_csv(df, &test_split_path)?;
+
+    Ok(test_split_path)
+}
+
+pub fn save_configuration_report(df: &mut DataFrame, identifier: &String) -> Result<PathBuf, XCReportError> {
+    let configuration_report_path = configuration_report_path(identifier)?;
+
+    save_dataframe_csv(df, &configuration_report_path)?;
+
+    Ok(configuration_report_path)
+}
+
+pub fn save_top_uncovered(df: &mut DataFrame, identifier: &String) -> Result<PathBuf, XCReportError> {
+    let top_uncovered_path = top_uncovered_path(identifier)?;
+
+    save_dataframe_csv(df, &top_uncovered_path)?;
+
+    Ok(top_uncovered_path)
+}
+
+pub fn save_exclusions(exclusions: &[Exclusion], identifier: &String) -> Result<PathBuf, XCReportError> {
+    let exclusions_path = exclusions_path(identifier)?;
+
+    let mut df = df!(
+        "Filepath" => exclusions.iter().map(|e| e.path.as_str()).collect::<Vec<_>>(),
+        "Rule" => exclusions.iter().map(|e| e.rule.as_str()).collect::<Vec<_>>(),
+        "Executable Lines" => exclusions.iter().map(|e| e.executable_lines as u64).collect::<Vec<_>>()
+    ).map_err(XCReportError::Polars)?;
+
+    save_dataframe_csv(&mut df, &exclusions_path)?;
+
+    Ok(exclusions_path)
+}
+
+/// Writes the trend table into `dir` as `trends.csv` or `trends.parquet`.
+pub fn save_trends(df: &mut DataFrame, dir: &Path, format: HistoryFormat) -> Result<PathBuf, XCReportError> {
+    std::fs::create_dir_all(dir)
+        .map_err(XCReportError::FileIO)?;
+
+    match format {
+        HistoryFormat::Csv => {
+            let trends_path = dir.join("trends.csv");
+            save_dataframe_csv(df, &trends_path)?;
+            Ok(trends_path)
+        },
+        HistoryFormat::Parquet => {
+            let trends_path = dir.join("trends.parquet");
+            let file = std::fs::File::create(&trends_path)
+                .map_err(XCReportError::FileIO)?;
+
+            ParquetWriter::new(file)
+                .finish(df)
+                .map_err(XCReportError::Polars)?;
+
+            Ok(trends_path)
+        }
+    }
+}
+
 pub fn save_report_to_default(df: &mut DataFrame, identifier: &String) -> Result<PathBuf, XCReportError> {
     let report_path = report_path(identifier)?;
 
@@ -87,15 +788,20 @@ pub fn save_report_to_default(df: &mut DataFrame, identifier: &String) -> Result
     Ok(report_path)
 }
 
-pub fn save_report_to_output(df: &mut DataFrame, output_path: &PathBuf) -> Result<(), XCReportError> {
-    save_dataframe_csv(df, output_path)
-}
+/// Saves the report in the named format, or the one of the file extension, falling back to csv.
+pub fn save_report_to_output(df: &mut DataFrame, output_path: &Path, format: Option<&str>) -> Result<(), XCReportError> {
+    let registry = Registry::default();
+    let renderer = match format {
+        Some(format) => registry.get(format),
+        None => registry.for_path(output_path)
+    };
 
-fn save_dataframe_csv(df: &mut DataFrame, path: &PathBuf) -> Result<(), XCReportError> {
-    let mut file = std::fs::File::create(path)
-        .map_err(XCReportError::FileIO)?;
+    match renderer {
+        Some(renderer) => render::save(df, output_path, renderer),
+        None => save_dataframe_csv(df, output_path)
+    }
+}
 
-    CsvWriter::new(&mut file)
-        .finish(df)
-        .map_err(XCReportError::Polars)
-}
\ No newline at end of file
+fn save_dataframe_csv(df: &mut DataFrame, path: &Path) -> Result<(), XCReportError> {
+    render::save(df, path, &CsvRenderer)
+}
@@ -0,0 +1,128 @@
+use std::path::Path;
+use std::process::Command;
+
+use crate::err::XCReportError;
+use crate::fs::home_path;
+use crate::metadata;
+use crate::simctl;
+use crate::tools;
+
+/// The outcome of a prerequisite check, with the fix to suggest when it failed.
+struct Check {
+    name: &'static str,
+    outcome: Result<String, String>,
+    fix: &'static str,
+    /// Whether runs fail without it, or only lose a feature.
+    required: bool
+}
+
+/// Checks the tools and directories runs depend on, printing how to fix every failed check.
+/// Fails when a required check failed.
+pub fn doctor() -> Result<(), XCReportError> {
+    let checks = [
+        Check {
+            name: "Xcode",
+            outcome: metadata::xcode_version(Path::new(".")).ok_or_else(|| String::from("xcodebuild -version failed")),
+            fix: "Install Xcode and select it with `sudo xcode-select -s /Applications/Xcode.app`, or pass --xcode.",
+            required: true
+        },
+        Check {
+            name: "Command line tools",
+            outcome: command_line(Command::new("xcode-select").arg("-p")),
+            fix: "Install them with `xcode-select --install`.",
+            required: true
+        },
+        Check {
+            name: "xccov",
+            outcome: command_line(tools::xcrun().args(["--find", "xccov"])),
+            fix: "Select a full Xcode rather than the command line tools with `sudo xcode-select -s /Applications/Xcode.app`.",
+            required: true
+        },
+        Check {
+            name: "xcpretty",
+            outcome: command_line(Command::new("xcpretty").arg("--version")),
+            fix: "Install it with `gem install xcpretty`. Only `generate`, `watch` and `upload` work without it.",
+            required: true
+        },
+        Check {
+            name: "~/.xcreport",
+            outcome: check_home_writable(),
+            fix: "Make sure the home directory is writable, e.g. `chown -R $USER ~/.xcreport`.",
+            required: true
+        },
+        Check {
+            name: "Simulators",
+            outcome: check_simulators(),
+            fix: "Add a simulator in Xcode > Settings > Platforms, or with `xcrun simctl create`.",
+            required: true
+        },
+        Check {
+            name: "git",
+            outcome: command_line(Command::new("git").arg("--version")),
+            fix: "Install git to record the commit and branch of runs and use --changed-files.",
+            required: false
+        }
+    ];
+
+    let mut failed_checks = 0;
+
+    for check in &checks {
+        match &check.outcome {
+            Ok(detail) => println!("✓ {}: {}", check.name, detail),
+            Err(problem) => {
+                let mark = if check.required { "✗" } else { "!" };
+                println!("{} {}: {}", mark, check.name, problem);
+                println!("  {}", check.fix);
+
+                if check.required {
+                    failed_checks += 1;
+                }
+            }
+        }
+    }
+
+    if failed_checks > 0 {
+        return Err(XCReportError::Doctor { failed_checks })
+    }
+
+    println!("\nEverything xcreport needs is in place");
+
+    Ok(())
+}
+
+/// The first line the command prints, if it succeeds.
+fn command_line(command: &mut Command) -> Result<String, String> {
+    let program = command.get_program().to_string_lossy().into_owned();
+
+    let output = command
+        .output()
+        .map_err(|e| format!("could not run {}: {}", program, e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("{} failed: {}", program, stderr.lines().next().unwrap_or_default()))
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).lines().next().unwrap_or_default().trim().to_string())
+}
+
+fn check_home_writable() -> Result<String, String> {
+    let home_path = home_path().map_err(|e| e.to_string())?;
+    let probe_path = home_path.join(format!(".doctor-{}", std::process::id()));
+
+    std::fs::create_dir_all(&home_path)
+        .and_then(|_| std::fs::write(&probe_path, b""))
+        .and_then(|_| std::fs::remove_file(&probe_path))
+        .map_err(|e| format!("{:?} is not writable: {}", home_path, e))?;
+
+    Ok(format!("{:?} is writable", home_path))
+}
+
+fn check_simulators() -> Result<String, String> {
+    let simulators = simctl::list_devices().map_err(|e| e.to_string())?;
+
+    match simulators.len() {
+        0 => Err(String::from("no simulator is available")),
+        count => Ok(format!("{} available", count))
+    }
+}
@@ -1,6 +1,8 @@
 use std::fmt::{Debug, Display, Formatter};
 use std::ops::Deref;
+use std::path::PathBuf;
 use polars::error::PolarsError;
+use serde::{Deserialize, Serialize};
 use thiserror::Error as ThisError;
 
 #[derive(ThisError, Debug)]
@@ -18,7 +20,175 @@ pub enum XCReportError {
     #[error("{0}")]
     Polars(#[source] PolarsError),
     #[error("{0}")]
-    Serde(#[source] serde_json::Error)
+    Serde(#[source] serde_json::Error),
+    #[error("{0}")]
+    Yaml(#[source] serde_yaml::Error),
+    #[error("{0}")]
+    Watch(#[source] notify::Error),
+    #[error("{0}")]
+    Config(#[source] toml::de::Error),
+    #[error("the config file defines no [[pipeline]] stages")]
+    NoPipeline,
+    #[error("pipeline stage {stage:?} has no xcreport command to run, or runs a pipeline itself")]
+    InvalidPipelineStage { stage: String },
+    #[error("pipeline stage {stage:?} exited with {status}")]
+    PipelineStage { stage: String, status: String, category: ErrorCategory },
+    #[error("{0}")]
+    XCResult(#[source] XCResultError),
+    #[error("{0}")]
+    Threshold(#[source] ThresholdError),
+    #[error("{count} test(s) failed")]
+    TestsFailed { count: usize },
+    #[error("{0}")]
+    GitHub(#[source] GitHubError),
+    #[error("{0}")]
+    GitLab(#[source] GitLabError),
+    #[error("{0}")]
+    Signal(#[source] ctrlc::Error),
+    #[error("{0}")]
+    Preflight(#[source] PreflightError),
+    #[error("{0}")]
+    Simctl(#[source] SimctlError),
+    #[error("{0}")]
+    XCode(#[source] XCodeError),
+    #[error("{0}")]
+    Xlsx(#[source] rust_xlsxwriter::XlsxError),
+    #[error("{0}")]
+    Upload(#[source] UploadError),
+    #[error("{0}")]
+    Workspace(#[source] WorkspaceError),
+    #[error("{0}")]
+    Verify(#[source] VerifyError),
+    #[error("{0}")]
+    Matcher(#[source] aho_corasick::BuildError),
+    #[error("{0}")]
+    Notify(#[source] NotifyError),
+    #[error("{0}")]
+    Serve(#[source] std::io::Error),
+    #[error("no test durations found in the last {runs} run(s)")]
+    NoTestHistory { runs: usize },
+    #[error("unknown report format {name:?}, expected one of {formats}")]
+    UnknownFormat { name: String, formats: String },
+    #[error("no test suite fits in the budget of {minutes} minute(s)")]
+    BudgetTooSmall { minutes: u64 },
+    #[error("build setting {setting:?} is not of the form KEY=VALUE")]
+    InvalidBuildSetting { setting: String },
+    #[error("could not list the files changed since {base:?} with git diff")]
+    GitDiff { base: String },
+    #[error("{failed_checks} prerequisite check(s) failed")]
+    Doctor { failed_checks: usize },
+    #[error("{problems} problem(s) found in the squads file")]
+    InvalidMapping { problems: usize },
+    #[error("smoke test failed: {problem}")]
+    Smoke { problem: String },
+    #[error("the input file has no {column:?} column, its columns are {columns}")]
+    MissingInputColumn { column: String, columns: String },
+    #[error("{0}")]
+    TomlSerialize(#[source] toml::ser::Error),
+    #[error("unknown ownership source {value:?}, expected backstage:<dir>")]
+    UnknownOwnershipSource { value: String },
+    #[error("no owned components found in the catalog-info files of {dir:?}")]
+    EmptyCatalog { dir: std::path::PathBuf }
+}
+
+impl XCReportError {
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            XCReportError::FilePath(_)
+            | XCReportError::DirPath(_)
+            | XCReportError::Yaml(_)
+            | XCReportError::Config(_)
+            | XCReportError::NoPipeline
+            | XCReportError::InvalidPipelineStage { .. }
+            | XCReportError::XCResult(_)
+            | XCReportError::Workspace(_)
+            | XCReportError::Matcher(_)
+            | XCReportError::NoTestHistory { .. }
+            | XCReportError::BudgetTooSmall { .. }
+            | XCReportError::UnknownFormat { .. }
+            | XCReportError::InvalidMapping { .. }
+            | XCReportError::MissingInputColumn { .. }
+            | XCReportError::UnknownOwnershipSource { .. }
+            | XCReportError::EmptyCatalog { .. }
+            | XCReportError::InvalidBuildSetting { .. }
+            | XCReportError::GitDiff { .. }
+            | XCReportError::XCode(_)
+            | XCReportError::Simctl(SimctlError::DeviceNotFound { .. } | SimctlError::NoDevice { .. } | SimctlError::UnknownDestination { .. })
+            | XCReportError::Verify(VerifyError::MissingInputs { .. })
+            | XCReportError::Upload(UploadError::MissingToken | UploadError::MissingCommit | UploadError::NoCoverage)
+            | XCReportError::GitHub(GitHubError::MissingRepository | GitHubError::MissingPullRequest | GitHubError::MissingToken | GitHubError::MissingCommit)
+            | XCReportError::GitLab(GitLabError::MissingProject | GitLabError::MissingToken) => {
+                ErrorCategory::Input
+            },
+            XCReportError::CommandExecution(_)
+            | XCReportError::Preflight(_)
+            | XCReportError::Simctl(_)
+            | XCReportError::Doctor { .. } => ErrorCategory::Build,
+            XCReportError::TestsFailed { .. } => ErrorCategory::TestsFailed,
+            XCReportError::Threshold(_) => ErrorCategory::Threshold,
+            XCReportError::PipelineStage { category, .. } => *category,
+            XCReportError::Verify(VerifyError::Mismatch { .. }) | XCReportError::Smoke { .. } => ErrorCategory::Verification,
+            XCReportError::Upload(_) | XCReportError::GitHub(_) | XCReportError::GitLab(_) | XCReportError::Notify(_) => ErrorCategory::Service,
+            XCReportError::FileIO(_)
+            | XCReportError::UTF8(_)
+            | XCReportError::Polars(_)
+            | XCReportError::Serde(_)
+            | XCReportError::TomlSerialize(_)
+            | XCReportError::Watch(_)
+            | XCReportError::Signal(_)
+            | XCReportError::Serve(_)
+            | XCReportError::Xlsx(_) => ErrorCategory::Internal
+        }
+    }
+}
+
+/// What kind of failure ended the run, each exiting with a code of its own.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCategory {
+    /// Unexpected failure reading results or writing reports.
+    Internal,
+    /// Invalid option, missing or malformed input file.
+    Input,
+    /// xcodebuild could not build, timed out or failed to start.
+    Build,
+    /// Some tests failed.
+    TestsFailed,
+    /// Some squad's coverage is below the gate.
+    Threshold,
+    /// GitHub, the coverage service or the webhook rejected a request or could not be reached.
+    Service,
+    /// `verify` found reports that differ from their recomputation, or `smoke` a wrong sample report.
+    Verification
+}
+
+impl ErrorCategory {
+    /// 2 is also what clap exits with on invalid arguments, 130 is taken by interruptions.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            ErrorCategory::Internal => 1,
+            ErrorCategory::Input => 2,
+            ErrorCategory::Build => 3,
+            ErrorCategory::TestsFailed => 4,
+            ErrorCategory::Threshold => 5,
+            ErrorCategory::Service => 6,
+            ErrorCategory::Verification => 7
+        }
+    }
+
+    /// The category an xcreport process exited with, `None` for success, interruptions and other codes.
+    pub fn from_exit_code(code: i32) -> Option<Self> {
+        match code {
+            1 => Some(ErrorCategory::Internal),
+            2 => Some(ErrorCategory::Input),
+            3 => Some(ErrorCategory::Build),
+            4 => Some(ErrorCategory::TestsFailed),
+            5 => Some(ErrorCategory::Threshold),
+            6 => Some(ErrorCategory::Service),
+            7 => Some(ErrorCategory::Verification),
+            _ => None
+        }
+    }
 }
 
 #[derive(ThisError, Debug)]
@@ -26,7 +196,14 @@ pub enum CommandExecutionError {
     XCodeBuild(#[source] std::io::Error),
     XCPretty(#[source] std::io::Error),
     XCRun(#[source] std::io::Error),
-    NonZeroExit { desc: String }
+    Swift(#[source] std::io::Error),
+    Open(#[source] std::io::Error),
+    GateCommand(#[source] std::io::Error),
+    Tar(#[source] std::io::Error),
+    Gzip(#[source] std::io::Error),
+    Hook(#[source] std::io::Error),
+    NonZeroExit { desc: String },
+    Timeout { minutes: u64 }
 }
 
 impl Display for CommandExecutionError {
@@ -35,7 +212,235 @@ impl Display for CommandExecutionError {
             CommandExecutionError::XCodeBuild(e) => Debug::fmt(&e, f),
             CommandExecutionError::XCPretty(e) => Debug::fmt(&e, f),
             CommandExecutionError::XCRun(e) => Debug::fmt(&e, f),
-            CommandExecutionError::NonZeroExit { desc } => f.write_str(desc.deref())
+            CommandExecutionError::Swift(e) => Debug::fmt(&e, f),
+            CommandExecutionError::Open(e) => Debug::fmt(&e, f),
+            CommandExecutionError::GateCommand(e) => Debug::fmt(&e, f),
+            CommandExecutionError::Tar(e) => Debug::fmt(&e, f),
+            CommandExecutionError::Gzip(e) => Debug::fmt(&e, f),
+            CommandExecutionError::Hook(e) => Debug::fmt(&e, f),
+            CommandExecutionError::NonZeroExit { desc } => f.write_str(desc.deref()),
+            CommandExecutionError::Timeout { minutes } => write!(f, "xcodebuild did not finish within {} minute(s)", minutes)
+        }
+    }
+}
+
+#[derive(ThisError, Debug)]
+pub enum PreflightError {
+    RosettaNotInstalled { runtime: String },
+    UnsupportedArchitecture { runtime: String, architectures: Vec<String> }
+}
+
+impl Display for PreflightError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PreflightError::RosettaNotInstalled { runtime } => {
+                write!(f, "{} only runs under Rosetta, install it with `softwareupdate --install-rosetta` or pick an arm64 runtime.", runtime)
+            },
+            PreflightError::UnsupportedArchitecture { runtime, architectures } => {
+                write!(f, "{} supports {:?}, none of which runs on this Mac.", runtime, architectures)
+            }
+        }
+    }
+}
+
+#[derive(ThisError, Debug)]
+pub enum SimctlError {
+    Launch(#[source] std::io::Error),
+    Failed { command: String, desc: String },
+    DeviceNotFound { name: String, os_version: Option<String> },
+    NoDevice { destination: String },
+    UnknownDestination { destination: String, suggestions: Vec<String> }
+}
+
+impl Display for SimctlError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SimctlError::Launch(e) => Debug::fmt(&e, f),
+            SimctlError::Failed { command, desc } => write!(f, "simctl {} failed: {}", command, desc),
+            SimctlError::DeviceNotFound { name, os_version: Some(os_version) } => write!(f, "No available simulator is named {:?} on OS {}.", name, os_version),
+            SimctlError::DeviceNotFound { name, os_version: None } => write!(f, "No available simulator is named {:?}.", name),
+            SimctlError::NoDevice { destination } => write!(f, "Destination {:?} names no simulator by id or name to boot.", destination),
+            SimctlError::UnknownDestination { destination, suggestions } if suggestions.is_empty() => {
+                write!(f, "No available simulator matches destination {:?}, see `xcrun simctl list devices available`.", destination)
+            },
+            SimctlError::UnknownDestination { destination, suggestions } => {
+                write!(f, "No available simulator matches destination {:?}, did you mean {}?", destination, suggestions.iter().map(|s| format!("{:?}", s)).collect::<Vec<String>>().join(" or "))
+            }
+        }
+    }
+}
+
+#[derive(ThisError, Debug)]
+pub enum XCodeError {
+    NotFound { xcode: String, installed: Vec<String> }
+}
+
+impl Display for XCodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            XCodeError::NotFound { xcode, installed } if installed.is_empty() => {
+                write!(f, "Xcode {} is not installed, and no other Xcode was found.", xcode)
+            },
+            XCodeError::NotFound { xcode, installed } => {
+                write!(f, "Xcode {} is not installed, the installed versions are {}.", xcode, installed.join(", "))
+            }
+        }
+    }
+}
+
+#[derive(ThisError, Debug)]
+pub enum XCResultError {
+    NoCoverage,
+    UnreadableCoverage { path: PathBuf, version: Option<String>, desc: String },
+    MalformedReport { location: String, desc: String }
+}
+
+impl Display for XCResultError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            XCResultError::NoCoverage => f.write_str("Test result does not contain a coverage report."),
+            XCResultError::UnreadableCoverage { path, version, desc } => {
+                let format = version.as_ref().map(|version| format!(" (result bundle format {})", version)).unwrap_or_default();
+                write!(f, "Neither xccov nor xcresulttool could read the coverage of {:?}{}: {}. Bundles of a newer Xcode can only be read with its tools, selected with --xcode or DEVELOPER_DIR.", path, format, desc)
+            },
+            XCResultError::MalformedReport { location, desc } => write!(f, "Coverage report of xccov is malformed at {}: {}", location, desc),
+        }
+    }
+}
+
+#[derive(ThisError, Debug)]
+pub enum ThresholdError {
+    Violation { count: usize, threshold: f64 },
+    UnownedShare { share: f64, max_share: f64 },
+    CommandRejected { commands: Vec<String> },
+    Ratchet { count: usize, tolerance: f64 }
+}
+
+impl Display for ThresholdError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ThresholdError::Violation { count, threshold } => {
+                write!(f, "{} squad(s) below the required coverage of {:.2}%", count, threshold)
+            },
+            ThresholdError::UnownedShare { share, max_share } => {
+                write!(f, "Files matching no squad hold {:.2}% of the executable lines, more than the allowed {:.2}%", share, max_share)
+            },
+            ThresholdError::CommandRejected { commands } => {
+                write!(f, "Gate command(s) rejected the run: {}", commands.join(", "))
+            },
+            ThresholdError::Ratchet { count, tolerance } => {
+                write!(f, "{} squad(s) dropped more than {:.2} percentage points below their ratcheted coverage", count, tolerance)
+            }
+        }
+    }
+}
+
+#[derive(ThisError, Debug)]
+pub enum GitHubError {
+    MissingRepository,
+    MissingPullRequest,
+    MissingToken,
+    MissingCommit,
+    Request(#[source] Box<ureq::Error>)
+}
+
+impl Display for GitHubError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GitHubError::MissingRepository => f.write_str("GitHub repository is not set, pass --github-repository."),
+            GitHubError::MissingPullRequest => f.write_str("Pull request number is not set, pass --github-pr."),
+            GitHubError::MissingToken => f.write_str("GitHub token is not set, pass --github-token."),
+            GitHubError::MissingCommit => f.write_str("Commit of the check run is not known, pass --github-sha."),
+            GitHubError::Request(e) => Display::fmt(&e, f)
+        }
+    }
+}
+
+#[derive(ThisError, Debug)]
+pub enum GitLabError {
+    MissingProject,
+    MissingToken,
+    Request(#[source] Box<ureq::Error>)
+}
+
+impl Display for GitLabError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GitLabError::MissingProject => f.write_str("GitLab project is not set, pass --gitlab-project."),
+            GitLabError::MissingToken => f.write_str("GitLab token is not set, pass --gitlab-token."),
+            GitLabError::Request(e) => Display::fmt(&e, f)
+        }
+    }
+}
+
+#[derive(ThisError, Debug)]
+pub enum UploadError {
+    MissingToken,
+    MissingCommit,
+    NoCoverage,
+    UnexpectedResponse { body: String },
+    Request(#[source] Box<ureq::Error>)
+}
+
+impl Display for UploadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UploadError::MissingToken => f.write_str("Upload token is not set, pass --token."),
+            UploadError::MissingCommit => f.write_str("Commit could not be determined, pass --commit."),
+            UploadError::NoCoverage => f.write_str("Test results do not contain line coverage to upload."),
+            UploadError::UnexpectedResponse { body } => write!(f, "Unexpected response from the coverage service: {:?}", body),
+            UploadError::Request(e) => Display::fmt(&e, f)
+        }
+    }
+}
+
+#[derive(ThisError, Debug)]
+pub enum WorkspaceError {
+    NotFound { project_path: PathBuf },
+    Ambiguous { candidates: Vec<PathBuf> }
+}
+
+impl Display for WorkspaceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WorkspaceError::NotFound { project_path } => {
+                write!(f, "No workspace found in {:?}, pass --workspace.", project_path)
+            },
+            WorkspaceError::Ambiguous { candidates } => {
+                write!(f, "Found several workspaces {:?}, pick one with --workspace.", candidates)
+            }
+        }
+    }
+}
+
+#[derive(ThisError, Debug)]
+pub enum VerifyError {
+    MissingInputs { identifier: String },
+    Mismatch { files: Vec<String> }
+}
+
+impl Display for VerifyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerifyError::MissingInputs { identifier } => {
+                write!(f, "Run {:?} has no inputs.json to verify its reports against.", identifier)
+            },
+            VerifyError::Mismatch { files } => {
+                write!(f, "Reports differ from their recomputation: {}", files.join(", "))
+            }
+        }
+    }
+}
+
+#[derive(ThisError, Debug)]
+pub enum NotifyError {
+    Request(#[source] Box<ureq::Error>)
+}
+
+impl Display for NotifyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NotifyError::Request(e) => Display::fmt(&e, f)
         }
     }
 }
@@ -57,7 +462,8 @@ impl Display for DirPathError {
 pub enum FilePathError {
     NotFound,
     AlreadyExists,
-    InvalidType { extension: String }
+    InvalidType { extension: String },
+    InvalidName { name: String }
 }
 
 impl Display for FilePathError {
@@ -71,6 +477,9 @@ impl Display for FilePathError {
             },
             FilePathError::InvalidType { extension } => {
                 write!(f, "File type: {:?} is invalid", extension)
+            },
+            FilePathError::InvalidName { name } => {
+                write!(f, "File name: {:?} must not contain a directory", name)
             }
         }
     }
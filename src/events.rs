@@ -0,0 +1,71 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use serde::Serialize;
+
+use crate::err::XCReportError;
+use crate::scrub::scrub;
+
+static EVENTS_FILE: OnceLock<Mutex<File>> = OnceLock::new();
+
+/// Progress events written as JSON lines to the `--events-file`.
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    PhaseStarted { phase: &'a str },
+    PhaseFinished { phase: &'a str },
+    Progress { phase: &'a str, percent: f64 },
+    Warning { message: &'a str }
+}
+
+#[derive(Serialize)]
+struct Record<'a> {
+    timestamp: String,
+    #[serde(flatten)]
+    event: &'a Event<'a>
+}
+
+pub fn init(path: &Path) -> Result<(), XCReportError> {
+    let file = File::create(path)
+        .map_err(XCReportError::FileIO)?;
+
+    let _ = EVENTS_FILE.set(Mutex::new(file));
+
+    Ok(())
+}
+
+/// Appends the event to the events file, a no-op when none was requested.
+pub fn emit(event: Event) {
+    let Some(file) = EVENTS_FILE.get() else {
+        return
+    };
+
+    let record = Record {
+        timestamp: chrono::offset::Local::now().to_rfc3339(),
+        event: &event
+    };
+
+    if let (Ok(mut file), Ok(line)) = (file.lock(), serde_json::to_string(&record)) {
+        let _ = writeln!(file, "{}", scrub(&line));
+    }
+}
+
+pub fn phase_started(phase: &str) {
+    emit(Event::PhaseStarted { phase })
+}
+
+pub fn phase_finished(phase: &str) {
+    emit(Event::PhaseFinished { phase })
+}
+
+pub fn progress(phase: &str, done: usize, total: usize) {
+    let percent = if total == 0 { 100.0 } else { done as f64 * 100.0 / total as f64 };
+    emit(Event::Progress { phase, percent: (percent * 100.0).round() / 100.0 })
+}
+
+/// Prints the warning to stderr and records it as an event.
+pub fn warning(message: &str) {
+    eprintln!("Warning: {}", scrub(message));
+    emit(Event::Warning { message })
+}
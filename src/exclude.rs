@@ -0,0 +1,167 @@
+use std::path::Path;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::cli::Generator;
+use crate::data::TargetFile;
+use crate::err::XCReportError;
+
+/// A path pattern that removes matching files from the report, or keeps them when `excludes` is off.
+#[derive(Serialize, Deserialize)]
+pub struct ExclusionRule {
+    pattern: String,
+    description: String,
+    excludes: bool
+}
+
+/// A file removed from the report, with the rule responsible for it.
+pub struct Exclusion {
+    pub path: String,
+    pub rule: String,
+    pub executable_lines: usize
+}
+
+/// Reads an ignore file: one path pattern per line, blank lines and `#` comments are skipped.
+pub fn parse_ignore_file(ignore_file: &Path) -> Result<Vec<ExclusionRule>, XCReportError> {
+    let contents = std::fs::read_to_string(ignore_file)
+        .map_err(XCReportError::FileIO)?;
+
+    let rules = contents
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(line_number, pattern)| ExclusionRule {
+            pattern: pattern.to_string(),
+            description: format!("{}:{}: {}", ignore_file.display(), line_number, pattern),
+            excludes: true
+        })
+        .collect();
+
+    Ok(rules)
+}
+
+impl Generator {
+    /// Path patterns of the files the generator writes.
+    fn patterns(&self) -> &'static [&'static str] {
+        match self {
+            Generator::RSwift => &["R.generated.swift"],
+            Generator::Swiftgen => &["+Generated.swift", "/SwiftGen/"],
+            Generator::Sourcery => &[".generated.swift"],
+            Generator::XcodeSymbols => &["GeneratedAssetSymbols.swift", "GeneratedStringSymbols"],
+            Generator::CoreData => &["+CoreDataClass.swift", "+CoreDataProperties.swift"],
+            Generator::Protobuf => &[".pb.swift", ".grpc.swift"],
+            Generator::Apollo => &[".graphql.swift"]
+        }
+    }
+}
+
+/// Rules excluding the output of every known code generator except the kept ones.
+///
+/// Kept generators still get rules, which keep their files, so that a file is attributed to the
+/// first generator it matches, e.g. `R.generated.swift` to R.swift rather than Sourcery.
+pub fn generated_file_rules(kept: &[Generator]) -> Vec<ExclusionRule> {
+    Generator::value_variants()
+        .iter()
+        .flat_map(|generator| {
+            let excludes = !kept.contains(generator);
+            let name = generator
+                .to_possible_value()
+                .map(|value| value.get_name().to_string())
+                .unwrap_or_default();
+
+            generator.patterns().iter().map(move |pattern| ExclusionRule {
+                pattern: pattern.to_string(),
+                description: format!("generated by {}: {}", name, pattern),
+                excludes
+            })
+        })
+        .collect()
+}
+
+/// Splits the files into the ones kept in the report and the audit trail of excluded ones.
+pub fn apply_exclusions(files: Vec<TargetFile>, rules: &[ExclusionRule]) -> (Vec<TargetFile>, Vec<Exclusion>) {
+    let mut kept: Vec<TargetFile> = vec![];
+    let mut exclusions: Vec<Exclusion> = vec![];
+
+    for file in files {
+        let rule = rules
+            .iter()
+            .find(|rule| file.file_path().contains(&rule.pattern));
+
+        if let Some(rule) = rule.filter(|rule| rule.excludes) {
+            exclusions.push(Exclusion {
+                path: file.file_path().clone(),
+                rule: rule.description.clone(),
+                executable_lines: file.executable_lines()
+            });
+        } else {
+            kept.push(file);
+        }
+    }
+
+    (kept, exclusions)
+}
+
+/// Marker comment excluding the whole file from the reports.
+const IGNORE_FILE_MARKER: &str = "xcreport:ignore-file";
+/// Marker comments around lines left out of the coverage of the file.
+const IGNORE_START_MARKER: &str = "xcreport:ignore-start";
+const IGNORE_END_MARKER: &str = "xcreport:ignore-end";
+
+/// The `// xcreport:` markers of a source file.
+#[derive(Default)]
+pub struct SourceMarkers {
+    /// Line of the `ignore-file` marker.
+    pub ignore_file_line: Option<usize>,
+    /// First and last line of every `ignore-start` to `ignore-end` region, a region left open ending with the file.
+    pub ignored_regions: Vec<(usize, usize)>
+}
+
+impl SourceMarkers {
+    pub fn ignores_line(&self, line: usize) -> bool {
+        self.ignored_regions.iter().any(|(start, end)| (*start..=*end).contains(&line))
+    }
+}
+
+/// Finds the markers in the `//` comments of the source.
+pub fn scan_markers(source: &str) -> SourceMarkers {
+    let mut markers = SourceMarkers::default();
+    let mut region_start: Option<usize> = None;
+    let mut line_count = 0;
+
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        line_count = line_number;
+
+        let Some((_, comment)) = line.split_once("//") else {
+            continue
+        };
+        let comment = comment.trim_start();
+
+        if comment.starts_with(IGNORE_FILE_MARKER) {
+            markers.ignore_file_line.get_or_insert(line_number);
+        } else if comment.starts_with(IGNORE_START_MARKER) {
+            region_start.get_or_insert(line_number);
+        } else if comment.starts_with(IGNORE_END_MARKER) {
+            if let Some(start) = region_start.take() {
+                markers.ignored_regions.push((start, line_number));
+            }
+        }
+    }
+
+    if let Some(start) = region_start {
+        markers.ignored_regions.push((start, line_count));
+    }
+
+    markers
+}
+
+/// Rule excluding the file marked with `// xcreport:ignore-file`, by its report path.
+pub fn marker_rule(path: &str, source_path: &Path, line: usize) -> ExclusionRule {
+    ExclusionRule {
+        pattern: path.to_string(),
+        description: format!("{}:{}: {}", source_path.display(), line, IGNORE_FILE_MARKER),
+        excludes: true
+    }
+}
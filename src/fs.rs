@@ -1,4 +1,5 @@
 use std::fs;
+use std::io::ErrorKind;
 use std::path::PathBuf;
 use crate::err::{DirPathError, XCReportError};
 
@@ -7,20 +8,53 @@ pub fn derived_data_path() -> Result<PathBuf, XCReportError> {
     Ok(PathBuf::from_iter([&home_path, &PathBuf::from("derived_data")]))
 }
 
+pub fn derived_data_cache_path() -> Result<PathBuf, XCReportError> {
+    let home_path = home_path()?;
+    Ok(PathBuf::from_iter([&home_path, &PathBuf::from("derived_data_cache")]))
+}
+
+pub fn smoke_path() -> Result<PathBuf, XCReportError> {
+    let home_path = home_path()?;
+    Ok(PathBuf::from_iter([&home_path, &PathBuf::from("smoke")]))
+}
+
 pub fn home_path() -> Result<PathBuf, XCReportError> {
     let home_path = home::home_dir().ok_or(XCReportError::DirPath(DirPathError::NotFound))?;
     Ok(PathBuf::from_iter([&home_path, &PathBuf::from(".xcreport")]))
 }
 
+pub fn index_path() -> Result<PathBuf, XCReportError> {
+    let home_path = home_path()?;
+    Ok(PathBuf::from_iter([&home_path, &PathBuf::from("index.json")]))
+}
+
 pub fn xcresult_path(identifier: &String) -> Result<PathBuf, XCReportError> {
     let home_path = home_path()?;
     Ok(PathBuf::from_iter([&home_path, &PathBuf::from(identifier), &PathBuf::from("result.xcresult")]))
 }
 
-pub fn xcpretty_report_path(identifier: &String) -> Result<PathBuf, XCReportError> {
+pub fn scheme_xcresult_path(identifier: &String, scheme: &str) -> Result<PathBuf, XCReportError> {
+    let home_path = home_path()?;
+    Ok(PathBuf::from_iter([&home_path, &PathBuf::from(identifier), &PathBuf::from(format!("{}.xcresult", safe_file_name(scheme)))]))
+}
+
+pub fn xcresult_cache_path(key: &str) -> Result<PathBuf, XCReportError> {
+    let home_path = home_path()?;
+    Ok(PathBuf::from_iter([&home_path, &PathBuf::from("xcresult_cache"), &PathBuf::from(format!("{}.json", key))]))
+}
+
+pub fn xcpretty_report_path(identifier: &String, report_name: &str) -> Result<PathBuf, XCReportError> {
     let home_path = home_path()?;
 
-    Ok(PathBuf::from_iter([&home_path, &PathBuf::from(identifier), &PathBuf::from("xcpretty_report.html")]))
+    Ok(PathBuf::from_iter([&home_path, &PathBuf::from(identifier), &PathBuf::from(report_name)]))
+}
+
+/// The raw output of xcodebuild, gzipped with `--compress-log`.
+pub fn build_log_path(identifier: &String, compressed: bool) -> Result<PathBuf, XCReportError> {
+    let home_path = home_path()?;
+    let file_name = if compressed { "xcodebuild.log.gz" } else { "xcodebuild.log" };
+
+    Ok(PathBuf::from_iter([&home_path, &PathBuf::from(identifier), &PathBuf::from(file_name)]))
 }
 
 pub fn full_report_path(identifier: &String) -> Result<PathBuf, XCReportError> {
@@ -45,6 +79,238 @@ pub fn report_path(identifier: &String) -> Result<PathBuf, XCReportError> {
     )
 }
 
+pub fn domain_report_path(identifier: &String) -> Result<PathBuf, XCReportError> {
+    let home_path = home_path()?;
+    Ok(
+        PathBuf::from_iter([
+            &home_path,
+            &PathBuf::from(&identifier),
+            &PathBuf::from("domains.csv")
+        ])
+    )
+}
+
+pub fn tribe_report_path(identifier: &String) -> Result<PathBuf, XCReportError> {
+    let home_path = home_path()?;
+    Ok(
+        PathBuf::from_iter([
+            &home_path,
+            &PathBuf::from(&identifier),
+            &PathBuf::from("tribes.csv")
+        ])
+    )
+}
+
+pub fn targets_report_path(identifier: &String) -> Result<PathBuf, XCReportError> {
+    let home_path = home_path()?;
+    Ok(
+        PathBuf::from_iter([
+            &home_path,
+            &PathBuf::from(&identifier),
+            &PathBuf::from("targets.csv")
+        ])
+    )
+}
+
+pub fn path_prefix_report_path(identifier: &String) -> Result<PathBuf, XCReportError> {
+    let home_path = home_path()?;
+    Ok(
+        PathBuf::from_iter([
+            &home_path,
+            &PathBuf::from(&identifier),
+            &PathBuf::from("paths.csv")
+        ])
+    )
+}
+
+pub fn fully_covered_path(identifier: &String) -> Result<PathBuf, XCReportError> {
+    let home_path = home_path()?;
+    Ok(
+        PathBuf::from_iter([
+            &home_path,
+            &PathBuf::from(&identifier),
+            &PathBuf::from("fully_covered.csv")
+        ])
+    )
+}
+
+pub fn patch_report_path(identifier: &String) -> Result<PathBuf, XCReportError> {
+    let home_path = home_path()?;
+    Ok(
+        PathBuf::from_iter([
+            &home_path,
+            &PathBuf::from(&identifier),
+            &PathBuf::from("patch.csv")
+        ])
+    )
+}
+
+pub fn test_split_path(identifier: &String) -> Result<PathBuf, XCReportError> {
+    let home_path = home_path()?;
+    Ok(
+        PathBuf::from_iter([
+            &home_path,
+            &PathBuf::from(&identifier),
+            &PathBuf::from("test_split.csv")
+        ])
+    )
+}
+
+/// Result bundle of a scheme rerun with the test bundles of one kind, e.g. `Unit-App.xcresult`.
+pub fn test_kind_xcresult_path(identifier: &String, kind: &str, scheme: &str) -> Result<PathBuf, XCReportError> {
+    let home_path = home_path()?;
+    Ok(PathBuf::from_iter([&home_path, &PathBuf::from(identifier), &PathBuf::from(format!("{}-{}.xcresult", kind, safe_file_name(scheme)))]))
+}
+
+/// Result bundle of a scheme rerun with another build configuration, e.g. `Release-App.xcresult`.
+pub fn configuration_xcresult_path(identifier: &String, configuration: &str, scheme: &str) -> Result<PathBuf, XCReportError> {
+    let home_path = home_path()?;
+    Ok(PathBuf::from_iter([&home_path, &PathBuf::from(identifier), &PathBuf::from(format!("{}-{}.xcresult", safe_file_name(configuration), safe_file_name(scheme)))]))
+}
+
+pub fn configuration_report_path(identifier: &String) -> Result<PathBuf, XCReportError> {
+    let home_path = home_path()?;
+    Ok(
+        PathBuf::from_iter([
+            &home_path,
+            &PathBuf::from(&identifier),
+            &PathBuf::from("configurations.csv")
+        ])
+    )
+}
+
+pub fn top_uncovered_path(identifier: &String) -> Result<PathBuf, XCReportError> {
+    let home_path = home_path()?;
+    Ok(
+        PathBuf::from_iter([
+            &home_path,
+            &PathBuf::from(&identifier),
+            &PathBuf::from("top_uncovered.csv")
+        ])
+    )
+}
+
+pub fn backstage_path(identifier: &String) -> Result<PathBuf, XCReportError> {
+    let home_path = home_path()?;
+    Ok(
+        PathBuf::from_iter([
+            &home_path,
+            &PathBuf::from(&identifier),
+            &PathBuf::from("backstage.json")
+        ])
+    )
+}
+
+pub fn xlsx_report_path(identifier: &String) -> Result<PathBuf, XCReportError> {
+    let home_path = home_path()?;
+    Ok(
+        PathBuf::from_iter([
+            &home_path,
+            &PathBuf::from(&identifier),
+            &PathBuf::from("report.xlsx")
+        ])
+    )
+}
+
+pub fn exclusions_path(identifier: &String) -> Result<PathBuf, XCReportError> {
+    let home_path = home_path()?;
+    Ok(
+        PathBuf::from_iter([
+            &home_path,
+            &PathBuf::from(&identifier),
+            &PathBuf::from("exclusions.csv")
+        ])
+    )
+}
+
+pub fn html_report_path(identifier: &String) -> Result<PathBuf, XCReportError> {
+    let home_path = home_path()?;
+    Ok(
+        PathBuf::from_iter([
+            &home_path,
+            &PathBuf::from(&identifier),
+            &PathBuf::from("html")
+        ])
+    )
+}
+
+pub fn squad_delta_path(identifier: &String) -> Result<PathBuf, XCReportError> {
+    let home_path = home_path()?;
+    Ok(
+        PathBuf::from_iter([
+            &home_path,
+            &PathBuf::from(&identifier),
+            &PathBuf::from("squad_delta.csv")
+        ])
+    )
+}
+
+pub fn file_delta_path(identifier: &String) -> Result<PathBuf, XCReportError> {
+    let home_path = home_path()?;
+    Ok(
+        PathBuf::from_iter([
+            &home_path,
+            &PathBuf::from(&identifier),
+            &PathBuf::from("file_delta.csv")
+        ])
+    )
+}
+
+pub fn compare_html_path(identifier: &String) -> Result<PathBuf, XCReportError> {
+    let home_path = home_path()?;
+    Ok(
+        PathBuf::from_iter([
+            &home_path,
+            &PathBuf::from(&identifier),
+            &PathBuf::from("compare.html")
+        ])
+    )
+}
+
+pub fn metadata_path(identifier: &String) -> Result<PathBuf, XCReportError> {
+    let home_path = home_path()?;
+    Ok(
+        PathBuf::from_iter([
+            &home_path,
+            &PathBuf::from(&identifier),
+            &PathBuf::from("metadata.json")
+        ])
+    )
+}
+
+pub fn artifact_manifest_path(identifier: &String) -> Result<PathBuf, XCReportError> {
+    let home_path = home_path()?;
+    Ok(
+        PathBuf::from_iter([
+            &home_path,
+            &PathBuf::from(&identifier),
+            &PathBuf::from("artifacts.json")
+        ])
+    )
+}
+
+pub fn inputs_path(identifier: &String) -> Result<PathBuf, XCReportError> {
+    let home_path = home_path()?;
+    Ok(
+        PathBuf::from_iter([
+            &home_path,
+            &PathBuf::from(&identifier),
+            &PathBuf::from("inputs.json")
+        ])
+    )
+}
+
+pub fn target_cache_path(identifier: &String) -> Result<PathBuf, XCReportError> {
+    let home_path = home_path()?;
+    Ok(
+        PathBuf::from_iter([
+            &home_path,
+            &PathBuf::from(&identifier),
+            &PathBuf::from("target_cache.json")
+        ])
+    )
+}
+
 pub fn get_workdir(identifier: &String) -> Result<PathBuf, XCReportError> {
     let home_path = home_path()?;
     let path = PathBuf::from_iter([
@@ -63,7 +329,88 @@ pub fn get_identifier() -> Result<String, XCReportError> {
         .format("%F-%H-%M-%S")
         .to_string();
 
-    get_workdir(&identifier)?;
+    reserve_identifier(&identifier)
+}
+
+/// Creates the run directory of the identifier, or of the first free `<identifier>-<n>` when it is taken,
+/// so runs starting within the same second, e.g. parallel CI jobs sharing a home directory, never write
+/// into each other's directory.
+pub fn reserve_identifier(identifier: &str) -> Result<String, XCReportError> {
+    let home_path = home_path()?;
+    fs::create_dir_all(&home_path)
+        .map_err(XCReportError::FileIO)?;
+
+    let mut attempt: usize = 0;
+    loop {
+        let candidate = match attempt {
+            0 => identifier.to_string(),
+            _ => format!("{}-{}", identifier, attempt)
+        };
 
-    Ok(identifier)
-}
\ No newline at end of file
+        // Creating the directory itself, not its parents, fails if another run got there first.
+        match fs::create_dir(home_path.join(&candidate)) {
+            Ok(()) => return Ok(candidate),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => attempt += 1,
+            Err(e) => return Err(XCReportError::FileIO(e))
+        }
+    }
+}
+
+/// Replaces the characters of scheme names, test identifiers and attachment names that file systems
+/// reject or read as directories.
+pub fn safe_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if matches!(c, '/' | ':' | '\\' | '*' | '?' | '"' | '<' | '>' | '|') { '_' } else { c })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_dir(identifier: &String) -> PathBuf {
+        home_path().unwrap().join(identifier)
+    }
+
+    #[test]
+    fn safe_file_name_replaces_rejected_characters() {
+        assert_eq!(safe_file_name(r#"Feature/Login: "Beta"\*?<>|"#), "Feature_Login_ _Beta_______");
+    }
+
+    #[test]
+    fn safe_file_name_keeps_other_characters() {
+        assert_eq!(safe_file_name("App Tests, Nightly (iOS 17.2) ✓"), "App Tests, Nightly (iOS 17.2) ✓");
+        assert_eq!(safe_file_name(""), "");
+    }
+
+    #[test]
+    fn scheme_bundles_stay_in_the_run_directory() {
+        let identifier = String::from("20260101_120000");
+        let path = scheme_xcresult_path(&identifier, "Features/Payments:CI").unwrap();
+
+        assert_eq!(path.parent(), Some(run_dir(&identifier).as_path()));
+        assert_eq!(path.file_name().unwrap(), "Features_Payments_CI.xcresult");
+    }
+
+    #[test]
+    fn rerun_bundles_stay_in_the_run_directory() {
+        let identifier = String::from("20260101_120000");
+        let kind_path = test_kind_xcresult_path(&identifier, "UI", "App/Core").unwrap();
+        let configuration_path = configuration_xcresult_path(&identifier, "Release/Beta", "App, Core").unwrap();
+
+        assert_eq!(kind_path.parent(), Some(run_dir(&identifier).as_path()));
+        assert_eq!(kind_path.file_name().unwrap(), "UI-App_Core.xcresult");
+        assert_eq!(configuration_path.parent(), Some(run_dir(&identifier).as_path()));
+        assert_eq!(configuration_path.file_name().unwrap(), "Release_Beta-App, Core.xcresult");
+    }
+
+    #[test]
+    fn scheme_test_reports_stay_in_the_run_directory() {
+        let identifier = String::from("20260101_120000");
+        let report_name = format!("{}-{}", safe_file_name("Features/Payments"), "xcpretty_report.html");
+        let path = xcpretty_report_path(&identifier, &report_name).unwrap();
+
+        assert_eq!(path.parent(), Some(run_dir(&identifier).as_path()));
+        assert_eq!(path.file_name().unwrap(), "Features_Payments-xcpretty_report.html");
+    }
+}
@@ -0,0 +1,268 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+use chrono::{Datelike, NaiveDate};
+use polars::frame::DataFrame;
+use polars::prelude::{DataType, JsonFormat, JsonWriter, SerWriter};
+use serde::Serialize;
+
+use crate::cli::{Aggregate, GateOptions, ReportOptions};
+use crate::err::{CommandExecutionError, ThresholdError, XCReportError};
+use crate::fs::get_workdir;
+use crate::metadata::RunMetadata;
+
+/// A squad whose coverage is below the required threshold.
+pub struct GateViolation {
+    pub squad: String,
+    pub coverage: f64,
+    pub exact_coverage: f64
+}
+
+/// The coverage every squad must reach on `date`, `None` when no gate is configured.
+///
+/// With a ramp, the threshold rises from `--min-coverage` by `--ramp-per-month` for every full month
+/// since `--ramp-start`, capped at `--ramp-target`.
+pub fn required_coverage(options: &GateOptions, date: NaiveDate) -> Option<f64> {
+    let min_coverage = options.min_coverage?;
+
+    let Some(ramp_start) = options.ramp_start else {
+        return Some(min_coverage)
+    };
+
+    let months = full_months_between(ramp_start, date);
+    let ramped = min_coverage + months as f64 * options.ramp_per_month;
+
+    Some(ramped.min(options.ramp_target.unwrap_or(100.0)))
+}
+
+/// The summary report column checked by the gate, rounded for display, along with the count columns
+/// whose ratio it is.
+pub struct Metric {
+    pub column: &'static str,
+    pub numerator: &'static str,
+    pub denominator: &'static str
+}
+
+pub fn metric(aggregate: Aggregate) -> Metric {
+    match aggregate {
+        Aggregate::Lines | Aggregate::Both => Metric {
+            column: "Coverage %",
+            numerator: "Covered Lines",
+            denominator: "Executable Lines"
+        },
+        Aggregate::Files => Metric {
+            column: "Files Above Threshold %",
+            numerator: "Files Above Threshold",
+            denominator: "Count"
+        }
+    }
+}
+
+/// Lists the squads of the summary report whose metric is below `threshold`. The metric is compared
+/// at full precision, so a squad just below the threshold fails even when its rounded value reaches it.
+pub fn evaluate(report: &DataFrame, metric: &Metric, threshold: f64) -> Result<Vec<GateViolation>, XCReportError> {
+    let squads = report.column("Squad")
+        .and_then(|c| c.str())
+        .map_err(XCReportError::Polars)?;
+    let coverages = report.column(metric.column)
+        .and_then(|c| c.f64())
+        .map_err(XCReportError::Polars)?;
+    let numerators = report.column(metric.numerator)
+        .and_then(|c| c.cast(&DataType::Float64))
+        .map_err(XCReportError::Polars)?;
+    let denominators = report.column(metric.denominator)
+        .and_then(|c| c.cast(&DataType::Float64))
+        .map_err(XCReportError::Polars)?;
+
+    let numerators = numerators.f64().map_err(XCReportError::Polars)?;
+    let denominators = denominators.f64().map_err(XCReportError::Polars)?;
+
+    let violations = squads
+        .into_iter()
+        .zip(coverages)
+        .zip(numerators.into_iter().zip(denominators))
+        .filter_map(|((squad, coverage), ratio)| {
+            // Squads without anything to cover can't fall below a threshold.
+            let (Some(numerator), Some(denominator)) = ratio else {
+                return None
+            };
+            if denominator == 0.0 {
+                return None
+            }
+
+            (numerator * 100.0 < threshold * denominator).then(|| GateViolation {
+                squad: squad.unwrap_or("N/A").to_string(),
+                coverage: coverage.unwrap_or_default(),
+                exact_coverage: numerator * 100.0 / denominator
+            })
+        })
+        .collect();
+
+    Ok(violations)
+}
+
+/// The squads of the summary report below the gate configured for today, along with the required
+/// coverage, `None` when no gate is configured.
+pub fn violations(report: &DataFrame, report_options: &ReportOptions) -> Result<Option<(f64, Vec<GateViolation>)>, XCReportError> {
+    let today = chrono::offset::Local::now().date_naive();
+    let Some(threshold) = required_coverage(&report_options.gate, today) else {
+        return Ok(None)
+    };
+
+    let mut violations = evaluate(report, &metric(report_options.aggregate), threshold)?;
+    if report_options.gate.gate_skip_unowned {
+        violations.retain(|violation| violation.squad != report_options.unowned_name);
+    }
+
+    Ok(Some((threshold, violations)))
+}
+
+/// Evaluates the configured gate against the summary report, printing and failing on violations.
+pub fn check(report: &DataFrame, report_options: &ReportOptions) -> Result<(), XCReportError> {
+    let precision = report_options.rounding.precision as usize;
+    let Some((threshold, violations)) = violations(report, report_options)? else {
+        return Ok(())
+    };
+
+    if violations.is_empty() {
+        println!("\nAll squads meet the required coverage of {:.*}%", precision, threshold);
+        return Ok(())
+    }
+
+    println!("\nSquads below the required coverage of {:.*}%:", precision, threshold);
+    for violation in &violations {
+        if violation.coverage >= threshold {
+            println!("  {}: {:.*}% ({:.*}% before rounding)", violation.squad, precision, violation.coverage, precision + 3, violation.exact_coverage);
+        } else {
+            println!("  {}: {:.*}%", violation.squad, precision, violation.coverage);
+        }
+    }
+
+    Err(XCReportError::Threshold(ThresholdError::Violation { count: violations.len(), threshold }))
+}
+
+/// Share of the executable lines of the full report in files matching no squad, in %.
+pub fn unowned_share(full_report: &DataFrame, unowned_name: &str) -> Result<f64, XCReportError> {
+    let squads = full_report.column("Squad")
+        .and_then(|c| c.str())
+        .map_err(XCReportError::Polars)?;
+    let executable_lines = full_report.column("Executable Lines")
+        .and_then(|c| c.cast(&DataType::Float64))
+        .map_err(XCReportError::Polars)?;
+    let executable_lines = executable_lines.f64().map_err(XCReportError::Polars)?;
+
+    let (unowned, total) = squads
+        .into_iter()
+        .zip(executable_lines)
+        .fold((0.0, 0.0), |(unowned, total), (squad, lines)| {
+            let lines = lines.unwrap_or_default();
+            let unowned_lines = if squad == Some(unowned_name) { lines } else { 0.0 };
+            (unowned + unowned_lines, total + lines)
+        });
+
+    if total == 0.0 {
+        return Ok(0.0)
+    }
+
+    Ok(unowned * 100.0 / total)
+}
+
+/// Fails when the files matching no squad hold more than `--max-unowned-share` of the executable lines.
+pub fn check_unowned_share(full_report: &DataFrame, report_options: &ReportOptions) -> Result<(), XCReportError> {
+    let Some(max_share) = report_options.gate.max_unowned_share else {
+        return Ok(())
+    };
+
+    let precision = report_options.rounding.precision as usize;
+    let share = unowned_share(full_report, &report_options.unowned_name)?;
+
+    if share > max_share {
+        println!("\n{} holds {:.*}% of the executable lines, more than the allowed {:.*}%", report_options.unowned_name, precision, share, precision, max_share);
+        return Err(XCReportError::Threshold(ThresholdError::UnownedShare { share, max_share }))
+    }
+
+    println!("\n{} holds {:.*}% of the executable lines, within the allowed {:.*}%", report_options.unowned_name, precision, share, precision, max_share);
+
+    Ok(())
+}
+
+/// What a `--gate-command` reads on stdin.
+#[derive(Serialize)]
+struct GateCommandInput<'a> {
+    metadata: &'a RunMetadata,
+    /// Directory with the reports of the run, for commands that need the full report.
+    run_dir: String,
+    /// Coverage required by `--min-coverage` today, if set.
+    required_coverage: Option<f64>,
+    /// Rows of the summary report.
+    summary: serde_json::Value
+}
+
+/// Runs every `--gate-command` with the run metadata and summary report as JSON on stdin. A command
+/// rejects the run by exiting with a non-zero status, the lines it prints being the reasons.
+pub fn check_commands(report: &DataFrame, metadata: &RunMetadata, report_options: &ReportOptions) -> Result<(), XCReportError> {
+    if report_options.gate.gate_command.is_empty() {
+        return Ok(())
+    }
+
+    let mut summary: Vec<u8> = vec![];
+    JsonWriter::new(&mut summary)
+        .with_json_format(JsonFormat::Json)
+        .finish(&mut report.clone())
+        .map_err(XCReportError::Polars)?;
+
+    let input = GateCommandInput {
+        metadata,
+        run_dir: get_workdir(&metadata.identifier)?.to_string_lossy().into_owned(),
+        required_coverage: required_coverage(&report_options.gate, chrono::offset::Local::now().date_naive()),
+        summary: serde_json::from_slice(&summary).map_err(XCReportError::Serde)?
+    };
+    let input = serde_json::to_vec(&input)
+        .map_err(XCReportError::Serde)?;
+
+    let mut rejections: Vec<String> = vec![];
+
+    for gate_command in &report_options.gate.gate_command {
+        let mut child = Command::new(gate_command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| XCReportError::CommandExecution(CommandExecutionError::GateCommand(e)))?;
+
+        // Commands deciding without reading the input close stdin early.
+        if let Some(mut stdin) = child.stdin.take() {
+            let _ = stdin.write_all(&input);
+        }
+
+        let output = child
+            .wait_with_output()
+            .map_err(|e| XCReportError::CommandExecution(CommandExecutionError::GateCommand(e)))?;
+
+        let command_name = gate_command.to_string_lossy().into_owned();
+        let outcome = if output.status.success() { "passed" } else { "rejected the run" };
+        println!("\nGate command {} {}", command_name, outcome);
+        for line in String::from_utf8_lossy(&output.stdout).lines().filter(|line| !line.trim().is_empty()) {
+            println!("  {}", line);
+        }
+
+        if !output.status.success() {
+            rejections.push(command_name);
+        }
+    }
+
+    if !rejections.is_empty() {
+        return Err(XCReportError::Threshold(ThresholdError::CommandRejected { commands: rejections }))
+    }
+
+    Ok(())
+}
+
+fn full_months_between(start: NaiveDate, end: NaiveDate) -> u32 {
+    if end <= start {
+        return 0
+    }
+
+    let months = (end.year() - start.year()) * 12 + end.month() as i32 - start.month() as i32;
+    let months = if end.day() < start.day() { months - 1 } else { months };
+
+    months.max(0) as u32
+}
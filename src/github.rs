@@ -0,0 +1,264 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::cli::GitHubOptions;
+use crate::comment::COMMENT_MARKER;
+use crate::err::{GitHubError, XCReportError};
+
+pub const LABEL_OK: &str = "coverage:ok";
+pub const LABEL_REGRESSION: &str = "coverage:regression";
+
+/// Name of the check run created with `--github-checks`.
+const CHECK_RUN_NAME: &str = "xcreport coverage";
+
+/// Annotations GitHub accepts per check run request, the rest being added by updating the check run.
+const ANNOTATIONS_PER_REQUEST: usize = 50;
+
+/// An annotation of a check run, on a line range of a repository file.
+#[derive(Serialize)]
+pub struct Annotation {
+    pub path: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub annotation_level: &'static str,
+    pub message: String
+}
+
+impl Annotation {
+    /// One warning per run of consecutive uncovered lines of the file, the lines being sorted.
+    pub fn uncovered_lines(path: &str, lines: &[usize]) -> Vec<Annotation> {
+        let mut ranges: Vec<(usize, usize)> = vec![];
+
+        for &line in lines {
+            match ranges.last_mut() {
+                Some((_, end)) if *end + 1 == line => *end = line,
+                _ => ranges.push((line, line))
+            }
+        }
+
+        ranges
+            .into_iter()
+            .map(|(start_line, end_line)| Annotation {
+                path: path.to_string(),
+                start_line,
+                end_line,
+                annotation_level: "warning",
+                message: if start_line == end_line {
+                    format!("Line {} is not covered by tests.", start_line)
+                } else {
+                    format!("Lines {}-{} are not covered by tests.", start_line, end_line)
+                }
+            })
+            .collect()
+    }
+}
+
+/// A pull request and the credentials to change it through the GitHub REST API.
+pub struct PullRequest {
+    api_url: String,
+    repository: String,
+    number: u64,
+    token: String
+}
+
+impl PullRequest {
+    /// Resolves the pull request from the options, falling back to the variables GitHub Actions sets.
+    pub fn from_options(options: &GitHubOptions) -> Result<Self, XCReportError> {
+        let repository = repository(options)?;
+
+        // Pull request workflows check out refs/pull/<number>/merge.
+        let number = options.github_pr
+            .or_else(|| {
+                std::env::var("GITHUB_REF")
+                    .ok()?
+                    .strip_prefix("refs/pull/")?
+                    .split('/')
+                    .next()?
+                    .parse()
+                    .ok()
+            })
+            .ok_or(XCReportError::GitHub(GitHubError::MissingPullRequest))?;
+
+        Ok(PullRequest {
+            api_url: api_url(options),
+            repository,
+            number,
+            token: token(options)?
+        })
+    }
+
+    pub fn add_labels(&self, labels: &[&str]) -> Result<(), XCReportError> {
+        let url = format!("{}/repos/{}/issues/{}/labels", self.api_url, self.repository, self.number);
+
+        self.request("POST", &url)
+            .send_json(json!({ "labels": labels }))
+            .map_err(|e| XCReportError::GitHub(GitHubError::Request(Box::new(e))))?;
+
+        Ok(())
+    }
+
+    /// Removes the label, succeeding when the pull request does not have it.
+    pub fn remove_label(&self, label: &str) -> Result<(), XCReportError> {
+        let url = format!("{}/repos/{}/issues/{}/labels/{}", self.api_url, self.repository, self.number, label);
+
+        match self.request("DELETE", &url).call() {
+            Ok(_) | Err(ureq::Error::Status(404, _)) => Ok(()),
+            Err(e) => Err(XCReportError::GitHub(GitHubError::Request(Box::new(e))))
+        }
+    }
+
+    /// Updates the comment starting with `marker`, or posts a new one when there is none.
+    pub fn upsert_comment(&self, marker: &str, body: &str) -> Result<(), XCReportError> {
+        let comments_url = format!("{}/repos/{}/issues/{}/comments", self.api_url, self.repository, self.number);
+        let mut page = 1;
+
+        loop {
+            let comments: Vec<Value> = self.request("GET", &comments_url)
+                .query("per_page", "100")
+                .query("page", &page.to_string())
+                .call()
+                .map_err(|e| XCReportError::GitHub(GitHubError::Request(Box::new(e))))?
+                .into_json()
+                .map_err(XCReportError::FileIO)?;
+
+            let existing_id = comments
+                .iter()
+                .find(|comment| comment["body"].as_str().is_some_and(|text| text.starts_with(marker)))
+                .and_then(|comment| comment["id"].as_u64());
+
+            if let Some(id) = existing_id {
+                let url = format!("{}/repos/{}/issues/comments/{}", self.api_url, self.repository, id);
+                self.request("PATCH", &url)
+                    .send_json(json!({ "body": body }))
+                    .map_err(|e| XCReportError::GitHub(GitHubError::Request(Box::new(e))))?;
+                return Ok(())
+            }
+
+            if comments.len() < 100 {
+                break
+            }
+            page += 1;
+        }
+
+        self.request("POST", &comments_url)
+            .send_json(json!({ "body": body }))
+            .map_err(|e| XCReportError::GitHub(GitHubError::Request(Box::new(e))))?;
+
+        Ok(())
+    }
+
+    fn request(&self, method: &str, url: &str) -> ureq::Request {
+        request(method, url, &self.token)
+    }
+}
+
+fn repository(options: &GitHubOptions) -> Result<String, XCReportError> {
+    options.github_repository
+        .clone()
+        .or_else(|| std::env::var("GITHUB_REPOSITORY").ok())
+        .ok_or(XCReportError::GitHub(GitHubError::MissingRepository))
+}
+
+fn token(options: &GitHubOptions) -> Result<String, XCReportError> {
+    options.github_token
+        .clone()
+        .or_else(|| std::env::var("GITHUB_TOKEN").ok())
+        .ok_or(XCReportError::GitHub(GitHubError::MissingToken))
+}
+
+fn api_url(options: &GitHubOptions) -> String {
+    options.github_api_url.trim_end_matches('/').to_string()
+}
+
+fn request(method: &str, url: &str, token: &str) -> ureq::Request {
+    ureq::request(method, url)
+        .set("Accept", "application/vnd.github+json")
+        .set("Authorization", &format!("Bearer {}", token))
+        .set("User-Agent", "xcreport")
+        .set("X-GitHub-Api-Version", "2022-11-28")
+}
+
+/// The commit to create the check run on: the given one, else the head of the pull request in GitHub Actions,
+/// whose checkout is a merge commit the pull request diff doesn't show, else the checked out one.
+fn check_head_sha(options: &GitHubOptions, commit: Option<&str>) -> Result<String, XCReportError> {
+    let event_head_sha = || -> Option<String> {
+        let event = std::fs::read_to_string(std::env::var("GITHUB_EVENT_PATH").ok()?).ok()?;
+        let event: Value = serde_json::from_str(&event).ok()?;
+
+        event["pull_request"]["head"]["sha"].as_str().map(str::to_string)
+    };
+
+    options.github_sha
+        .clone()
+        .or_else(event_head_sha)
+        .or_else(|| commit.map(str::to_string))
+        .ok_or(XCReportError::GitHub(GitHubError::MissingCommit))
+}
+
+/// Labels the pull request with `coverage:ok` or `coverage:regression`, removing the other one.
+pub fn label_gate_outcome(options: &GitHubOptions, passed: bool) -> Result<(), XCReportError> {
+    let pull_request = PullRequest::from_options(options)?;
+    let (label, stale_label) = if passed { (LABEL_OK, LABEL_REGRESSION) } else { (LABEL_REGRESSION, LABEL_OK) };
+
+    pull_request.remove_label(stale_label)?;
+    pull_request.add_labels(&[label])?;
+
+    println!("\nLabeled pull request #{} with {}", pull_request.number, label);
+
+    Ok(())
+}
+
+/// Posts the coverage comment on the pull request, replacing the one of an earlier run.
+pub fn comment_coverage(options: &GitHubOptions, comment: &str) -> Result<(), XCReportError> {
+    let pull_request = PullRequest::from_options(options)?;
+    pull_request.upsert_comment(COMMENT_MARKER, comment)?;
+
+    println!("\nCommented the coverage on pull request #{}", pull_request.number);
+
+    Ok(())
+}
+
+/// Creates a completed check run on the commit with the annotations, which GitHub shows inline in the pull request diff.
+pub fn create_check_run(
+    options: &GitHubOptions,
+    commit: Option<&str>,
+    conclusion: &str,
+    title: &str,
+    summary: &str,
+    annotations: &[Annotation]
+) -> Result<(), XCReportError> {
+    let token = token(options)?;
+    let check_runs_url = format!("{}/repos/{}/check-runs", api_url(options), repository(options)?);
+
+    let mut batches = annotations.chunks(ANNOTATIONS_PER_REQUEST);
+    let first_batch = batches.next().unwrap_or_default();
+
+    let check_run: Value = request("POST", &check_runs_url, &token)
+        .send_json(json!({
+            "name": CHECK_RUN_NAME,
+            "head_sha": check_head_sha(options, commit)?,
+            "status": "completed",
+            "conclusion": conclusion,
+            "output": { "title": title, "summary": summary, "annotations": first_batch }
+        }))
+        .map_err(|e| XCReportError::GitHub(GitHubError::Request(Box::new(e))))?
+        .into_json()
+        .map_err(XCReportError::FileIO)?;
+
+    // Annotations sent with an update are added to the ones the check run already has.
+    if let Some(id) = check_run["id"].as_u64() {
+        let check_run_url = format!("{}/{}", check_runs_url, id);
+
+        for batch in batches {
+            request("PATCH", &check_run_url, &token)
+                .send_json(json!({
+                    "output": { "title": title, "summary": summary, "annotations": batch }
+                }))
+                .map_err(|e| XCReportError::GitHub(GitHubError::Request(Box::new(e))))?;
+        }
+    }
+
+    println!("\nCreated the {:?} check run with {} annotation(s)", CHECK_RUN_NAME, annotations.len());
+
+    Ok(())
+}
@@ -0,0 +1,97 @@
+use serde_json::{json, Value};
+
+use crate::cli::GitLabOptions;
+use crate::err::{GitLabError, XCReportError};
+
+/// API of gitlab.com, for runs outside of GitLab CI.
+const DEFAULT_API_URL: &str = "https://gitlab.com/api/v4";
+
+/// A merge request and the credentials to comment on it through the GitLab REST API.
+pub struct MergeRequest {
+    api_url: String,
+    project: String,
+    iid: u64,
+    token: String
+}
+
+impl MergeRequest {
+    /// Resolves the merge request from the options, falling back to the variables GitLab CI sets.
+    /// `None` outside of a merge request pipeline.
+    pub fn from_options(options: &GitLabOptions) -> Result<Option<Self>, XCReportError> {
+        let iid = options.gitlab_mr
+            .or_else(|| std::env::var("CI_MERGE_REQUEST_IID").ok()?.parse().ok());
+        let Some(iid) = iid else {
+            return Ok(None)
+        };
+
+        let project = options.gitlab_project
+            .clone()
+            .or_else(|| std::env::var("CI_PROJECT_ID").ok())
+            .ok_or(XCReportError::GitLab(GitLabError::MissingProject))?;
+        let token = options.gitlab_token
+            .clone()
+            .or_else(|| std::env::var("GITLAB_TOKEN").ok())
+            .ok_or(XCReportError::GitLab(GitLabError::MissingToken))?;
+        let api_url = options.gitlab_api_url
+            .clone()
+            .or_else(|| std::env::var("CI_API_V4_URL").ok())
+            .unwrap_or_else(|| DEFAULT_API_URL.to_string());
+
+        Ok(Some(MergeRequest {
+            api_url: api_url.trim_end_matches('/').to_string(),
+            // Projects given by path, e.g. group/app, are one URL segment.
+            project: project.replace('/', "%2F"),
+            iid,
+            token
+        }))
+    }
+
+    pub fn iid(&self) -> u64 {
+        self.iid
+    }
+
+    /// Updates the note starting with `marker`, or posts a new one when there is none.
+    pub fn upsert_note(&self, marker: &str, body: &str) -> Result<(), XCReportError> {
+        let notes_url = format!("{}/projects/{}/merge_requests/{}/notes", self.api_url, self.project, self.iid);
+        let mut page = 1;
+
+        loop {
+            let notes: Vec<Value> = self.request("GET", &notes_url)
+                .query("per_page", "100")
+                .query("page", &page.to_string())
+                .call()
+                .map_err(|e| XCReportError::GitLab(GitLabError::Request(Box::new(e))))?
+                .into_json()
+                .map_err(XCReportError::FileIO)?;
+
+            let existing_id = notes
+                .iter()
+                .find(|note| note["body"].as_str().is_some_and(|text| text.starts_with(marker)))
+                .and_then(|note| note["id"].as_u64());
+
+            if let Some(id) = existing_id {
+                self.request("PUT", &format!("{}/{}", notes_url, id))
+                    .send_json(json!({ "body": body }))
+                    .map_err(|e| XCReportError::GitLab(GitLabError::Request(Box::new(e))))?;
+                return Ok(())
+            }
+
+            if notes.len() < 100 {
+                break
+            }
+            page += 1;
+        }
+
+        self.request("POST", &notes_url)
+            .send_json(json!({ "body": body }))
+            .map_err(|e| XCReportError::GitLab(GitLabError::Request(Box::new(e))))?;
+
+        Ok(())
+    }
+
+    fn request(&self, method: &str, url: &str) -> ureq::Request {
+        ureq::request(method, url)
+            .set("PRIVATE-TOKEN", &self.token)
+            .set("User-Agent", "xcreport")
+    }
+}
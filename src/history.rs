@@ -0,0 +1,291 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use polars::frame::DataFrame;
+use polars::prelude::*;
+use sha2::{Digest, Sha256};
+
+use crate::cli::RoundingOptions;
+use crate::df;
+use crate::err::XCReportError;
+use crate::events;
+use crate::fs::{full_report_path, home_path, reserve_identifier};
+use crate::index;
+use crate::manifest;
+use crate::metadata::{self, RunMetadata};
+
+/// The squad summary of a previous run, rebuilt from its full report.
+pub struct HistoricalRun {
+    pub identifier: String,
+    pub summary: DataFrame,
+    pub metadata: Option<RunMetadata>
+}
+
+/// Outcome of merging history directories into the home directory.
+pub struct MergeSummary {
+    /// Identifiers the runs were imported under, which differ from the original ones on clashes.
+    pub imported: Vec<String>,
+    /// Runs already in the home directory with the same files.
+    pub skipped: usize
+}
+
+/// Coverage % of every squad per run identifier, in run order.
+pub type SquadTrends<'a> = BTreeMap<String, Vec<(&'a String, f64)>>;
+
+/// Summary columns exported as trend metrics, with their metric names.
+const TREND_METRICS: [(&str, &str); 4] = [
+    ("Coverage %", "coverage_pct"),
+    ("Covered Lines", "covered_lines"),
+    ("Executable Lines", "executable_lines"),
+    ("Count", "files")
+];
+
+/// Identifiers of the directories under the home directory, oldest first.
+pub fn run_identifiers() -> Result<Vec<String>, XCReportError> {
+    let home_path = home_path()?;
+    if !home_path.is_dir() {
+        return Ok(vec![])
+    }
+
+    let mut identifiers: Vec<String> = fs::read_dir(home_path)
+        .map_err(XCReportError::FileIO)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        // Hidden directories are runs still being merged in.
+        .filter(|name| !name.starts_with('.'))
+        .collect();
+    identifiers.sort();
+
+    Ok(identifiers)
+}
+
+/// Every indexed run that still has its full report, oldest first.
+pub fn load_history(rounding: &RoundingOptions) -> Result<Vec<HistoricalRun>, XCReportError> {
+    let mut runs: Vec<HistoricalRun> = vec![];
+
+    for identifier in index::run_identifiers()? {
+        let full_report_path = full_report_path(&identifier)?;
+        if !full_report_path.is_file() {
+            continue
+        }
+
+        let summary = match df::read_full_report(&identifier).and_then(|report| df::process_report(&report, rounding)) {
+            Ok(summary) => summary,
+            Err(e) => {
+                events::warning(&format!("skipping run {}: {}", identifier, e));
+                continue
+            }
+        };
+
+        let metadata = metadata::load_metadata(&identifier);
+        runs.push(HistoricalRun { identifier, summary, metadata });
+    }
+
+    Ok(runs)
+}
+
+/// Coverage % of every squad in the last `runs` runs before the given one, oldest first.
+pub fn recent_squad_coverages(identifier: &String, runs: usize, rounding: &RoundingOptions) -> Result<Vec<(String, Vec<f64>)>, XCReportError> {
+    let history: Vec<HistoricalRun> = load_history(rounding)?
+        .into_iter()
+        .filter(|run| run.identifier < *identifier)
+        .collect();
+    let recent_runs = &history[history.len().saturating_sub(runs)..];
+
+    let coverages = squad_trends(recent_runs)?
+        .into_iter()
+        .map(|(squad, trend)| (squad, trend.into_iter().map(|(_, coverage)| coverage).collect()))
+        .collect();
+
+    Ok(coverages)
+}
+
+/// Trends of every squad across the runs. Runs a squad is missing from are left out of its trend.
+pub fn squad_trends(runs: &[HistoricalRun]) -> Result<SquadTrends<'_>, XCReportError> {
+    let mut trends = SquadTrends::new();
+
+    for run in runs {
+        let squads = run.summary.column("Squad")
+            .and_then(|c| c.str())
+            .map_err(XCReportError::Polars)?;
+        let coverages = run.summary.column("Coverage %")
+            .and_then(|c| c.f64())
+            .map_err(XCReportError::Polars)?;
+
+        for (squad, coverage) in squads.into_iter().zip(coverages) {
+            let (Some(squad), Some(coverage)) = (squad, coverage) else {
+                continue
+            };
+
+            trends
+                .entry(squad.to_string())
+                .or_default()
+                .push((&run.identifier, coverage));
+        }
+    }
+
+    Ok(trends)
+}
+
+/// The summaries of the runs as a tidy table with a row per run, squad and metric, which BI tools
+/// pivot as they need. The date is the one of the run's metadata, or of its identifier without one.
+pub fn trend_table(runs: &[HistoricalRun]) -> Result<DataFrame, XCReportError> {
+    let mut run_ids: Vec<&str> = vec![];
+    let mut dates: Vec<&str> = vec![];
+    let mut squads: Vec<String> = vec![];
+    let mut metrics: Vec<&str> = vec![];
+    let mut values: Vec<f64> = vec![];
+
+    for run in runs {
+        let timestamp = run.metadata
+            .as_ref()
+            .map(|metadata| metadata.timestamp.as_str())
+            .unwrap_or(run.identifier.as_str());
+        let date = timestamp.get(..10).unwrap_or(timestamp);
+
+        let run_squads = run.summary.column("Squad")
+            .and_then(|c| c.str())
+            .map_err(XCReportError::Polars)?;
+
+        for (column, metric) in TREND_METRICS {
+            let run_values = run.summary.column(column)
+                .and_then(|c| c.cast(&DataType::Float64))
+                .map_err(XCReportError::Polars)?;
+            let run_values = run_values.f64().map_err(XCReportError::Polars)?;
+
+            for (squad, value) in run_squads.into_iter().zip(run_values) {
+                let (Some(squad), Some(value)) = (squad, value) else {
+                    continue
+                };
+
+                run_ids.push(&run.identifier);
+                dates.push(date);
+                squads.push(squad.to_string());
+                metrics.push(metric);
+                values.push(value);
+            }
+        }
+    }
+
+    df!(
+        "run_id" => run_ids,
+        "date" => dates,
+        "squad" => squads,
+        "metric" => metrics,
+        "value" => values
+    )
+        .map_err(XCReportError::Polars)
+}
+
+/// Copies the runs of history directories from other agents, e.g. the `~/.xcreport` of parallel CI jobs saved
+/// as artifacts, into the home directory. Runs already there with the same files are skipped, and runs whose
+/// identifier is taken by a different run are imported under the next free one. Every run is copied to a hidden
+/// directory first and renamed into place, so commands reading the history never see half of a run.
+pub fn merge_history(stores: &[PathBuf]) -> Result<MergeSummary, XCReportError> {
+    let home_path = home_path()?;
+    let mut summary = MergeSummary { imported: vec![], skipped: 0 };
+
+    for store in stores {
+        for (identifier, run_dir) in store_runs(store)? {
+            if has_identical_run(&home_path, &identifier, &run_digest(&run_dir)?)? {
+                summary.skipped += 1;
+                continue
+            }
+
+            let partial_dir = home_path.join(format!(".merge-{}-{}", identifier, std::process::id()));
+            if let Err(e) = copy_dir(&run_dir, &partial_dir) {
+                let _ = fs::remove_dir_all(&partial_dir);
+                return Err(XCReportError::FileIO(e))
+            }
+
+            let merged_identifier = reserve_identifier(&identifier)?;
+            fs::rename(&partial_dir, home_path.join(&merged_identifier))
+                .map_err(XCReportError::FileIO)?;
+
+            index::record_saved_run(&merged_identifier)?;
+            summary.imported.push(merged_identifier);
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Directories of the store holding a full report or run metadata, with their identifiers, oldest first.
+fn store_runs(store: &Path) -> Result<Vec<(String, PathBuf)>, XCReportError> {
+    let mut runs: Vec<(String, PathBuf)> = fs::read_dir(store)
+        .map_err(XCReportError::FileIO)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().join("full_report.csv").is_file() || entry.path().join("metadata.json").is_file())
+        .filter_map(|entry| Some((entry.file_name().into_string().ok()?, entry.path())))
+        .filter(|(identifier, _)| !identifier.starts_with('.'))
+        .collect();
+    runs.sort();
+
+    Ok(runs)
+}
+
+/// Whether the run, or one imported under a numbered identifier by an earlier merge, has the digest.
+fn has_identical_run(home_path: &Path, identifier: &str, digest: &str) -> Result<bool, XCReportError> {
+    let mut attempt: usize = 0;
+    loop {
+        let candidate = match attempt {
+            0 => home_path.join(identifier),
+            _ => home_path.join(format!("{}-{}", identifier, attempt))
+        };
+
+        if !candidate.is_dir() {
+            return Ok(false)
+        }
+        if run_digest(&candidate)? == digest {
+            return Ok(true)
+        }
+
+        attempt += 1;
+    }
+}
+
+/// Hashes the relative paths, sizes and contents of every file of a run directory.
+fn run_digest(run_dir: &Path) -> Result<String, XCReportError> {
+    let mut files: Vec<PathBuf> = vec![];
+    manifest::collect_files(run_dir, &mut files)
+        .map_err(XCReportError::FileIO)?;
+    files.sort();
+
+    let mut hasher = Sha256::new();
+
+    for path in &files {
+        let relative_path = path.strip_prefix(run_dir).unwrap_or(path);
+        hasher.update(relative_path.to_string_lossy().as_bytes());
+
+        let mut file = File::open(path)
+            .map_err(XCReportError::FileIO)?;
+        let size = file.metadata()
+            .map_err(XCReportError::FileIO)?
+            .len();
+        hasher.update(size.to_le_bytes());
+        io::copy(&mut file, &mut hasher)
+            .map_err(XCReportError::FileIO)?;
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let path = entry.path();
+        let target = to.join(entry.file_name());
+
+        if path.is_dir() {
+            copy_dir(&path, &target)?;
+        } else if path.is_file() {
+            fs::copy(&path, &target)?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use std::process::Command;
+
+use crate::cli::ReportOptions;
+use crate::err::{CommandExecutionError, XCReportError};
+use crate::fs::{artifact_manifest_path, full_report_path, get_workdir, report_path};
+use crate::metadata::RunMetadata;
+
+/// Points of a run the `--on-*` shell commands are run at.
+#[derive(Clone, Copy)]
+pub enum Hook {
+    /// The reports of the run are written.
+    ReportGenerated,
+    /// The coverage gate failed, in `--threshold-mode warn` too.
+    ThresholdViolation
+}
+
+impl Hook {
+    /// Name of the hook, as its option and config key are spelled.
+    fn name(&self) -> &'static str {
+        match self {
+            Hook::ReportGenerated => "on_report_generated",
+            Hook::ThresholdViolation => "on_threshold_violation"
+        }
+    }
+
+    fn commands<'a>(&self, report_options: &'a ReportOptions) -> &'a [String] {
+        match self {
+            Hook::ReportGenerated => &report_options.hooks.on_report_generated,
+            Hook::ThresholdViolation => &report_options.hooks.on_threshold_violation
+        }
+    }
+}
+
+/// Runs the shell commands of the hook one after the other with `sh -c`, giving them the run in
+/// `XCREPORT_HOOK_*` environment variables: the identifier, the run directory, the summary, the full
+/// report, the manifest listing every file of the run, and for a violation, what the gate rejected.
+/// Fails on the first command exiting with a non-zero status.
+pub fn run(hook: Hook, metadata: &RunMetadata, report_options: &ReportOptions, violation: Option<&str>) -> Result<(), XCReportError> {
+    let commands = hook.commands(report_options);
+    if commands.is_empty() {
+        return Ok(())
+    }
+
+    let identifier = &metadata.identifier;
+    let report_path = match &report_options.output_file {
+        Some(output_file) => output_file.clone(),
+        None => report_path(identifier)?
+    };
+
+    for command in commands {
+        println!("\nRunning the {} hook {}", hook.name(), command);
+
+        let mut hook_command = Command::new("sh");
+        hook_command
+            .args(["-c", command])
+            .env("XCREPORT_HOOK_EVENT", hook.name())
+            .env("XCREPORT_HOOK_IDENTIFIER", identifier)
+            .env("XCREPORT_HOOK_RUN_DIR", get_workdir(identifier)?)
+            .env("XCREPORT_HOOK_REPORT", &report_path)
+            .env("XCREPORT_HOOK_FULL_REPORT", full_report_path(identifier)?)
+            .env("XCREPORT_HOOK_MANIFEST", artifact_manifest_path(identifier)?);
+        if let Some(violation) = violation {
+            hook_command.env("XCREPORT_HOOK_VIOLATION", violation);
+        }
+
+        let status = hook_command
+            .status()
+            .map_err(|e| XCReportError::CommandExecution(CommandExecutionError::Hook(e)))?;
+
+        if !status.success() {
+            return Err(XCReportError::CommandExecution(CommandExecutionError::NonZeroExit {
+                desc: format!("{} hook {:?} exited with {}", hook.name(), command, status)
+            }))
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,322 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use polars::frame::DataFrame;
+
+use crate::data::{LineCoverage, TargetFile};
+use crate::err::XCReportError;
+use crate::history::SquadTrends;
+use crate::metadata::RunMetadata;
+
+const STYLE: &str = "
+body { font-family: -apple-system, Helvetica, sans-serif; margin: 2em; }
+table { border-collapse: collapse; }
+th, td { padding: 2px 8px; text-align: left; }
+tr:nth-child(even) { background: #f6f6f6; }
+.source td { font-family: Menlo, monospace; font-size: 12px; white-space: pre; padding: 0 8px; }
+.source td.gutter { text-align: right; color: #888; }
+.covered td.gutter { background: #c8f0c8; }
+.uncovered td.gutter { background: #f5c2c2; }
+.uncovered td.code { background: #fde8e8; }
+td.increase { color: #1a7f37; font-weight: bold; }
+td.decrease { color: #cf222e; font-weight: bold; }
+tr.below-threshold td { background: #fde8e8; color: #cf222e; }
+table.metadata { margin-bottom: 1em; color: #555; }
+svg.trend polyline { fill: none; stroke: #0969da; stroke-width: 1.5; }
+svg.trend circle { fill: #0969da; }
+svg.trend line { stroke: #ddd; }
+";
+
+/// A report file together with its line-level coverage and the path its source is read from.
+pub struct AnnotatedFile<'a> {
+    pub file: &'a TargetFile,
+    pub source_path: PathBuf,
+    pub lines: Vec<LineCoverage>
+}
+
+/// Writes the squad summary page and one annotated source page per file into `dir`, highlighting the
+/// squads below the coverage gate.
+pub fn save_html_report(
+    summary: &DataFrame,
+    top_uncovered: Option<&DataFrame>,
+    below_threshold: &[String],
+    files: &[TargetFile],
+    annotated: &[AnnotatedFile],
+    metadata: &RunMetadata,
+    dir: &Path
+) -> Result<PathBuf, XCReportError> {
+    let files_dir = dir.join("files");
+    fs::create_dir_all(&files_dir)
+        .map_err(XCReportError::FileIO)?;
+
+    let mut links: Vec<(&String, String)> = vec![];
+
+    for (index, annotated_file) in annotated.iter().enumerate() {
+        let page_name = format!("{}.html", index);
+        let page = render_source_page(annotated_file);
+
+        fs::write(files_dir.join(&page_name), page)
+            .map_err(XCReportError::FileIO)?;
+
+        links.push((annotated_file.file.file_path(), format!("files/{}", page_name)));
+    }
+
+    let index_path = dir.join("index.html");
+    fs::write(&index_path, render_summary_page(summary, top_uncovered, below_threshold, files, &links, metadata))
+        .map_err(XCReportError::FileIO)?;
+
+    Ok(index_path)
+}
+
+/// Writes the squad and file coverage deltas of a comparison as a single page.
+pub fn save_compare_html(squad_delta: &DataFrame, file_delta: &DataFrame, path: &Path) -> Result<(), XCReportError> {
+    let mut body = String::from("<h1>Coverage comparison</h1>\n<h2>Squads</h2>\n");
+    body.push_str(&render_dataframe(squad_delta, Some("Delta"), &[]));
+    body.push_str("<h2>Changed files</h2>\n");
+    body.push_str(&render_dataframe(file_delta, Some("Delta"), &[]));
+
+    fs::write(path, page("Coverage comparison", &body))
+        .map_err(XCReportError::FileIO)
+}
+
+/// Writes the dashboard index with the latest coverage and trend of every squad, and one page per
+/// squad with its coverage in every run, into `dir`.
+pub fn save_dashboard(
+    trends: &SquadTrends,
+    latest: Option<&RunMetadata>,
+    precision: usize,
+    dir: &Path
+) -> Result<PathBuf, XCReportError> {
+    let squads_dir = dir.join("squads");
+    fs::create_dir_all(&squads_dir)
+        .map_err(XCReportError::FileIO)?;
+
+    let mut body = String::from("<h1>Coverage dashboard</h1>\n");
+    if let Some(latest) = latest {
+        body.push_str("<table class=\"metadata\">\n");
+        for (label, value) in latest.rows() {
+            let _ = writeln!(body, "<tr><th>{}</th><td>{}</td></tr>", label, escape(&value));
+        }
+        body.push_str("</table>\n");
+    }
+    body.push_str("<table>\n<tr><th>Squad</th><th>Coverage %</th><th>Change</th><th>Trend</th></tr>\n");
+
+    for (index, (squad, trend)) in trends.iter().enumerate() {
+        let page_name = format!("{}.html", index);
+        fs::write(squads_dir.join(&page_name), render_squad_page(squad, trend, precision))
+            .map_err(XCReportError::FileIO)?;
+
+        let values: Vec<f64> = trend.iter().map(|(_, coverage)| *coverage).collect();
+        let (coverage, change) = match values.as_slice() {
+            [.., previous, latest] => (format!("{:.*}", precision, latest), Some(latest - previous)),
+            [latest] => (format!("{:.*}", precision, latest), None),
+            [] => (String::new(), None)
+        };
+        let (class, change) = match change {
+            Some(change) if change > 0.0 => ("increase", format!("+{:.*}", precision, change)),
+            Some(change) if change < 0.0 => ("decrease", format!("{:.*}", precision, change)),
+            Some(change) => ("", format!("{:.*}", precision, change)),
+            None => ("", String::new())
+        };
+
+        let _ = writeln!(
+            body,
+            "<tr><td><a href=\"squads/{}\">{}</a></td><td>{}</td><td class=\"{}\">{}</td><td>{}</td></tr>",
+            page_name,
+            escape(squad),
+            coverage,
+            class,
+            change,
+            render_trend_svg(&values, 160, 32)
+        );
+    }
+    body.push_str("</table>\n");
+
+    let index_path = dir.join("index.html");
+    fs::write(&index_path, page("Coverage dashboard", &body))
+        .map_err(XCReportError::FileIO)?;
+
+    Ok(index_path)
+}
+
+fn render_squad_page(squad: &str, trend: &[(&String, f64)], precision: usize) -> String {
+    let values: Vec<f64> = trend.iter().map(|(_, coverage)| *coverage).collect();
+
+    let mut body = format!("<p><a href=\"../index.html\">Back to dashboard</a></p>\n<h1>{}</h1>\n", escape(squad));
+    body.push_str(&render_trend_svg(&values, 640, 200));
+    body.push_str("\n<table>\n<tr><th>Run</th><th>Coverage %</th></tr>\n");
+    for (identifier, coverage) in trend.iter().rev() {
+        let _ = writeln!(body, "<tr><td>{}</td><td>{:.*}</td></tr>", escape(identifier), precision, coverage);
+    }
+    body.push_str("</table>\n");
+
+    page(squad, &body)
+}
+
+/// Draws the coverage values as a line on a 0-100% scale.
+fn render_trend_svg(values: &[f64], width: usize, height: usize) -> String {
+    let x = |index: usize| match values.len() {
+        0 | 1 => width as f64 / 2.0,
+        count => index as f64 * width as f64 / (count - 1) as f64
+    };
+    let y = |coverage: f64| height as f64 - coverage.clamp(0.0, 100.0) * height as f64 / 100.0;
+
+    let mut svg = format!(
+        "<svg class=\"trend\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\"><line x1=\"0\" y1=\"{mid}\" x2=\"{w}\" y2=\"{mid}\"/>",
+        w = width,
+        h = height,
+        mid = height as f64 / 2.0
+    );
+
+    if let [coverage] = values {
+        let _ = write!(svg, "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"2\"/>", x(0), y(*coverage));
+    } else {
+        let points: Vec<String> = values.iter()
+            .enumerate()
+            .map(|(index, coverage)| format!("{:.1},{:.1}", x(index), y(*coverage)))
+            .collect();
+        let _ = write!(svg, "<polyline points=\"{}\"/>", points.join(" "));
+    }
+
+    svg.push_str("</svg>");
+    svg
+}
+
+fn render_summary_page(
+    summary: &DataFrame,
+    top_uncovered: Option<&DataFrame>,
+    below_threshold: &[String],
+    files: &[TargetFile],
+    links: &[(&String, String)],
+    metadata: &RunMetadata
+) -> String {
+    let mut body = String::from("<h1>Coverage report</h1>\n<table class=\"metadata\">\n");
+    for (label, value) in metadata.rows() {
+        let _ = writeln!(body, "<tr><th>{}</th><td>{}</td></tr>", label, escape(&value));
+    }
+    body.push_str("</table>\n<h2>Squads</h2>\n");
+    if !below_threshold.is_empty() {
+        let _ = writeln!(body, "<p>{} squad(s) below the required coverage are highlighted.</p>", below_threshold.len());
+    }
+    body.push_str(&render_dataframe(summary, None, below_threshold));
+
+    if let Some(top_uncovered) = top_uncovered {
+        body.push_str("<h2>Top uncovered files</h2>\n");
+        body.push_str(&render_dataframe(top_uncovered, None, &[]));
+    }
+
+    let mut squads: Vec<Option<&String>> = files.iter().map(|f| f.squad_name()).collect();
+    squads.sort();
+    squads.dedup();
+
+    for squad in squads {
+        let squad_name = squad.map(|s| s.as_str()).unwrap_or("N/A");
+        let _ = write!(body, "<h2>{}</h2>\n<table>\n<tr><th>Filepath</th><th>Covered Lines</th><th>Executable Lines</th></tr>\n", escape(squad_name));
+
+        for file in files.iter().filter(|f| f.squad_name() == squad) {
+            let path = escape(file.file_path());
+            let cell = match links.iter().find(|(p, _)| *p == file.file_path()) {
+                Some((_, href)) => format!("<a href=\"{}\">{}</a>", href, path),
+                None => path
+            };
+
+            let _ = writeln!(body, "<tr><td>{}</td><td>{}</td><td>{}</td></tr>", cell, file.covered_lines(), file.executable_lines());
+        }
+
+        body.push_str("</table>\n");
+    }
+
+    page("Coverage report", &body)
+}
+
+fn render_source_page(annotated: &AnnotatedFile) -> String {
+    let path = annotated.file.file_path();
+    let mut body = format!("<p><a href=\"../index.html\">Back to summary</a></p>\n<h1>{}</h1>\n", escape(path));
+
+    let source = fs::read_to_string(&annotated.source_path).ok();
+    if source.is_none() {
+        body.push_str("<p>Source not available on this machine, showing hit counts only.</p>\n");
+    }
+    let source_lines: Vec<&str> = source.as_deref().map(|s| s.lines().collect()).unwrap_or_default();
+
+    body.push_str("<table class=\"source\">\n");
+    for line in &annotated.lines {
+        let (class, hits) = match line.hits() {
+            Some(0) => ("uncovered", String::from("0")),
+            Some(hits) => ("covered", hits.to_string()),
+            None => ("", String::new())
+        };
+        let code = source_lines.get(line.line().saturating_sub(1)).copied().unwrap_or_default();
+
+        let _ = writeln!(
+            body,
+            "<tr class=\"{}\"><td class=\"gutter\">{}</td><td class=\"gutter\">{}</td><td class=\"code\">{}</td></tr>",
+            class,
+            line.line(),
+            hits,
+            escape(code)
+        );
+    }
+    body.push_str("</table>\n");
+
+    page(path, &body)
+}
+
+/// Renders the dataframe as a table, coloring positive and negative values of `delta_column` and
+/// the rows of the `highlighted_squads`.
+pub fn render_dataframe(df: &DataFrame, delta_column: Option<&str>, highlighted_squads: &[String]) -> String {
+    let mut table = String::from("<table>\n<tr>");
+
+    for name in df.get_column_names() {
+        let _ = write!(table, "<th>{}</th>", escape(name));
+    }
+    table.push_str("</tr>\n");
+
+    let squads = df.column("Squad").and_then(|c| c.str()).ok();
+
+    for row in 0..df.height() {
+        let highlighted = squads
+            .and_then(|squads| squads.get(row))
+            .is_some_and(|squad| highlighted_squads.iter().any(|highlighted| highlighted == squad));
+
+        if highlighted {
+            table.push_str("<tr class=\"below-threshold\">");
+        } else {
+            table.push_str("<tr>");
+        }
+        for column in df.get_columns() {
+            let value = column.get(row)
+                .map(|v| v.to_string())
+                .unwrap_or_default();
+
+            let class = match value.parse::<f64>() {
+                Ok(delta) if Some(column.name()) == delta_column && delta > 0.0 => "increase",
+                Ok(delta) if Some(column.name()) == delta_column && delta < 0.0 => "decrease",
+                _ => ""
+            };
+
+            let _ = write!(table, "<td class=\"{}\">{}</td>", class, escape(value.trim_matches('"')));
+        }
+        table.push_str("</tr>\n");
+    }
+
+    table.push_str("</table>\n");
+    table
+}
+
+pub fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape(title),
+        STYLE,
+        body
+    )
+}
+
+pub fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,164 @@
+use std::path::{Path, PathBuf};
+use polars::frame::DataFrame;
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::cli::RoundingOptions;
+use crate::df;
+use crate::err::XCReportError;
+use crate::events;
+use crate::fs::{full_report_path, index_path, report_path};
+use crate::history;
+use crate::metadata::{self, RunMetadata};
+
+/// The runs under the home directory, saved as `index.json` so commands listing them don't have to
+/// read every run directory.
+#[derive(Serialize, Deserialize, Default)]
+pub struct RunIndex {
+    /// Oldest first.
+    pub runs: Vec<IndexedRun>
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct IndexedRun {
+    pub identifier: String,
+    pub timestamp: Option<String>,
+    pub scheme: Option<String>,
+    pub commit: Option<String>,
+    pub branch: Option<String>,
+    pub squads: usize,
+    pub files: usize,
+    pub covered_lines: u64,
+    pub executable_lines: u64,
+    /// Coverage % of all files, `None` without executable lines.
+    pub coverage: Option<f64>,
+    /// The summary, in the run directory or where `--output-file` saved it.
+    pub report_path: PathBuf
+}
+
+impl IndexedRun {
+    fn new(identifier: &str, metadata: Option<&RunMetadata>, full_report: &DataFrame, report_path: &Path) -> Result<Self, XCReportError> {
+        let total = |column: &str| -> Result<u64, XCReportError> {
+            let values = full_report.column(column)
+                .and_then(|c| c.cast(&DataType::UInt64))
+                .map_err(XCReportError::Polars)?;
+            let values = values.u64().map_err(XCReportError::Polars)?;
+
+            Ok(values.sum().unwrap_or_default())
+        };
+
+        let squads = full_report.column("Squad")
+            .and_then(|c| c.n_unique())
+            .map_err(XCReportError::Polars)?;
+        let covered_lines = total("Covered Lines")?;
+        let executable_lines = total("Executable Lines")?;
+        let coverage = (executable_lines > 0)
+            .then(|| df::round_value(covered_lines as f64 * 100.0 / executable_lines as f64, &RoundingOptions::default()));
+
+        Ok(IndexedRun {
+            identifier: identifier.to_string(),
+            timestamp: metadata.map(|metadata| metadata.timestamp.clone()),
+            scheme: metadata.and_then(|metadata| metadata.scheme.clone()),
+            commit: metadata.and_then(|metadata| metadata.commit.clone()),
+            branch: metadata.and_then(|metadata| metadata.branch.clone()),
+            squads,
+            files: full_report.height(),
+            covered_lines,
+            executable_lines,
+            coverage,
+            report_path: report_path.to_path_buf()
+        })
+    }
+}
+
+/// Loads the index, building it from the run directories the first time, e.g. for runs made before
+/// the index existed.
+pub fn load_index() -> Result<RunIndex, XCReportError> {
+    let index_path = index_path()?;
+
+    if !index_path.is_file() {
+        let index = build_index()?;
+        save_index(&index)?;
+        return Ok(index)
+    }
+
+    let json = std::fs::read_to_string(&index_path)
+        .map_err(XCReportError::FileIO)?;
+
+    serde_json::from_str(&json)
+        .map_err(XCReportError::Serde)
+}
+
+/// Identifiers of the indexed runs, oldest first.
+pub fn run_identifiers() -> Result<Vec<String>, XCReportError> {
+    Ok(load_index()?.runs.into_iter().map(|run| run.identifier).collect())
+}
+
+/// Adds the run to the index, replacing an earlier entry with its identifier.
+pub fn record_run(metadata: &RunMetadata, full_report: &DataFrame, report_path: &Path) -> Result<(), XCReportError> {
+    let run = IndexedRun::new(&metadata.identifier, Some(metadata), full_report, report_path)?;
+
+    insert_run(run)
+}
+
+/// Adds a run that is already on disk, e.g. one imported from another machine.
+pub fn record_saved_run(identifier: &String) -> Result<(), XCReportError> {
+    if !full_report_path(identifier)?.is_file() {
+        return Ok(())
+    }
+
+    insert_run(saved_run(identifier)?)
+}
+
+fn insert_run(run: IndexedRun) -> Result<(), XCReportError> {
+    let mut index = load_index()?;
+
+    index.runs.retain(|indexed_run| indexed_run.identifier != run.identifier);
+    index.runs.push(run);
+    index.runs.sort_by(|a, b| a.identifier.cmp(&b.identifier));
+
+    save_index(&index)
+}
+
+fn saved_run(identifier: &String) -> Result<IndexedRun, XCReportError> {
+    let full_report = df::read_full_report(identifier)?;
+    let metadata = metadata::load_metadata(identifier);
+
+    IndexedRun::new(identifier, metadata.as_ref(), &full_report, &report_path(identifier)?)
+}
+
+/// Indexes every run directory holding a full report, skipping the ones that can't be read.
+fn build_index() -> Result<RunIndex, XCReportError> {
+    let mut index = RunIndex::default();
+
+    for identifier in history::run_identifiers()? {
+        if !full_report_path(&identifier)?.is_file() {
+            continue
+        }
+
+        match saved_run(&identifier) {
+            Ok(run) => index.runs.push(run),
+            Err(e) => events::warning(&format!("leaving run {} out of the index: {}", identifier, e))
+        }
+    }
+
+    Ok(index)
+}
+
+/// Writes the index to a temporary file renamed into place, so readers never see half of it.
+fn save_index(index: &RunIndex) -> Result<(), XCReportError> {
+    let index_path = index_path()?;
+    if let Some(parent) = index_path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(XCReportError::FileIO)?;
+    }
+
+    let json = serde_json::to_string_pretty(index)
+        .map_err(XCReportError::Serde)?;
+
+    let partial_path = index_path.with_extension(format!("json.{}", std::process::id()));
+    std::fs::write(&partial_path, json)
+        .map_err(XCReportError::FileIO)?;
+    std::fs::rename(&partial_path, &index_path)
+        .map_err(XCReportError::FileIO)
+}
@@ -1,7 +1,12 @@
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
 use std::path::{Path, PathBuf};
 use std::io::Cursor;
-use std::process::{Command, Stdio};
-use clap::Parser;
+use std::os::unix::process::CommandExt;
+use std::process::{Command, ExitCode, Stdio};
+use std::time::Duration;
+use clap::{CommandFactory, Parser};
+use indexmap::IndexMap;
 use polars::prelude::*;
 
 mod fs;
@@ -9,23 +14,179 @@ mod cli;
 mod err;
 mod data;
 mod df;
+mod html;
+mod exclude;
+mod watch;
+mod pipeline;
+mod normalize;
+mod config;
+mod events;
+mod xcresult;
+mod metadata;
+mod cache;
+mod gate;
+mod history;
+mod owners;
+mod github;
+mod process;
+mod scrub;
+mod simulator;
+mod simctl;
+mod xlsx;
+mod upload;
+mod project;
+mod snapshot;
+mod comment;
+mod matching;
+mod view;
+mod shard;
+mod notify;
+mod xcode;
+mod manifest;
+mod serve;
+mod derived_data;
+mod table;
+mod backstage;
+mod render;
+mod doctor;
+mod smoke;
+mod mapping;
+mod tools;
+mod spm;
+mod index;
+mod ratchet;
+mod gitlab;
+mod summary;
+mod build_log;
+mod hooks;
 
-use crate::cli::{Cli, Commands};
-use crate::err::{FilePathError, XCReportError};
+use crate::cli::{Backend, BuildOptions, CacheAction, Cli, Commands, ErrorFormat, GitLabOptions, GroupBy, HistoryFormat, InputOptions, OwnershipSource, PrintSummary, PublishTarget, RatchetAction, ReportOptions, RoundingOptions, ShardPlanFormat, SimulatorOptions, ThresholdMode, UploadService};
+use crate::err::{FilePathError, UploadError, VerifyError, XCReportError, XCResultError};
 use crate::err::CommandExecutionError;
-use crate::fs::{derived_data_path, get_identifier, full_report_path, xcresult_path, xcpretty_report_path};
-use crate::data::{SquadData, TargetFile, XCodeBuildReport};
+use crate::fs::{artifact_manifest_path, backstage_path, build_log_path, compare_html_path, configuration_xcresult_path, derived_data_path, domain_report_path, exclusions_path, get_identifier, get_workdir, full_report_path, home_path, fully_covered_path, html_report_path, metadata_path, patch_report_path, path_prefix_report_path, report_path, safe_file_name, scheme_xcresult_path, targets_report_path, test_kind_xcresult_path, top_uncovered_path, tribe_report_path, xcresult_path, xcpretty_report_path, xlsx_report_path};
+use crate::build_log::BuildLog;
+use crate::data::{LineCoverage, Ownership, SquadData, TargetFile, TargetFilter, TestResults, XCodeBuildReport};
+use crate::exclude::ExclusionRule;
+use crate::hooks::Hook;
+use crate::html::AnnotatedFile;
+use crate::matching::SquadMatcher;
+use crate::metadata::RunMetadata;
+use crate::normalize::SquadNames;
+use crate::simctl::BootGuard;
+use crate::snapshot::{ReportInputs, ReportSettings};
 
 
-fn main() -> Result<(), XCReportError> {
-    let cli = Cli::parse();
-    let identifier = get_identifier()?;
-    process_command(cli.command(), identifier)?;
+/// Kinds of test bundles `--split-by-test-target` reruns, unit tests first, with whether they are UI tests.
+const TEST_KINDS: [(&str, bool); 2] = [("Unit", false), ("UI", true)];
+
+/// Most recent runs whose test durations `--budget` averages.
+const BUDGET_HISTORY_RUNS: usize = 5;
+
+/// Build settings of every test run, unless overridden with `--build-setting`.
+const DEFAULT_BUILD_SETTINGS: [(&str, &str); 2] = [
+    ("CODE_SIGN_IDENTITY", ""),
+    ("CODE_SIGNING_REQUIRED", "NO")
+];
+
+fn main() -> ExitCode {
+    // Errors of the config file, loaded before the arguments are parsed, are always printed as text.
+    let mut error_format = ErrorFormat::Text;
+
+    match try_main(&mut error_format) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => report_error(&e, error_format)
+    }
+}
+
+/// The destination to test on, from `--destination` or the `--device` simulator, booting the
+/// simulator with `--boot-simulator`.
+fn resolve_destination(destination: Option<&str>, simulator_options: &SimulatorOptions) -> Result<(String, Option<BootGuard>), XCReportError> {
+    let simulator = match &simulator_options.device {
+        Some(device) => Some(simctl::find_device(device, simulator_options.os.as_deref())?),
+        None => None
+    };
+
+    // clap requires --destination without --device.
+    let destination = match &simulator {
+        Some(simulator) => {
+            let destination = simulator.destination();
+            println!("Using destination {:?}", destination);
+            destination
+        },
+        None => {
+            let destination = destination.unwrap_or_default();
+            simctl::validate_destination(destination)?;
+            destination.to_string()
+        }
+    };
+
+    if !simulator_options.boot_simulator {
+        return Ok((destination, None))
+    }
+
+    let simulator = match simulator {
+        Some(simulator) => simulator,
+        None => simctl::find_destination_device(&destination)?
+    };
+    let boot_guard = simctl::boot(&simulator)?;
+
+    Ok((destination, Some(boot_guard)))
+}
+
+/// Prints the error and returns the exit code of its category.
+fn report_error(error: &XCReportError, error_format: ErrorFormat) -> ExitCode {
+    let category = error.category();
+
+    match error_format {
+        ErrorFormat::Text => eprintln!("Error: {}", scrub::scrub(&format!("{:?}", error))),
+        ErrorFormat::Json => {
+            let json = serde_json::json!({
+                "category": category,
+                "exit_code": category.exit_code(),
+                "message": scrub::scrub(&error.to_string())
+            });
+            eprintln!("{}", json);
+        }
+    }
+
+    ExitCode::from(category.exit_code())
+}
+
+fn try_main(error_format: &mut ErrorFormat) -> Result<(), XCReportError> {
+    let mut args: Vec<OsString> = std::env::args_os().collect();
+    if let Some(config_path) = config::find_config_file() {
+        let config = config::load_config(&config_path)?;
+        args = config::config_args(&config, args);
+        config::apply_config_env(&config);
+    }
+
+    let cli = Cli::parse_from(args);
+    *error_format = cli.error_format();
+
+    process::install_interrupt_handler()?;
+
+    if let Some(events_file) = cli.events_file() {
+        events::init(events_file)?;
+    }
+
+    if cli.subcommand().report_options().is_some_and(|report_options| report_options.print_summary == PrintSummary::Json) {
+        summary::redirect_stdout()?;
+    }
+
+    tools::init(cli.xcrun_path().map(PathBuf::as_path), cli.xcodebuild_path().map(PathBuf::as_path), cli.xccov_args());
+
+    if let Some(xcode) = cli.xcode() {
+        let developer_dir = xcode::resolve_developer_dir(xcode)?;
+        println!("Using Xcode at {:?}", developer_dir);
+        std::env::set_var("DEVELOPER_DIR", developer_dir);
+    }
+
+    process_command(cli.subcommand())?;
 
     Ok(())
 }
 
-fn process_command(command: &Commands, identifier: String) -> Result<(), XCReportError> {
+fn process_command(command: &Commands) -> Result<(), XCReportError> {
     match command {
         Commands::Run {
             input_file,
@@ -33,60 +194,352 @@ fn process_command(command: &Commands, identifier: String) -> Result<(), XCRepor
             workspace,
             scheme,
             destination,
-            output_file
+            simulator_options,
+            build_options,
+            report_options
         } => {
-            let xcresult_path = xcresult_path(&identifier)?;
-            run_tests(project_path, &xcresult_path, workspace, scheme, destination, &identifier)?;
-            let report_path = process_xcresult(input_file, &xcresult_path, &identifier, output_file)?;
-            print_result(&report_path, &identifier)?;
+            let workspace = &match workspace {
+                Some(workspace) => workspace.clone(),
+                None => {
+                    let workspace = project::find_workspace(project_path)?;
+                    println!("Using workspace {:?}", workspace);
+                    workspace
+                }
+            };
+            let budgeted_options = build_options.budget
+                .map(|minutes| budgeted_build_options(build_options, report_options, minutes))
+                .transpose()?;
+            let build_options = budgeted_options.as_ref().unwrap_or(build_options);
+            let (destination, _boot_guard) = resolve_destination(destination.as_deref(), simulator_options)?;
+            let destination = &simulator::preflight(&destination)?;
+            let identifier = get_identifier()?;
+            let _workdir_guard = process::track_workdir(get_workdir(&identifier)?);
+            events::phase_started("run_tests");
+            let scheme_outputs = run_schemes(project_path, workspace, scheme, destination, build_options, &identifier)?;
+            events::phase_finished("run_tests");
+            let xcresult_files: Vec<PathBuf> = scheme_outputs.iter().map(|outputs| outputs.xcresult_path.clone()).collect();
+            let test_report_paths: Vec<PathBuf> = scheme_outputs.into_iter().map(|outputs| outputs.test_report_path).collect();
+            let metadata = RunMetadata {
+                test_budget_minutes: build_options.budget,
+                ..metadata::collect_metadata(&identifier, project_path, Some(&scheme.join(", ")), Some(destination), &xcresult_files)
+            };
+            let (report_path, report_df) = process_xcresult(input_file, &xcresult_files, &metadata, report_options)?;
+            if build_options.split_by_test_target {
+                events::phase_started("split_tests");
+                let scheme_results: Vec<(&String, &PathBuf)> = scheme.iter().zip(&xcresult_files).collect();
+                let test_split_path = split_by_test_kind(project_path, workspace, &scheme_results, destination, build_options, report_options, &identifier)?;
+                events::phase_finished("split_tests");
+                if let Some(test_split_path) = test_split_path {
+                    println!("\nSaved the coverage of the unit and the UI tests per squad to {:?}", test_split_path);
+                }
+            }
+            if build_options.compare_configuration.is_some() {
+                events::phase_started("compare_configurations");
+                let configuration_report_path = compare_configurations(project_path, workspace, scheme, destination, build_options, report_options, &identifier)?;
+                events::phase_finished("compare_configurations");
+                if let Some(configuration_report_path) = configuration_report_path {
+                    println!("\nSaved the coverage of both configurations per squad to {:?}", configuration_report_path);
+                }
+            }
+            manifest::save_manifest(&identifier, &[report_path.as_path()])?;
+            copy_artifacts(&identifier, &report_path, &test_report_paths, report_options)?;
+            print_result(&report_df, &report_path, &identifier, &test_report_paths, report_options)?;
+            check_gate(&report_df, &metadata, report_options)?;
+        },
+        Commands::Generate { input_file, xcresult_file, report_options } => {
+            let identifier = get_identifier()?;
+            let _workdir_guard = process::track_workdir(get_workdir(&identifier)?);
+            let xcresult_files = expand_xcresult_inputs(xcresult_file)?;
+            let metadata = metadata::collect_metadata(&identifier, Path::new("."), None, None, &xcresult_files);
+            let (report_path, report_df) = process_xcresult(input_file, &xcresult_files, &metadata, report_options)?;
+            manifest::save_manifest(&identifier, &[report_path.as_path()])?;
+            copy_artifacts(&identifier, &report_path, &[], report_options)?;
+            print_result(&report_df, &report_path, &identifier, &[], report_options)?;
+            check_gate(&report_df, &metadata, report_options)?;
+        },
+        Commands::RunSpm { input_file, package_path, report_options } => {
+            if report_options.test_metrics {
+                events::warning("test metrics need result bundles, leaving them out of the Swift package report");
+            }
+            let identifier = get_identifier()?;
+            let _workdir_guard = process::track_workdir(get_workdir(&identifier)?);
+            events::phase_started("run_tests");
+            spm::run_tests(package_path)?;
+            events::phase_finished("run_tests");
+            events::phase_started("parse_coverage");
+            let xcodebuild_report = spm::export_coverage(package_path)?;
+            events::phase_finished("parse_coverage");
+            let metadata = metadata::collect_metadata(&identifier, package_path, None, None, &[]);
+            metadata::save_metadata(&metadata)?;
+            let squads_data = parse_squads_file(input_file, &report_options.input_options, report_options.squad_aliases.as_deref())?;
+            let (report_path, report_df) = process_coverage(xcodebuild_report, squads_data, None, &[], &metadata, report_options)?;
+            manifest::save_manifest(&identifier, &[report_path.as_path()])?;
+            copy_artifacts(&identifier, &report_path, &[], report_options)?;
+            print_result(&report_df, &report_path, &identifier, &[], report_options)?;
+            check_gate(&report_df, &metadata, report_options)?;
+        },
+        Commands::Compare { base, head, html, rounding } => {
+            let identifier = get_identifier()?;
+            compare_reports(base, head, &identifier, *html, rounding)?;
+        },
+        Commands::Pipeline => {
+            pipeline::run()?;
+        },
+        Commands::Watch { input_file, dir, settle_seconds, report_options } => {
+            watch::watch_bundles(dir, Duration::from_secs(*settle_seconds), |xcresult_file| {
+                let identifier = get_identifier()?;
+                let _workdir_guard = process::track_workdir(get_workdir(&identifier)?);
+                let xcresult_files = [xcresult_file.to_path_buf()];
+                let metadata = metadata::collect_metadata(&identifier, Path::new("."), None, None, &xcresult_files);
+                let (report_path, report_df) = process_xcresult(input_file, &xcresult_files, &metadata, report_options)?;
+                manifest::save_manifest(&identifier, &[report_path.as_path()])?;
+                copy_artifacts(&identifier, &report_path, &[], report_options)?;
+                print_result(&report_df, &report_path, &identifier, &[], report_options)?;
+                check_gate(&report_df, &metadata, report_options)
+            })?;
+        },
+        Commands::Dashboard { output_dir, rounding } => {
+            save_dashboard(output_dir, rounding)?;
+        },
+        Commands::History { export, out, rounding } => {
+            export_history(*export, out, rounding)?;
+        },
+        Commands::List { limit } => {
+            list_runs(*limit)?;
+        },
+        Commands::MergeHistory { stores } => {
+            let summary = history::merge_history(stores)?;
+            for identifier in &summary.imported {
+                println!("Imported run {}", identifier);
+            }
+            println!("Merged {} run(s), skipped {} already present", summary.imported.len(), summary.skipped);
+        },
+        Commands::Serve { address, rounding } => {
+            serve::serve(address, rounding)?;
+        },
+        Commands::Cache { action } => {
+            match action {
+                CacheAction::Save(cache_options) => derived_data::save(cache_options)?,
+                CacheAction::Restore(cache_options) => derived_data::restore(cache_options)?
+            }
+        },
+        Commands::Publish { target } => {
+            match target {
+                PublishTarget::Gitlab(gitlab_options) => publish_gitlab(gitlab_options)?
+            }
+        },
+        Commands::Ratchet { action } => {
+            match action {
+                RatchetAction::Update(ratchet_options) => ratchet::update(ratchet_options)?
+            }
+        },
+        Commands::Doctor => {
+            doctor::doctor()?;
+        },
+        Commands::Smoke => {
+            smoke::smoke()?;
+        },
+        Commands::Completions { shell } => {
+            clap_complete::generate(*shell, &mut Cli::command(), "xcreport", &mut std::io::stdout());
+        },
+        Commands::InferOwners { by_directory, root, output_file } => {
+            infer_owners(root, by_directory, output_file.as_deref())?;
+        },
+        Commands::ExportCodeowners { input_file, teams_file, output_file, input_options } => {
+            export_codeowners(input_file, input_options, teams_file, output_file.as_deref())?;
+        },
+        Commands::ValidateMapping { input_file, repo, input_options } => {
+            validate_mapping(input_file, input_options, repo)?;
+        },
+        Commands::ShardPlan { shards, history_runs, format } => {
+            print_shard_plan(*shards as usize, *history_runs, *format)?;
+        },
+        Commands::Upload { xcresult_file, service, token, commit, branch, repo_root, service_url } => {
+            let upload_token = token
+                .clone()
+                .or_else(|| {
+                    let token_variable = match service {
+                        UploadService::Codecov => "CODECOV_TOKEN",
+                        UploadService::Coveralls => "COVERALLS_REPO_TOKEN"
+                    };
+                    std::env::var(token_variable).ok()
+                })
+                .ok_or(XCReportError::Upload(UploadError::MissingToken))?;
+            let commit = commit
+                .clone()
+                .or_else(|| metadata::git_commit(Path::new(".")))
+                .ok_or(XCReportError::Upload(UploadError::MissingCommit))?;
+            let branch = branch.clone().or_else(|| metadata::git_branch(Path::new(".")));
+
+            let upload = upload::Upload {
+                service: *service,
+                service_url: service_url.as_deref(),
+                token: &upload_token,
+                commit: &commit,
+                branch: branch.as_deref()
+            };
+            upload_coverage(xcresult_file, repo_root.as_deref(), &upload)?;
+        },
+        Commands::Verify { identifier } => {
+            verify_run(identifier)?;
+        },
+        Commands::Open { identifier, csv, reveal } => {
+            open_report(identifier.as_ref(), *csv, *reveal)?;
         },
-        Commands::Generate { input_file, xcresult_file, output_file } => {
-            let report_path = process_xcresult(input_file, xcresult_file, &identifier, output_file)?;
-            print_result(&report_path, &identifier)?;
+        Commands::View { identifier, full_report } => {
+            let full_report_path = match (full_report, identifier) {
+                (Some(full_report), _) => full_report.clone(),
+                (None, Some(identifier)) => full_report_path(identifier)?,
+                (None, None) => latest_full_report_path()?
+            };
+            if !full_report_path.is_file() {
+                return Err(XCReportError::FilePath(FilePathError::NotFound))
+            }
+            view::browse(&full_report_path)?;
         }
     }
 
     Ok(())
 }
 
-fn run_tests(
+/// Where the test run of a scheme writes its result bundle, xcpretty report and raw xcodebuild output.
+struct SchemeOutputs {
+    xcresult_path: PathBuf,
+    test_report_path: PathBuf,
+    build_log_path: PathBuf
+}
+
+/// Tests the schemes one after the other, stopping at the first one that fails.
+/// With several schemes, every result bundle and test report is named after its scheme.
+fn run_schemes(
     project_path: &Path,
-    xcresult_path: &Path,
     workspace: &Path,
-    scheme: &String,
-    destination: &String,
+    schemes: &[String],
+    destination: &str,
+    build_options: &BuildOptions,
     identifier: &String
+) -> Result<Vec<SchemeOutputs>, XCReportError> {
+    let mut scheme_outputs: Vec<SchemeOutputs> = vec![];
+
+    for (index, scheme) in schemes.iter().enumerate() {
+        let outputs = if schemes.len() == 1 {
+            SchemeOutputs {
+                xcresult_path: xcresult_path(identifier)?,
+                test_report_path: xcpretty_report_path(identifier, &build_options.test_report_name)?,
+                build_log_path: build_log_path(identifier, build_options.compress_log)?
+            }
+        } else {
+            println!("\nTesting scheme {} ({}/{})", scheme, index + 1, schemes.len());
+            SchemeOutputs {
+                xcresult_path: scheme_xcresult_path(identifier, scheme)?,
+                test_report_path: xcpretty_report_path(identifier, &format!("{}-{}", safe_file_name(scheme), build_options.test_report_name))?,
+                build_log_path: build_log_path(identifier, build_options.compress_log)?
+            }
+        };
+
+        // Later schemes reuse the build products of the earlier ones they share.
+        let clean = index == 0;
+        run_tests(project_path, workspace, scheme, destination, build_options, &outputs, clean)?;
+        scheme_outputs.push(outputs);
+    }
+
+    Ok(scheme_outputs)
+}
+
+fn run_tests(
+    project_path: &Path,
+    workspace: &Path,
+    scheme: &str,
+    destination: &str,
+    build_options: &BuildOptions,
+    outputs: &SchemeOutputs,
+    clean: bool
 ) -> Result<(), XCReportError> {
+    let xcresult_path = outputs.xcresult_path.as_path();
+
+    let derived_data_path = match &build_options.derived_data_path {
+        Some(derived_data_path) => Some(derived_data_path.clone()),
+        None if build_options.use_project_derived_data => None,
+        None => Some(derived_data_path()?)
+    };
+
+    let xcbuild_args = xcodebuild_test_args(workspace, scheme, destination, xcresult_path, derived_data_path.as_deref(), clean, build_options);
+
+    let mut xcbuild_child = tools::xcodebuild()
+        .args(xcbuild_args)
+        .current_dir(project_path)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0)
+        .spawn()
+        .map_err(|e| XCReportError::CommandExecution(CommandExecutionError::XCodeBuild(e)))?;
+    process::track_process_group(&xcbuild_child);
+
+    let xcbuild_stdout = xcbuild_child
+        .stdout
+        .take()
+        .ok_or(XCReportError::CommandExecution(CommandExecutionError::NonZeroExit { desc: String::from("N/A") }))?;
+    let xcbuild_stderr = xcbuild_child
+        .stderr
+        .take()
+        .ok_or(XCReportError::CommandExecution(CommandExecutionError::NonZeroExit { desc: String::from("N/A") }))?;
 
-    let derived_data_path = derived_data_path()?;
-    let mut xcbuild_child = Command::new("xcodebuild")
+    let xcp_output_file = &outputs.test_report_path;
+    let mut xcpretty_child = Command::new("xcpretty")
         .args([
-            "-workspace",
-            &workspace.to_str().unwrap(),
-            "-scheme",
-            &scheme,
-            "-derivedDataPath",
-            &derived_data_path.to_str().unwrap(),
-            "-resultBundlePath",
-            &xcresult_path.to_str().unwrap(),
-            "-sdk",
-            "iphonesimulator",
-            "-destination",
-            &destination,
-            "-enableCodeCoverage",
-            "YES",
-            "clean",
-            "test",
-            "CODE_SIGN_IDENTITY=\"\"",
-            "CODE_SIGNING_REQUIRED=NO"
+            "--test",
+            "--simple",
+            "--color",
+            "--report",
+            "html",
+            "--output"
         ])
+        .arg(xcp_output_file)
         .current_dir(project_path)
+        .stdin(Stdio::piped())
+        .process_group(xcbuild_child.id() as i32)
         .spawn()
+        .map_err(|e| XCReportError::CommandExecution(CommandExecutionError::XCPretty(e)))?;
+    let xcpretty_stdin = xcpretty_child
+        .stdin
+        .take()
+        .ok_or(XCReportError::CommandExecution(CommandExecutionError::NonZeroExit { desc: String::from("N/A") }))?;
+
+    // xcpretty only shows what it recognizes, the log keeps all of the output.
+    let build_log = BuildLog::open(&outputs.build_log_path, build_options.compress_log)?;
+    let stdout_tee = build_log.tee(xcbuild_stdout, xcpretty_stdin);
+    let stderr_tee = build_log.tee(xcbuild_stderr, std::io::stderr());
+
+    let timeout = build_options.timeout.map(|minutes| Duration::from_secs(minutes * 60));
+    let xcbuild_exit_status = process::wait_with_timeout(&mut xcbuild_child, timeout)
         .map_err(|e| XCReportError::CommandExecution(CommandExecutionError::XCodeBuild(e)))?;
 
-    let xcbuild_exit_status = xcbuild_child
+    let _ = stdout_tee.join();
+    let _ = stderr_tee.join();
+    build_log.finish()?;
+
+    xcpretty_child
         .wait()
         .map_err(|e| XCReportError::CommandExecution(CommandExecutionError::XCPretty(e)))?;
+    process::untrack_process_group(&xcbuild_child);
+
+    if let Ok(xcpretty_report) = std::fs::read_to_string(xcp_output_file) {
+        std::fs::write(xcp_output_file, scrub::scrub(&xcpretty_report))
+            .map_err(XCReportError::FileIO)?;
+    }
+
+    let Some(xcbuild_exit_status) = xcbuild_exit_status else {
+        // A bundle of a killed run is incomplete and can't be read.
+        if xcresult_path.try_exists().unwrap_or_default() {
+            std::fs::remove_dir_all(xcresult_path)
+                .map_err(XCReportError::FileIO)?;
+        }
+
+        let minutes = build_options.timeout.unwrap_or_default();
+        return Err(XCReportError::CommandExecution(CommandExecutionError::Timeout { minutes }))
+    };
+
+    check_test_failures(xcresult_path, build_options.extract_attachments.as_deref())?;
 
     if !xcbuild_exit_status.success() {
         let exit_code = xcbuild_exit_status
@@ -99,142 +552,1734 @@ fn run_tests(
         return Err(XCReportError::CommandExecution(CommandExecutionError::NonZeroExit { desc: exit_code }))
     }
 
-    let xcbuild_stdout = xcbuild_child
-        .stdout
-        .ok_or(XCReportError::CommandExecution(CommandExecutionError::NonZeroExit { desc: String::from("N/A") }))?;
+    Ok(())
+}
 
-    let xcp_output_file = xcpretty_report_path(identifier)?;
-    Command::new("xcpretty")
-        .args([
-            "--test",
-            "--simple",
-            "--color",
-            "--report",
-            "html",
-            "--output",
-            &xcp_output_file.to_str().unwrap()
-        ])
-        .current_dir(project_path)
-        .stdin(Stdio::from(xcbuild_stdout))
-        .spawn()
-        .map_err(|e| XCReportError::CommandExecution(CommandExecutionError::XCPretty(e)))?
-        .wait()
-        .map_err(|e| XCReportError::CommandExecution(CommandExecutionError::XCPretty(e)))?;
+/// The arguments testing the scheme with coverage. Every value is an argument of its own, passed to
+/// xcodebuild as is, so schemes, destinations and paths with spaces or quotes need no quoting.
+fn xcodebuild_test_args(
+    workspace: &Path,
+    scheme: &str,
+    destination: &str,
+    xcresult_path: &Path,
+    derived_data_path: Option<&Path>,
+    clean: bool,
+    build_options: &BuildOptions
+) -> Vec<OsString> {
+    let mut xcbuild_args: Vec<OsString> = vec![
+        "-workspace".into(),
+        workspace.into(),
+        "-scheme".into(),
+        scheme.into(),
+        "-resultBundlePath".into(),
+        xcresult_path.into(),
+        "-sdk".into(),
+        "iphonesimulator".into(),
+        "-destination".into(),
+        destination.into(),
+        "-enableCodeCoverage".into(),
+        "YES".into()
+    ];
 
-    Ok(())
+    if let Some(configuration) = &build_options.configuration {
+        xcbuild_args.extend(["-configuration".into(), configuration.into()]);
+    }
+
+    if let Some(derived_data_path) = derived_data_path {
+        xcbuild_args.extend(["-derivedDataPath".into(), derived_data_path.into()]);
+    }
+
+    // Cleaning would throw away the build cache the project's DerivedData is reused for.
+    if clean && !build_options.use_project_derived_data {
+        xcbuild_args.push("clean".into());
+    }
+
+    xcbuild_args.push("test".into());
+    xcbuild_args.extend(build_options.only_testing.iter().map(|identifier| format!("-only-testing:{}", identifier).into()));
+    xcbuild_args.extend(build_options.skip_testing.iter().map(|identifier| format!("-skip-testing:{}", identifier).into()));
+    xcbuild_args.extend(
+        build_settings(&build_options.build_setting)
+            .into_iter()
+            .map(|(key, value)| format!("{}={}", key, value).into())
+    );
+
+    xcbuild_args
 }
 
-fn match_squad_files(squads_data: Vec<SquadData>, report: XCodeBuildReport) -> Vec<TargetFile> {
-    // TODO: Move this inefficient logic to polars (if possible)
-    let all_files = report.get_all_files();
-    let mut report_files: Vec<TargetFile> = vec![];
+/// The `--build-setting`s after the default ones they don't override, which build without code signing.
+fn build_settings(overrides: &[(String, String)]) -> Vec<(String, String)> {
+    // An empty identity, not a pair of quotes, which no shell is there to remove.
+    let mut build_settings: Vec<(String, String)> = DEFAULT_BUILD_SETTINGS
+        .iter()
+        .filter(|(key, _)| !overrides.iter().any(|(override_key, _)| override_key == key))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+    build_settings.extend(overrides.iter().cloned());
 
-    for file in all_files {
-        let squad_file = squads_data
-            .iter()
-            .find(|squad_data| file.file_path().contains(squad_data.file_name()));
+    build_settings
+}
+
+/// Prints the failed tests of the result bundle grouped by test bundle and suite, and fails if there are any.
+/// The attachments of the failed tests are extracted to `attachments_dir` first.
+fn check_test_failures(xcresult_path: &Path, attachments_dir: Option<&Path>) -> Result<(), XCReportError> {
+    if !xcresult_path.try_exists().unwrap_or_default() {
+        return Ok(())
+    }
+
+    let test_results = match xcresult::parse_test_results(xcresult_path) {
+        Ok(test_results) => test_results,
+        Err(e) => {
+            events::warning(&format!("could not read test results: {}", e));
+            return Ok(())
+        }
+    };
+
+    let failed_tests = test_results.failed_tests();
+    if failed_tests.is_empty() {
+        return Ok(())
+    }
 
-        if let Some(squad_file) = squad_file {
+    println!("\n{} test(s) failed:", failed_tests.len());
+
+    let mut current_group: Option<(&String, &String)> = None;
+    for failed_test in &failed_tests {
+        let group = (failed_test.bundle, failed_test.suite);
+        if current_group != Some(group) {
+            println!("\n  {} / {}", group.0, group.1);
+            current_group = Some(group);
+        }
+        println!("    ✗ {}", failed_test.name);
+    }
+
+    if let Some(attachments_dir) = attachments_dir {
+        match xcresult::export_failure_attachments(xcresult_path, attachments_dir) {
+            Ok(count) => println!("\n{} attachment(s) of the failed tests were extracted to:\n{:?}", count, attachments_dir),
+            Err(e) => events::warning(&format!("could not extract the attachments of the failed tests: {}", e))
+        }
+    }
+
+    Err(XCReportError::TestsFailed { count: failed_tests.len() })
+}
+
+fn match_squad_files(
+    squads_data: &[SquadData],
+    report: &XCodeBuildReport,
+    target_filter: &TargetFilter,
+    unowned_name: &str,
+    ignore_path_case: bool
+) -> Result<Vec<TargetFile>, XCReportError> {
+    let matcher = SquadMatcher::new(squads_data, ignore_path_case)?;
+
+    let report_files = report
+        .get_all_files(target_filter)
+        .into_iter()
+        .map(|file| {
             let mut file = file.clone();
-            file.set_squad_name(squad_file.squad_name().clone());
-            report_files.push(file);
+            let squad_name = matcher
+                .find(file.file_path())
+                .map(|squad_file| squad_file.squad_name().clone())
+                .unwrap_or(unowned_name.to_string());
+            file.set_squad_name(squad_name);
+            file
+        })
+        .collect();
 
-            if report_files.len() == squads_data.len() {
-                break
-            }
-        } else {
-            report_files.push(file.clone());
+    Ok(report_files)
+}
+
+fn match_squad_tests(
+    squads_data: &[SquadData],
+    test_results: &[TestResults],
+    report_options: &ReportOptions
+) -> Result<Vec<(String, u64)>, XCReportError> {
+    let matcher = SquadMatcher::new(squads_data, report_options.ignore_path_case)?;
+    let mut squad_tests: Vec<(String, u64)> = vec![];
+
+    for (target, count) in test_results.iter().flat_map(|r| r.tests_per_target()) {
+        let squad_name = matcher
+            .find(target)
+            .map(|squad_data| squad_data.squad_name().clone())
+            .unwrap_or(report_options.unowned_name.clone());
+
+        match squad_tests.iter_mut().find(|(squad, _)| *squad == squad_name) {
+            Some((_, tests)) => *tests += count as u64,
+            None => squad_tests.push((squad_name, count as u64))
         }
     }
 
-    report_files
+    Ok(squad_tests)
 }
 
 fn process_xcresult(
-    input_file: &Path,
-    xcresult_file: &Path,
-    identifier: &String,
-    output_file: &Option<PathBuf>
-) -> Result<PathBuf, XCReportError> {
+    input_files: &[PathBuf],
+    xcresult_files: &[PathBuf],
+    metadata: &RunMetadata,
+    report_options: &ReportOptions
+) -> Result<(PathBuf, DataFrame), XCReportError> {
+    let identifier = &metadata.identifier;
+    metadata::save_metadata(metadata)?;
 
-    let squads_data = parse_squads_file(input_file)?;
-    let xcodebuild_report = parse_xcresult_json(xcresult_file)?;
-    let report_files = match_squad_files(squads_data, xcodebuild_report);
+    events::phase_started("parse_input");
+    let squads_data = parse_squads_file(input_files, &report_options.input_options, report_options.squad_aliases.as_deref())?;
+    events::phase_finished("parse_input");
 
-    let json = serde_json::to_string(&report_files)
-        .map_err(XCReportError::Serde)?;
+    let squad_tests = if report_options.test_metrics {
+        events::phase_started("parse_tests");
+        let test_results = xcresult_files
+            .iter()
+            .map(|xcresult_file| xcresult::parse_test_results(xcresult_file))
+            .collect::<Result<Vec<_>, _>>()?;
+        events::phase_finished("parse_tests");
+        Some(match_squad_tests(&squads_data, &test_results, report_options)?)
+    } else {
+        None
+    };
 
-    let cursor = Cursor::new(json);
-    let df = JsonReader::new(cursor)
-        .finish()
-        .map_err(XCReportError::Polars)?;
+    events::phase_started("parse_xcresult");
+    let mut xcodebuild_reports: Vec<XCodeBuildReport> = vec![];
+    for (index, xcresult_file) in xcresult_files.iter().enumerate() {
+        xcodebuild_reports.push(parse_coverage(xcresult_file, report_options, identifier)?);
+        events::progress("parse_xcresult", index + 1, xcresult_files.len());
+    }
+    let mut xcodebuild_report = XCodeBuildReport::merge(xcodebuild_reports);
+    if xcresult_files.len() > 1 {
+        let archives = xcresult_files
+            .iter()
+            .map(|xcresult_file| xcresult::parse_archive(xcresult_file))
+            .collect::<Result<Vec<_>, _>>()?;
+        xcodebuild_report.merge_line_coverage(archives);
+    }
+    events::phase_finished("parse_xcresult");
+
+    process_coverage(xcodebuild_report, squads_data, squad_tests, xcresult_files, metadata, report_options)
+}
 
-    let mut full_report_df = df::process_full_report(df)?;
-    df::save_full_report(&mut full_report_df, identifier)?;
+/// Generates the reports of coverage already parsed, from result bundles or another build system.
+/// Ignore regions and the HTML source views need the line coverage of the result bundles.
+fn process_coverage(
+    mut xcodebuild_report: XCodeBuildReport,
+    squads_data: Vec<SquadData>,
+    squad_tests: Option<Vec<(String, u64)>>,
+    xcresult_files: &[PathBuf],
+    metadata: &RunMetadata,
+    report_options: &ReportOptions
+) -> Result<(PathBuf, DataFrame), XCReportError> {
+    let identifier = &metadata.identifier;
+
+    if report_options.reuse_unchanged_targets {
+        if let Some(snapshots) = cache::previous_target_cache(identifier)? {
+            for target in cache::reuse_unchanged_targets(&mut xcodebuild_report, snapshots) {
+                events::warning(&format!("reusing stale coverage of unchanged target {:?}", target));
+            }
+        }
+    }
+    cache::save_target_cache(&xcodebuild_report, identifier)?;
 
-    let mut report_df = df::process_report(&full_report_df)?;
+    let repo_root = repo_root(report_options)?;
+    if let Some(repo_root) = &repo_root {
+        xcodebuild_report.relativize_paths(repo_root);
+    }
 
-    if let Some(report_path) = output_file {
-        df::save_report_to_output(&mut report_df, report_path)?;
-        Ok(report_path.to_owned())
+    // Marked files come first, so that no rule keeping generated files keeps them.
+    let mut exclusion_rules = if report_options.no_source_markers {
+        vec![]
     } else {
-        let path = df::save_report_to_default(&mut report_df, identifier)?;
-        Ok(path)
+        apply_source_markers(&mut xcodebuild_report, xcresult_files, repo_root.as_deref())?
+    };
+    if let Some(ignore_file) = &report_options.ignore_file {
+        exclusion_rules.extend(exclude::parse_ignore_file(ignore_file)?);
     }
-}
+    exclusion_rules.extend(exclude::generated_file_rules(&report_options.keep_generated));
 
-fn parse_xcresult_json(xcresult_file: &Path) -> Result<XCodeBuildReport, XCReportError> {
+    let mut squad_names = SquadNames::load(report_options.squad_aliases.as_deref())?;
+    squad_names.extend(&squads_data);
 
-    if !&xcresult_file.try_exists().unwrap_or_default() {
-        return Err(XCReportError::FilePath(FilePathError::NotFound))
+    let squad_domains = report_options.tags_file
+        .as_deref()
+        .map(|tags_file| parse_tags_file(tags_file, &squad_names))
+        .transpose()?;
+
+    let squad_tribes = match report_options.group_by {
+        GroupBy::Squad | GroupBy::PathPrefix => None,
+        GroupBy::Tribe => Some(squad_tribes(&squads_data))
+    };
+
+    let squad_history = report_options.volatility_runs
+        .map(|runs| history::recent_squad_coverages(identifier, runs as usize - 1, &report_options.rounding))
+        .transpose()?;
+
+    let changed_files = report_options.changed_files
+        .as_deref()
+        .map(read_changed_files)
+        .transpose()?;
+
+    let inputs = ReportInputs {
+        coverage: xcodebuild_report,
+        squads: squads_data,
+        exclusion_rules,
+        squad_tests,
+        squad_domains,
+        squad_tribes,
+        squad_history,
+        changed_files,
+        settings: ReportSettings::from(report_options)
+    };
+    snapshot::save_inputs(&inputs, identifier)?;
+
+    let Reports { report_files, full_report_df, mut report_df, top_uncovered_df, hidden_files } = generate_reports(&inputs, identifier)?;
+
+    if let Some(hidden_files) = hidden_files {
+        metadata::save_metadata(&RunMetadata { hidden_fully_covered_files: Some(hidden_files), ..metadata.clone() })?;
+        println!("\nLeft {} fully covered file(s) out of the full report", hidden_files);
     }
 
-    let xcrun_output = Command::new("xcrun")
-        .args([
-            "xccov",
-            "view",
-            "--report",
-            "--json",
-            &xcresult_file.to_str().unwrap()
-        ])
-        .output()
-        .map_err(|e| XCReportError::CommandExecution(CommandExecutionError::XCRun(e)))?;
+    if report_options.html {
+        events::phase_started("html");
+        let below_threshold: Vec<String> = gate::violations(&report_df, report_options)?
+            .map(|(_, violations)| violations.into_iter().map(|violation| violation.squad).collect())
+            .unwrap_or_default();
+        save_html_report(&report_df, top_uncovered_df.as_ref(), &below_threshold, &report_files, xcresult_files, repo_root.as_deref(), metadata)?;
+        events::phase_finished("html");
+    }
 
-    let json_report = String::from_utf8(xcrun_output.stdout)
-        .map_err(XCReportError::UTF8)?;
+    if report_options.xlsx {
+        xlsx::save_xlsx_report(&report_df, &full_report_df, &xlsx_report_path(identifier)?)?;
+    }
 
-    let targets: XCodeBuildReport = serde_json::from_str(&json_report)
-        .map_err(XCReportError::Serde)?;
+    if let Some(components_file) = &report_options.backstage_components {
+        let components: IndexMap<String, backstage::ComponentRefs> = backstage::parse_components_file(components_file)?
+            .into_iter()
+            .map(|(squad, refs)| (squad_names.resolve(&squad), refs))
+            .collect();
+        // The summary has no line totals with `--aggregate files`, the full report always has.
+        let squad_df = df::process_report(&full_report_df, &report_options.rounding)?;
+        backstage::save_scorecard(&squad_df, &components, metadata, &backstage_path(identifier)?)?;
+    }
 
-    Ok(targets)
+    let report_path = if let Some(report_path) = &report_options.output_file {
+        df::save_report_to_output(&mut report_df, report_path, report_options.output_format.as_deref())?;
+        report_path.to_owned()
+    } else {
+        df::save_report_to_default(&mut report_df, identifier)?
+    };
+    index::record_run(metadata, &full_report_df, &report_path)?;
+
+    Ok((report_path, report_df))
 }
 
-fn parse_squads_file(filepath: &Path) -> Result<Vec<SquadData>, XCReportError> {
-    let mut df = CsvReader::from_path(filepath)
-        .map_err(XCReportError::Polars)?
-        .with_columns(Some(vec!["Squad".into(), "Filepath".into()]))
-        .has_header(true)
-        .finish()
-        .map_err(XCReportError::Polars)?;
+fn parse_coverage(xcresult_file: &Path, report_options: &ReportOptions, identifier: &String) -> Result<XCodeBuildReport, XCReportError> {
+    match report_options.backend {
+        Backend::Xccov => {
+            let xccov_error = match xcresult::parse_xcresult_json(xcresult_file, !report_options.no_xcresult_cache) {
+                Ok(report) => return Ok(report),
+                Err(e @ (XCReportError::Serde(_)
+                    | XCReportError::XCResult(XCResultError::MalformedReport { .. })
+                    | XCReportError::CommandExecution(CommandExecutionError::NonZeroExit { .. }))) => e,
+                Err(e) => return Err(e)
+            };
 
-    let mut bytes: Vec<u8> = vec![];
+            // The output of xccov changes with the result bundle format, e.g. for the bundles of Xcode 16.
+            let version = xcresult::bundle_version(xcresult_file).map(|version| version.to_string());
+            let format = version.as_ref().map(|version| format!(", a format {} result bundle", version)).unwrap_or_default();
+            events::warning(&format!("xccov could not read the coverage of {:?}{}: {}, reading it with xcresulttool instead", xcresult_file, format, xccov_error));
 
-    JsonWriter::new(&mut bytes)
-        .with_json_format(JsonFormat::Json)
-        .finish(&mut df)
-        .map_err(XCReportError::Polars)?;
+            let workdir = get_workdir(identifier)?;
+            xcresult::parse_xcresult_with_xcresulttool(xcresult_file, &workdir).map_err(|e| match e {
+                XCReportError::XCResult(_) => e,
+                e => XCReportError::XCResult(XCResultError::UnreadableCoverage {
+                    path: xcresult_file.to_path_buf(),
+                    version,
+                    desc: e.to_string()
+                })
+            })
+        },
+        Backend::Xcresulttool => {
+            let workdir = get_workdir(identifier)?;
+            xcresult::parse_xcresult_with_xcresulttool(xcresult_file, &workdir)
+        }
+    }
+}
 
-    let squads_data: Vec<SquadData> = serde_json::from_slice(&bytes[..])
-        .map_err(XCReportError::Serde)?;
+/// The `--repo-root`, relative to the current directory.
+fn repo_root(report_options: &ReportOptions) -> Result<Option<PathBuf>, XCReportError> {
+    report_options.repo_root
+        .as_ref()
+        .map(|repo_root| std::env::current_dir().map(|dir| dir.join(repo_root)))
+        .transpose()
+        .map_err(XCReportError::FileIO)
+}
 
-    Ok(squads_data)
+/// Reads the `// xcreport:` markers of the report's sources: returns the rules excluding the files marked
+/// with `ignore-file`, and leaves the lines of `ignore-start` to `ignore-end` regions out of the counts of
+/// the other files. Sources missing on this machine are skipped.
+fn apply_source_markers(report: &mut XCodeBuildReport, xcresult_files: &[PathBuf], repo_root: Option<&Path>) -> Result<Vec<ExclusionRule>, XCReportError> {
+    let mut rules: Vec<ExclusionRule> = vec![];
+    let mut ignored_lines = 0;
+
+    for file in report.files_mut() {
+        // Report paths were made relative to the build machine path of the repository.
+        let source_path = match repo_root {
+            Some(repo_root) => repo_root.join(file.file_path()),
+            None => PathBuf::from(file.file_path())
+        };
+        let Ok(source) = std::fs::read_to_string(&source_path) else {
+            continue
+        };
+
+        let markers = exclude::scan_markers(&source);
+        if let Some(line) = markers.ignore_file_line {
+            rules.push(exclude::marker_rule(file.file_path(), &source_path, line));
+            continue
+        }
+        if markers.ignored_regions.is_empty() {
+            continue
+        }
+
+        let bundle_lines = xcresult_files
+            .iter()
+            .map(|xcresult_file| xcresult::parse_line_coverage(xcresult_file, &source_path.to_string_lossy()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let (executable_lines, covered_lines) = LineCoverage::merge(bundle_lines)
+            .iter()
+            .filter(|line| markers.ignores_line(line.line()))
+            .filter_map(LineCoverage::hits)
+            .fold((0, 0), |(executable, covered), hits| (executable + 1, covered + usize::from(hits > 0)));
+
+        file.exclude_lines(executable_lines, covered_lines);
+        ignored_lines += executable_lines;
+    }
+
+    if ignored_lines > 0 {
+        println!("\nLeft {} executable line(s) of xcreport:ignore regions out of the coverage", ignored_lines);
+    }
+
+    Ok(rules)
 }
 
-fn print_result(report_path: &PathBuf, identifier: &String) -> Result<(), XCReportError> {
-    let full_report_path = full_report_path(identifier)?;
+/// Files and data frames of the reports, the summary being left for the caller to save.
+struct Reports {
+    report_files: Vec<TargetFile>,
+    full_report_df: DataFrame,
+    report_df: DataFrame,
+    top_uncovered_df: Option<DataFrame>,
+    /// Fully covered files left out of the saved full report, with `--hide-fully-covered`.
+    hidden_files: Option<usize>
+}
 
-    println!("\nYour report is ready at:\n{:?}", report_path);
-    println!("\nYour full report is at:\n{:?}", full_report_path);
+/// Computes the reports from the inputs and saves all but the summary in the run directory.
+fn generate_reports(inputs: &ReportInputs, identifier: &String) -> Result<Reports, XCReportError> {
+    let settings = &inputs.settings;
 
-    Ok(())
-}
\ No newline at end of file
+    events::phase_started("match_squads");
+    for name in settings.target_filter.unknown_names(&inputs.coverage) {
+        events::warning(&format!("no build target is named {:?}", name));
+    }
+    let report_files = match_squad_files(&inputs.squads, &inputs.coverage, &settings.target_filter, &settings.unowned_name, settings.ignore_path_case)?;
+    events::phase_finished("match_squads");
+
+    if settings.targets_report {
+        let mut targets_report_df = df::targets_report(&inputs.coverage, &settings.target_filter, &settings.rounding)?;
+        df::save_targets_report(&mut targets_report_df, identifier)?;
+    }
+
+    let (report_files, exclusions) = exclude::apply_exclusions(report_files, &inputs.exclusion_rules);
+    if !exclusions.is_empty() {
+        df::save_exclusions(&exclusions, identifier)?;
+    }
+
+    events::phase_started("report");
+    let mut full_report_df = df::process_full_report(report_dataframe(&report_files)?, &settings.file_filter)?;
+    let hidden_files = if settings.hide_fully_covered {
+        let (mut actionable_df, mut fully_covered_df) = df::split_fully_covered(&full_report_df)?;
+        df::save_full_report(&mut actionable_df, identifier)?;
+        // Kept aside so the history, comparisons and gates still see every file.
+        if fully_covered_df.height() > 0 {
+            df::save_fully_covered(&mut fully_covered_df, identifier)?;
+        }
+        Some(fully_covered_df.height())
+    } else {
+        df::save_full_report(&mut full_report_df, identifier)?;
+        None
+    };
+
+    let mut report_df = df::process_report(&full_report_df, &settings.rounding)?;
+
+    if let Some(squad_domains) = &inputs.squad_domains {
+        let mut domain_report_df = df::process_group_report(&full_report_df, squad_domains, "Domain", &settings.rounding)?;
+        df::save_domain_report(&mut domain_report_df, identifier)?;
+    }
+
+    if let Some(squad_tribes) = &inputs.squad_tribes {
+        let mut tribe_report_df = df::process_group_report(&full_report_df, squad_tribes, "Tribe", &settings.rounding)?;
+        df::save_tribe_report(&mut tribe_report_df, identifier)?;
+    }
+
+    if let Some(depth) = settings.path_prefix_depth {
+        let mut path_prefix_report_df = df::process_path_prefix_report(&full_report_df, depth, &settings.rounding)?;
+        df::save_path_prefix_report(&mut path_prefix_report_df, identifier)?;
+    }
+
+    if let Some(changed_files) = &inputs.changed_files {
+        let mut patch_report_df = df::process_patch_report(&full_report_df, changed_files, &settings.rounding)?;
+        df::save_patch_report(&mut patch_report_df, identifier)?;
+    }
+
+    if let Some(squad_tests) = &inputs.squad_tests {
+        let (squads, tests): (Vec<&str>, Vec<u64>) = squad_tests
+            .iter()
+            .map(|(squad, tests)| (squad.as_str(), *tests))
+            .unzip();
+        let squad_tests = df!("Squad" => squads, "Tests" => tests)
+            .map_err(XCReportError::Polars)?;
+        report_df = df::add_test_metrics(report_df, squad_tests, &settings.rounding)?;
+    }
+
+    if let Some(squad_history) = &inputs.squad_history {
+        report_df = df::add_volatility(report_df, squad_history, &settings.rounding)?;
+    }
+
+    report_df = df::aggregate_report(
+        report_df,
+        &full_report_df,
+        settings.aggregate,
+        settings.file_threshold,
+        &settings.rounding
+    )?;
+
+    let top_uncovered_df = match settings.top_uncovered {
+        Some(n) => {
+            let mut top_uncovered_df = df::top_uncovered(&full_report_df, n, &settings.rounding)?;
+            df::save_top_uncovered(&mut top_uncovered_df, identifier)?;
+            Some(top_uncovered_df)
+        },
+        None => None
+    };
+    events::phase_finished("report");
+
+    Ok(Reports { report_files, full_report_df, report_df, top_uncovered_df, hidden_files })
+}
+
+fn report_dataframe(report_files: &[TargetFile]) -> Result<DataFrame, XCReportError> {
+    let json = serde_json::to_string(report_files)
+        .map_err(XCReportError::Serde)?;
+
+    JsonReader::new(Cursor::new(json))
+        .finish()
+        .map_err(XCReportError::Polars)
+}
+
+/// Summary of other coverage than the run's, e.g. of a rerun of part of the tests, matched and
+/// filtered with the inputs of the run.
+fn squad_summary(inputs: &ReportInputs, coverage: &XCodeBuildReport) -> Result<DataFrame, XCReportError> {
+    let settings = &inputs.settings;
+    let report_files = match_squad_files(&inputs.squads, coverage, &settings.target_filter, &settings.unowned_name, settings.ignore_path_case)?;
+    let (report_files, _) = exclude::apply_exclusions(report_files, &inputs.exclusion_rules);
+    let full_report_df = df::process_full_report(report_dataframe(&report_files)?, &settings.file_filter)?;
+
+    df::process_report(&full_report_df, &settings.rounding)
+}
+
+/// Reruns the schemes once with their unit test bundles and once with their UI test bundles, found in
+/// the test results of the run, and saves the coverage of either kind per squad to `test_split.csv`.
+/// Skipped with a warning when the run lacks either kind.
+fn split_by_test_kind(
+    project_path: &Path,
+    workspace: &Path,
+    scheme_results: &[(&String, &PathBuf)],
+    destination: &str,
+    build_options: &BuildOptions,
+    report_options: &ReportOptions,
+    identifier: &String
+) -> Result<Option<PathBuf>, XCReportError> {
+    // Bundles of every scheme with whether they are UI tests.
+    let scheme_bundles: Vec<(&String, Vec<(String, bool)>)> = scheme_results
+        .iter()
+        .map(|&(scheme, xcresult_file)| {
+            xcresult::parse_test_results(xcresult_file).map(|test_results| {
+                let bundles: Vec<(String, bool)> = test_results
+                    .test_bundles()
+                    .into_iter()
+                    .map(|(name, is_ui)| (name.clone(), is_ui))
+                    .collect();
+                (scheme, bundles)
+            })
+        })
+        .collect::<Result<_, _>>()?;
+
+    for (kind, is_ui) in TEST_KINDS {
+        if !scheme_bundles.iter().flat_map(|(_, bundles)| bundles).any(|(_, bundle_is_ui)| *bundle_is_ui == is_ui) {
+            events::warning(&format!("the run has no {} test bundles, leaving its coverage unsplit", kind));
+            return Ok(None)
+        }
+    }
+
+    let inputs = snapshot::load_inputs(identifier)?;
+    let repo_root = repo_root(report_options)?;
+    let mut kind_summaries: Vec<DataFrame> = vec![];
+
+    for (kind, is_ui) in TEST_KINDS {
+        let mut kind_reports: Vec<XCodeBuildReport> = vec![];
+
+        for (scheme, bundles) in &scheme_bundles {
+            let only_testing: Vec<String> = bundles
+                .iter()
+                .filter(|(_, bundle_is_ui)| *bundle_is_ui == is_ui)
+                .map(|(name, _)| name.clone())
+                .collect();
+            if only_testing.is_empty() {
+                continue
+            }
+
+            println!("\nRerunning the {} tests of scheme {}", kind, scheme);
+            let outputs = SchemeOutputs {
+                xcresult_path: test_kind_xcresult_path(identifier, kind, scheme)?,
+                test_report_path: xcpretty_report_path(identifier, &format!("{}-{}-{}", kind, safe_file_name(scheme), build_options.test_report_name))?,
+                build_log_path: build_log_path(identifier, build_options.compress_log)?
+            };
+            let kind_options = BuildOptions { only_testing, ..build_options.clone() };
+            run_tests(project_path, workspace, scheme, destination, &kind_options, &outputs, false)?;
+            kind_reports.push(parse_coverage(&outputs.xcresult_path, report_options, identifier)?);
+        }
+
+        let mut kind_report = XCodeBuildReport::merge(kind_reports);
+        if let Some(repo_root) = &repo_root {
+            kind_report.relativize_paths(repo_root);
+        }
+        kind_summaries.push(squad_summary(&inputs, &kind_report)?);
+    }
+
+    let summary = squad_summary(&inputs, &inputs.coverage)?;
+    let mut test_split_df = df::test_split_report(&summary, &kind_summaries[0], &kind_summaries[1], &inputs.settings.rounding)?;
+
+    df::save_test_split_report(&mut test_split_df, identifier).map(Some)
+}
+
+/// Reruns the schemes with the `--compare-configuration`, e.g. Release, and saves the coverage of the run's
+/// configuration and of the other one per squad side by side to `configurations.csv`, printing it too.
+fn compare_configurations(
+    project_path: &Path,
+    workspace: &Path,
+    schemes: &[String],
+    destination: &str,
+    build_options: &BuildOptions,
+    report_options: &ReportOptions,
+    identifier: &String
+) -> Result<Option<PathBuf>, XCReportError> {
+    let Some(other_configuration) = build_options.compare_configuration.as_deref() else {
+        return Ok(None)
+    };
+
+    let inputs = snapshot::load_inputs(identifier)?;
+    let other_options = BuildOptions { configuration: Some(other_configuration.to_string()), ..build_options.clone() };
+    let mut other_reports: Vec<XCodeBuildReport> = vec![];
+
+    for scheme in schemes {
+        println!("\nRerunning the tests of scheme {} with the {} configuration", scheme, other_configuration);
+        let outputs = SchemeOutputs {
+            xcresult_path: configuration_xcresult_path(identifier, other_configuration, scheme)?,
+            test_report_path: xcpretty_report_path(identifier, &format!("{}-{}-{}", safe_file_name(other_configuration), safe_file_name(scheme), build_options.test_report_name))?,
+            build_log_path: build_log_path(identifier, build_options.compress_log)?
+        };
+        run_tests(project_path, workspace, scheme, destination, &other_options, &outputs, false)?;
+        other_reports.push(parse_coverage(&outputs.xcresult_path, report_options, identifier)?);
+    }
+
+    let mut other_report = XCodeBuildReport::merge(other_reports);
+    if let Some(repo_root) = repo_root(report_options)? {
+        other_report.relativize_paths(&repo_root);
+    }
+
+    let summary = squad_summary(&inputs, &inputs.coverage)?;
+    let other_summary = squad_summary(&inputs, &other_report)?;
+    // Without --configuration the scheme picks the configuration, Debug unless it was changed.
+    let configuration = build_options.configuration.as_deref().unwrap_or("Default");
+    let mut configuration_df = df::configuration_report(&summary, &other_summary, configuration, other_configuration, &inputs.settings.rounding)?;
+
+    table::print_summary(&configuration_df, inputs.settings.rounding.precision as usize)?;
+
+    df::save_configuration_report(&mut configuration_df, identifier).map(Some)
+}
+
+/// Prints the indexed runs as a table, the most recent first.
+fn list_runs(limit: Option<usize>) -> Result<(), XCReportError> {
+    let index = index::load_index()?;
+    if index.runs.is_empty() {
+        println!("No runs in {:?} yet", home_path()?);
+        return Ok(())
+    }
+
+    let runs: Vec<&index::IndexedRun> = index.runs
+        .iter()
+        .rev()
+        .take(limit.unwrap_or(usize::MAX))
+        .collect();
+
+    let dates: Vec<Option<String>> = runs
+        .iter()
+        .map(|run| {
+            run.timestamp
+                .as_deref()
+                .and_then(|timestamp| chrono::DateTime::parse_from_rfc3339(timestamp).ok())
+                .map(|date| date.format("%Y-%m-%d %H:%M").to_string())
+        })
+        .collect();
+
+    let runs_df = df!(
+        "Run" => runs.iter().map(|run| run.identifier.as_str()).collect::<Vec<_>>(),
+        "Date" => dates,
+        "Scheme" => runs.iter().map(|run| run.scheme.as_deref()).collect::<Vec<_>>(),
+        "Commit" => runs.iter().map(|run| run.commit.as_deref().map(|commit| &commit[..commit.len().min(7)])).collect::<Vec<_>>(),
+        "Coverage %" => runs.iter().map(|run| run.coverage).collect::<Vec<_>>(),
+        "Report" => runs.iter().map(|run| run.report_path.to_string_lossy().into_owned()).collect::<Vec<_>>()
+    ).map_err(XCReportError::Polars)?;
+
+    table::print_summary(&runs_df, 2)?;
+    println!("{} of {} run(s)", runs.len(), index.runs.len());
+
+    Ok(())
+}
+
+/// Opens the HTML report of the run with the default browser, or its summary when it has none, with
+/// macOS's `open`.
+fn open_report(identifier: Option<&String>, csv: bool, reveal: bool) -> Result<(), XCReportError> {
+    let index = index::load_index()?;
+    let run = match identifier {
+        Some(identifier) => index.runs.iter().find(|run| run.identifier == *identifier),
+        None => index.runs.last()
+    };
+
+    let html_index_path = match identifier.or(run.map(|run| &run.identifier)) {
+        Some(identifier) => html_report_path(identifier)?.join("index.html"),
+        None => return Err(XCReportError::FilePath(FilePathError::NotFound))
+    };
+    let summary_path = match (run, identifier) {
+        (Some(run), _) => run.report_path.clone(),
+        (None, Some(identifier)) => report_path(identifier)?,
+        (None, None) => return Err(XCReportError::FilePath(FilePathError::NotFound))
+    };
+
+    let path = if !csv && html_index_path.is_file() { html_index_path } else { summary_path };
+    if !path.is_file() {
+        return Err(XCReportError::FilePath(FilePathError::NotFound))
+    }
+
+    println!("Opening {:?}", path);
+
+    let mut open = Command::new("open");
+    if reveal {
+        open.arg("-R");
+    }
+    let status = open
+        .arg(&path)
+        .status()
+        .map_err(|e| XCReportError::CommandExecution(CommandExecutionError::Open(e)))?;
+
+    if !status.success() {
+        return Err(XCReportError::CommandExecution(CommandExecutionError::NonZeroExit {
+            desc: format!("open exited with {}", status)
+        }))
+    }
+
+    Ok(())
+}
+
+fn latest_full_report_path() -> Result<PathBuf, XCReportError> {
+    for identifier in index::run_identifiers()?.iter().rev() {
+        let full_report_path = full_report_path(identifier)?;
+        if full_report_path.is_file() {
+            return Ok(full_report_path)
+        }
+    }
+
+    Err(XCReportError::FilePath(FilePathError::NotFound))
+}
+
+/// Recomputes the reports of the run into a scratch directory and compares them byte by byte.
+fn verify_run(identifier: &String) -> Result<(), XCReportError> {
+    let inputs = snapshot::load_inputs(identifier)?;
+
+    let verify_identifier = format!("{}-verify", identifier);
+    let verify_dir = get_workdir(&verify_identifier)?;
+    let _workdir_guard = process::track_workdir(verify_dir.clone());
+
+    let comparison = generate_reports(&inputs, &verify_identifier)
+        .and_then(|mut reports| df::save_report_to_default(&mut reports.report_df, &verify_identifier))
+        .and_then(|_| compare_reports_of_runs(identifier, &verify_identifier));
+
+    std::fs::remove_dir_all(&verify_dir)
+        .map_err(XCReportError::FileIO)?;
+
+    let mismatched_files = comparison?;
+    if !mismatched_files.is_empty() {
+        return Err(XCReportError::Verify(VerifyError::Mismatch { files: mismatched_files }))
+    }
+
+    println!("\nThe reports of {} match their inputs", identifier);
+
+    Ok(())
+}
+
+/// Compares the report files of two runs, printing the outcome of every file and returning the mismatched ones.
+fn compare_reports_of_runs(identifier: &String, other_identifier: &String) -> Result<Vec<String>, XCReportError> {
+    let report_paths = [
+        (report_path(identifier)?, report_path(other_identifier)?),
+        (full_report_path(identifier)?, full_report_path(other_identifier)?),
+        (fully_covered_path(identifier)?, fully_covered_path(other_identifier)?),
+        (exclusions_path(identifier)?, exclusions_path(other_identifier)?),
+        (domain_report_path(identifier)?, domain_report_path(other_identifier)?),
+        (tribe_report_path(identifier)?, tribe_report_path(other_identifier)?),
+        (targets_report_path(identifier)?, targets_report_path(other_identifier)?),
+        (path_prefix_report_path(identifier)?, path_prefix_report_path(other_identifier)?),
+        (patch_report_path(identifier)?, patch_report_path(other_identifier)?),
+        (top_uncovered_path(identifier)?, top_uncovered_path(other_identifier)?)
+    ];
+
+    let mut mismatched_files: Vec<String> = vec![];
+
+    for (path, other_path) in report_paths {
+        let file_name = path.file_name().and_then(OsStr::to_str).unwrap_or_default().to_string();
+
+        match (std::fs::read(&path).ok(), std::fs::read(&other_path).ok()) {
+            (None, None) => continue,
+            // The summary is not in the run directory when it was saved to --output-file.
+            (None, Some(_)) if file_name == "report.csv" => {
+                println!("- {} was saved elsewhere, not verified", file_name);
+            },
+            (Some(contents), Some(other_contents)) if contents == other_contents => {
+                println!("✓ {}", file_name);
+            },
+            (Some(_), Some(_)) => {
+                println!("✗ {} differs", file_name);
+                mismatched_files.push(file_name);
+            },
+            (None, Some(_)) | (Some(_), None) => {
+                println!("✗ {} is missing", file_name);
+                mismatched_files.push(file_name);
+            }
+        }
+    }
+
+    Ok(mismatched_files)
+}
+
+fn upload_coverage(xcresult_inputs: &[PathBuf], repo_root: Option<&Path>, upload: &upload::Upload) -> Result<(), XCReportError> {
+    let xcresult_files = expand_xcresult_inputs(xcresult_inputs)?;
+    let repo_root = repo_root
+        .map(|repo_root| std::env::current_dir().map(|dir| dir.join(repo_root)))
+        .transpose()
+        .map_err(XCReportError::FileIO)?;
+
+    events::phase_started("parse_xcresult");
+    let archives = xcresult_files
+        .iter()
+        .map(|xcresult_file| xcresult::parse_archive(xcresult_file))
+        .collect::<Result<Vec<_>, _>>()?;
+    events::phase_finished("parse_xcresult");
+
+    let files = upload::source_coverage(archives, repo_root.as_deref());
+
+    events::phase_started("upload");
+    let url = upload.send(&files)?;
+    events::phase_finished("upload");
+
+    println!("\nUploaded the coverage of {} file(s) for commit {}:\n{}", files.len(), upload.commit, url);
+
+    Ok(())
+}
+
+/// Replaces directories in the inputs with the .xcresult files they contain.
+fn expand_xcresult_inputs(inputs: &[PathBuf]) -> Result<Vec<PathBuf>, XCReportError> {
+    let mut xcresult_files: Vec<PathBuf> = vec![];
+
+    for input in inputs {
+        if input.extension() == Some(OsStr::new("xcresult")) {
+            xcresult_files.push(input.clone());
+            continue
+        }
+
+        let mut bundles: Vec<PathBuf> = std::fs::read_dir(input)
+            .map_err(XCReportError::FileIO)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension() == Some(OsStr::new("xcresult")))
+            .collect();
+        bundles.sort();
+        xcresult_files.append(&mut bundles);
+    }
+
+    if xcresult_files.is_empty() {
+        return Err(XCReportError::FilePath(FilePathError::NotFound))
+    }
+
+    Ok(xcresult_files)
+}
+
+fn save_html_report(
+    report_df: &DataFrame,
+    top_uncovered_df: Option<&DataFrame>,
+    below_threshold: &[String],
+    report_files: &[TargetFile],
+    xcresult_files: &[PathBuf],
+    repo_root: Option<&Path>,
+    metadata: &RunMetadata
+) -> Result<PathBuf, XCReportError> {
+    let mut annotated: Vec<AnnotatedFile> = vec![];
+    let partially_covered: Vec<&TargetFile> = report_files
+        .iter()
+        .filter(|file| file.covered_lines() < file.executable_lines())
+        .collect();
+
+    let total = partially_covered.len();
+
+    for (index, file) in partially_covered.into_iter().enumerate() {
+        // The coverage archive is keyed by the build machine paths the report paths were made relative to.
+        let source_path = match repo_root {
+            Some(repo_root) => repo_root.join(file.file_path()),
+            None => PathBuf::from(file.file_path())
+        };
+        let bundle_lines = xcresult_files
+            .iter()
+            .map(|xcresult_file| xcresult::parse_line_coverage(xcresult_file, &source_path.to_string_lossy()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let lines = LineCoverage::merge(bundle_lines);
+        annotated.push(AnnotatedFile { file, source_path, lines });
+
+        events::progress("html", index + 1, total);
+    }
+
+    let html_report_path = html_report_path(&metadata.identifier)?;
+    html::save_html_report(report_df, top_uncovered_df, below_threshold, report_files, &annotated, metadata, &html_report_path)
+}
+
+/// Reads the squads of the `--ownership-source`, or of the input files without one, resolving the
+/// aliases of the `aliases_file` to their canonical names.
+fn parse_squads_file(filepaths: &[PathBuf], input_options: &InputOptions, aliases_file: Option<&Path>) -> Result<Vec<SquadData>, XCReportError> {
+    let mut squads_data = match &input_options.ownership_source {
+        Some(OwnershipSource::Backstage(catalog_dir)) => backstage::catalog_squads(catalog_dir)?,
+        None => merge_squads_files(filepaths, input_options)?
+    };
+
+    let mut squad_names = SquadNames::load(aliases_file)?;
+    for collision in normalize::normalize_squad_names(&mut squads_data, &mut squad_names) {
+        events::warning(&format!("squad names {:?} were merged into {:?}", collision.spellings, collision.normalized));
+    }
+
+    Ok(squads_data)
+}
+
+/// Merges the squads of the input files. Rows of later files come first, so their patterns match
+/// before the ones of earlier files, and a pattern listed again by a later file replaces the row of
+/// the earlier one, with a warning when their squads differ.
+fn merge_squads_files(filepaths: &[PathBuf], input_options: &InputOptions) -> Result<Vec<SquadData>, XCReportError> {
+    let mut squads_data: Vec<SquadData> = vec![];
+    // File and squad of every merged pattern.
+    let mut pattern_sources: HashMap<String, (&PathBuf, String)> = HashMap::new();
+
+    for filepath in filepaths.iter().rev() {
+        for squad_data in read_squads_file(filepath, input_options)? {
+            match pattern_sources.get(squad_data.file_name()) {
+                // Rows repeating a pattern of their own file are kept, for validate-mapping to report.
+                Some((source, _)) if *source == filepath => {},
+                Some((source, squad)) => {
+                    if !squad.eq_ignore_ascii_case(squad_data.squad_name()) {
+                        events::warning(&format!(
+                            "pattern {:?} of squad {:?} in {:?} is overridden by squad {:?} in {:?}",
+                            squad_data.file_name(), squad_data.squad_name(), filepath, squad, source
+                        ));
+                    }
+                    continue
+                },
+                None => {
+                    pattern_sources.insert(squad_data.file_name().clone(), (filepath, squad_data.squad_name().clone()));
+                }
+            }
+
+            squads_data.push(squad_data);
+        }
+    }
+
+    Ok(squads_data)
+}
+
+fn read_squads_file(filepath: &Path, input_options: &InputOptions) -> Result<Vec<SquadData>, XCReportError> {
+    let extension = filepath.extension().and_then(OsStr::to_str).unwrap_or_default();
+
+    match extension {
+        "yaml" | "yml" => {
+            let file = std::fs::File::open(filepath)
+                .map_err(XCReportError::FileIO)?;
+            let ownership: IndexMap<String, Ownership> = serde_yaml::from_reader(file)
+                .map_err(XCReportError::Yaml)?;
+            Ok(squads_from_ownership(ownership))
+        },
+        "json" => {
+            let file = std::fs::File::open(filepath)
+                .map_err(XCReportError::FileIO)?;
+            let ownership: IndexMap<String, Ownership> = serde_json::from_reader(file)
+                .map_err(XCReportError::Serde)?;
+            Ok(squads_from_ownership(ownership))
+        },
+        _ => parse_squads_csv(filepath, input_options)
+    }
+}
+
+/// Reads the changed file paths of `--changed-files`, from a file listing them or from git when given a ref.
+fn read_changed_files(changed_files: &str) -> Result<Vec<String>, XCReportError> {
+    let path = Path::new(changed_files);
+
+    let changed_files = if path.is_file() {
+        std::fs::read_to_string(path)
+            .map_err(XCReportError::FileIO)?
+            .lines()
+            .map(|line| line.trim().to_string())
+            .collect()
+    } else {
+        metadata::git_changed_files(Path::new("."), changed_files)
+            .ok_or(XCReportError::GitDiff { base: changed_files.to_string() })?
+    };
+
+    Ok(changed_files.into_iter().filter(|path| !path.is_empty()).collect())
+}
+
+/// Reads the squad and domain pairs of a tags file.
+fn parse_tags_file(filepath: &Path, squad_names: &SquadNames) -> Result<Vec<(String, String)>, XCReportError> {
+    let file = std::fs::File::open(filepath)
+        .map_err(XCReportError::FileIO)?;
+
+    let tags: IndexMap<String, Vec<String>> = match filepath.extension().and_then(OsStr::to_str) {
+        Some("json") => serde_json::from_reader(file).map_err(XCReportError::Serde)?,
+        _ => serde_yaml::from_reader(file).map_err(XCReportError::Yaml)?
+    };
+
+    let squad_domains = tags
+        .into_iter()
+        .flat_map(|(domain, squads)| {
+            squads
+                .into_iter()
+                .map(move |squad| (squad_names.resolve(&squad), domain.clone()))
+        })
+        .collect();
+
+    Ok(squad_domains)
+}
+
+/// Flattens a squad -> path patterns mapping, keeping the order of the file. Squads nested under
+/// a tribe belong to it.
+fn squads_from_ownership(ownership: IndexMap<String, Ownership>) -> Vec<SquadData> {
+    ownership
+        .into_iter()
+        .flat_map(|(name, ownership)| match ownership {
+            Ownership::Patterns(patterns) => patterns
+                .into_iter()
+                .map(|pattern| SquadData::new(name.clone(), pattern, None))
+                .collect::<Vec<SquadData>>(),
+            Ownership::Tribe(squads) => squads
+                .into_iter()
+                .flat_map(|(squad_name, patterns)| {
+                    let tribe = name.clone();
+                    patterns
+                        .into_iter()
+                        .map(move |pattern| SquadData::new(squad_name.clone(), pattern, Some(tribe.clone())))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// The tribe of every squad of the input file that has one, warning about squads given several.
+fn squad_tribes(squads_data: &[SquadData]) -> Vec<(String, String)> {
+    let mut squad_tribes: IndexMap<String, String> = IndexMap::new();
+
+    for squad_data in squads_data {
+        let Some(tribe) = squad_data.tribe() else {
+            continue
+        };
+
+        match squad_tribes.get(squad_data.squad_name()) {
+            Some(known_tribe) if known_tribe != tribe => events::warning(&format!(
+                "squad {:?} is in tribes {:?} and {:?}, keeping {:?}",
+                squad_data.squad_name(), known_tribe, tribe, known_tribe
+            )),
+            Some(_) => {},
+            None => {
+                squad_tribes.insert(squad_data.squad_name().clone(), tribe.clone());
+            }
+        }
+    }
+
+    squad_tribes.into_iter().collect()
+}
+
+/// Reads the squad and path columns of the csv, named by the `input_options`, along with an optional Tribe column.
+fn parse_squads_csv(filepath: &Path, input_options: &InputOptions) -> Result<Vec<SquadData>, XCReportError> {
+    let df = CsvReader::from_path(filepath)
+        .map_err(XCReportError::Polars)?
+        .has_header(true)
+        .finish()
+        .map_err(XCReportError::Polars)?;
+
+    let mut columns = vec![input_options.squad_column.as_str(), input_options.path_column.as_str()];
+    for column in &columns {
+        if df.get_column_index(column).is_none() {
+            return Err(XCReportError::MissingInputColumn {
+                column: column.to_string(),
+                columns: format!("{:?}", df.get_column_names())
+            })
+        }
+    }
+    // Squad data is read by the default column names.
+    let mut names = vec!["Squad", "Filepath"];
+    if df.get_column_index("Tribe").is_some() {
+        columns.push("Tribe");
+        names.push("Tribe");
+    }
+    let mut df = df.select(columns)
+        .map_err(XCReportError::Polars)?;
+    df.set_column_names(&names)
+        .map_err(XCReportError::Polars)?;
+
+    let mut bytes: Vec<u8> = vec![];
+
+    JsonWriter::new(&mut bytes)
+        .with_json_format(JsonFormat::Json)
+        .finish(&mut df)
+        .map_err(XCReportError::Polars)?;
+
+    let squads_data: Vec<SquadData> = serde_json::from_slice(&bytes[..])
+        .map_err(XCReportError::Serde)?;
+
+    Ok(squads_data)
+}
+
+fn compare_reports(
+    base: &Path,
+    head: &Path,
+    identifier: &String,
+    html: bool,
+    rounding: &RoundingOptions
+) -> Result<(), XCReportError> {
+    let base_df = df::read_report_csv(base)?;
+    let head_df = df::read_report_csv(head)?;
+
+    let mut squad_delta_df = df::squad_delta(&base_df, &head_df, rounding)?;
+    let mut file_delta_df = df::file_delta(&base_df, &head_df, rounding)?;
+
+    let squad_delta_path = df::save_squad_delta(&mut squad_delta_df, identifier)?;
+    let file_delta_path = df::save_file_delta(&mut file_delta_df, identifier)?;
+
+    table::print_delta(&squad_delta_df, &file_delta_df, rounding.precision as usize)?;
+
+    println!("\nSquad coverage deltas are at:\n{:?}", squad_delta_path);
+    println!("\nFile coverage deltas are at:\n{:?}", file_delta_path);
+
+    if html {
+        let compare_html_path = compare_html_path(identifier)?;
+        html::save_compare_html(&squad_delta_df, &file_delta_df, &compare_html_path)?;
+        println!("\nYour HTML diff is at:\n{:?}", compare_html_path);
+    }
+
+    Ok(())
+}
+
+/// Evaluates the coverage gate and reports its outcome to the pull request when requested.
+fn check_gate(report_df: &DataFrame, metadata: &RunMetadata, report_options: &ReportOptions) -> Result<(), XCReportError> {
+    let gate_result = gate::check(report_df, report_options);
+    let unowned_result = match report_options.gate.max_unowned_share {
+        Some(_) => df::read_full_report(&metadata.identifier)
+            .and_then(|full_report_df| gate::check_unowned_share(&full_report_df, report_options)),
+        None => Ok(())
+    };
+    let ratchet_result = ratchet::check(&metadata.identifier, report_options);
+    let command_result = gate::check_commands(report_df, metadata, report_options);
+    let gate_result = gate_result.and(unowned_result).and(ratchet_result).and(command_result);
+    // The gate could not be evaluated on any other error, so no outcome is reported for it.
+    let passed = match &gate_result {
+        Ok(()) => Some(true),
+        Err(XCReportError::Threshold(_)) => Some(false),
+        Err(_) => None
+    };
+    let violation = match &gate_result {
+        Err(XCReportError::Threshold(e)) => Some(e.to_string()),
+        _ => None
+    };
+
+    // Teams adopting a gate see its violations without the run failing on them.
+    let gate_result = match gate_result {
+        Err(XCReportError::Threshold(e)) if report_options.gate.threshold_mode == ThresholdMode::Warn => {
+            events::warning(&format!("{}, not failing the run with --threshold-mode warn", e));
+            Ok(())
+        },
+        gate_result => gate_result
+    };
+
+    let label_result = match passed {
+        Some(passed) if report_options.github.github_labels => github::label_gate_outcome(&report_options.github, passed),
+        _ => Ok(())
+    };
+
+    let comment_result = if report_options.github.github_comment {
+        comment_coverage(&metadata.identifier, report_options)
+    } else {
+        Ok(())
+    };
+
+    let checks_result = match passed {
+        Some(passed) if report_options.github.github_checks => publish_github_checks(metadata, report_options, passed),
+        _ => Ok(())
+    };
+
+    let notify_result = match passed {
+        Some(passed) => notify::notify_run(&report_options.notify, metadata, passed, &report_options.rounding),
+        None => Ok(())
+    };
+
+    let report_hook_result = hooks::run(Hook::ReportGenerated, metadata, report_options, None);
+
+    let violation_hook_result = match &violation {
+        Some(violation) => hooks::run(Hook::ThresholdViolation, metadata, report_options, Some(violation)),
+        None => Ok(())
+    };
+
+    // The first failure fails the run, the ones after it are only reported.
+    let mut first_error = gate_result.err();
+    let publish_results = [
+        ("label the pull request", label_result),
+        ("comment on the pull request", comment_result),
+        ("create the check run", checks_result),
+        ("send the notification", notify_result),
+        ("run the on_report_generated hook", report_hook_result),
+        ("run the on_threshold_violation hook", violation_hook_result)
+    ];
+    for (action, result) in publish_results {
+        match (result, &first_error) {
+            (Ok(()), _) => {},
+            (Err(e), Some(_)) => events::warning(&format!("could not {}: {}", action, e)),
+            (Err(e), None) => first_error = Some(e)
+        }
+    }
+
+    match first_error {
+        Some(e) => Err(e),
+        None => Ok(())
+    }
+}
+
+/// Comments the coverage of the run on the pull request, with the changes since the base report or the previous run.
+fn comment_coverage(identifier: &String, report_options: &ReportOptions) -> Result<(), XCReportError> {
+    let head_df = df::read_full_report(identifier)?;
+
+    let base_df = match &report_options.github.github_comment_base {
+        Some(base_path) => Some(df::read_report_csv(base_path)?),
+        None => previous_full_report(identifier)?
+    };
+
+    let comment = comment::coverage_comment(&head_df, base_df.as_ref(), report_options.file_threshold, &report_options.rounding)?;
+    github::comment_coverage(&report_options.github, &comment)
+}
+
+/// The full report of the latest run before the given one that has one.
+fn previous_full_report(identifier: &String) -> Result<Option<DataFrame>, XCReportError> {
+    for other_identifier in index::run_identifiers()?.into_iter().rev().filter(|other_identifier| other_identifier < identifier) {
+        if full_report_path(&other_identifier)?.is_file() {
+            return df::read_full_report(&other_identifier).map(Some)
+        }
+    }
+
+    Ok(None)
+}
+
+/// Prints the overall coverage of the run in the `Coverage: 81.25%` form GitLab's coverage regex reads,
+/// then comments the coverage of every squad on the merge request of the pipeline, if there is one.
+fn publish_gitlab(options: &GitLabOptions) -> Result<(), XCReportError> {
+    let identifier = match &options.identifier {
+        Some(identifier) => identifier.clone(),
+        None => index::run_identifiers()?
+            .pop()
+            .ok_or(XCReportError::FilePath(FilePathError::NotFound))?
+    };
+
+    let head_df = df::read_full_report(&identifier)?;
+    let summary_df = df::process_report(&head_df, &options.rounding)?;
+    if let Some(coverage) = df::total_coverage(&summary_df)? {
+        println!("Coverage: {:.2}%", coverage);
+    }
+
+    let Some(merge_request) = gitlab::MergeRequest::from_options(options)? else {
+        println!("Not in a merge request pipeline, leaving out the note");
+        return Ok(())
+    };
+
+    let base_df = match &options.base {
+        Some(base_path) => Some(df::read_report_csv(base_path)?),
+        None => previous_full_report(&identifier)?
+    };
+
+    let note = comment::coverage_comment(&head_df, base_df.as_ref(), options.file_threshold, &options.rounding)?;
+    merge_request.upsert_note(comment::COMMENT_MARKER, &note)?;
+
+    println!("Commented the coverage on merge request !{}", merge_request.iid());
+
+    Ok(())
+}
+
+/// Creates a check run annotating the uncovered lines of the changed files, only the changed lines of them when
+/// `--changed-files` is a git ref.
+fn publish_github_checks(metadata: &RunMetadata, report_options: &ReportOptions, passed: bool) -> Result<(), XCReportError> {
+    let Some(changed_files) = report_options.changed_files.as_deref() else {
+        return Ok(())
+    };
+
+    let changed_lines = if Path::new(changed_files).is_file() {
+        None
+    } else {
+        let changed_lines = metadata::git_changed_lines(Path::new("."), changed_files)
+            .ok_or(XCReportError::GitDiff { base: changed_files.to_string() })?;
+        Some(changed_lines)
+    };
+    let changed_paths = read_changed_files(changed_files)?;
+
+    let repo_root = report_options.repo_root
+        .as_ref()
+        .map(|repo_root| std::env::current_dir().map(|dir| dir.join(repo_root)))
+        .transpose()
+        .map_err(XCReportError::FileIO)?;
+
+    let full_report_df = df::read_report_csv(&full_report_path(&metadata.identifier)?)?;
+    let paths = full_report_df.column("Filepath")
+        .and_then(|c| c.str())
+        .map_err(XCReportError::Polars)?;
+
+    let mut annotations: Vec<github::Annotation> = vec![];
+    let mut annotated_files = 0;
+
+    for path in paths.into_iter().flatten() {
+        let Some(changed_path) = changed_paths.iter().find(|changed_path| path == changed_path.as_str() || path.ends_with(&format!("/{}", changed_path))) else {
+            continue
+        };
+
+        // The coverage archive is keyed by the build machine paths the report paths were made relative to.
+        let source_path = match &repo_root {
+            Some(repo_root) => repo_root.join(path),
+            None => PathBuf::from(path)
+        };
+        let bundle_lines = metadata.xcresult_files
+            .iter()
+            .map(|xcresult_file| xcresult::parse_line_coverage(xcresult_file, &source_path.to_string_lossy()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let uncovered_lines: Vec<usize> = LineCoverage::merge(bundle_lines)
+            .iter()
+            .filter(|line| line.hits() == Some(0))
+            .map(LineCoverage::line)
+            .filter(|line| match &changed_lines {
+                Some(changed_lines) => changed_lines
+                    .get(changed_path)
+                    .is_some_and(|ranges| ranges.iter().any(|(start, end)| (*start..=*end).contains(line))),
+                None => true
+            })
+            .collect();
+
+        if !uncovered_lines.is_empty() {
+            annotated_files += 1;
+            annotations.extend(github::Annotation::uncovered_lines(changed_path, &uncovered_lines));
+        }
+    }
+
+    let conclusion = match (passed, report_options.gate.threshold_mode) {
+        (true, _) => "success",
+        (false, ThresholdMode::Warn) => "neutral",
+        (false, ThresholdMode::Fail) => "failure"
+    };
+    let title = match annotations.len() {
+        0 => String::from("All changed lines are covered"),
+        count => format!("{} uncovered range(s) in {} changed file(s)", count, annotated_files)
+    };
+    let summary = format!("Uncovered lines of the {} changed file(s) of run {}.", changed_paths.len(), metadata.identifier);
+
+    github::create_check_run(&report_options.github, metadata.commit.as_deref(), conclusion, &title, &summary, &annotations)
+}
+
+fn save_dashboard(output_dir: &Path, rounding: &RoundingOptions) -> Result<(), XCReportError> {
+    let runs = history::load_history(rounding)?;
+    let trends = history::squad_trends(&runs)?;
+    let latest = runs.last().and_then(|run| run.metadata.as_ref());
+
+    let dashboard_path = html::save_dashboard(&trends, latest, rounding.precision as usize, output_dir)?;
+
+    println!("\nFound {} run(s) with a full report.", runs.len());
+    println!("\nYour dashboard is at:\n{:?}", dashboard_path);
+
+    Ok(())
+}
+
+fn export_history(format: HistoryFormat, out: &Path, rounding: &RoundingOptions) -> Result<(), XCReportError> {
+    let runs = history::load_history(rounding)?;
+    let mut trends_df = history::trend_table(&runs)?;
+
+    let trends_path = df::save_trends(&mut trends_df, out, format)?;
+
+    println!("\nFound {} run(s) with a full report.", runs.len());
+    println!("\nYour trend table is at:\n{:?}", trends_path);
+
+    Ok(())
+}
+
+fn infer_owners(root: &Path, patterns: &[String], output_file: Option<&Path>) -> Result<(), XCReportError> {
+    let ownership = owners::infer_owners(root, patterns)?;
+
+    if ownership.is_empty() {
+        events::warning(&format!("no directories match {:?}", patterns));
+    }
+
+    let yaml = serde_yaml::to_string(&ownership)
+        .map_err(XCReportError::Yaml)?;
+
+    match output_file {
+        Some(output_file) => {
+            std::fs::write(output_file, yaml)
+                .map_err(XCReportError::FileIO)?;
+            println!("\nInferred {} squad(s), your squads file is at:\n{:?}", ownership.len(), output_file);
+        },
+        None => print!("{}", yaml)
+    }
+
+    Ok(())
+}
+
+fn export_codeowners(input_files: &[PathBuf], input_options: &InputOptions, teams_file: &Path, output_file: Option<&Path>) -> Result<(), XCReportError> {
+    let squads_data = parse_squads_file(input_files, input_options, None)?;
+
+    let file = std::fs::File::open(teams_file)
+        .map_err(XCReportError::FileIO)?;
+    let teams: IndexMap<String, owners::SquadOwners> = match teams_file.extension().and_then(OsStr::to_str) {
+        Some("json") => serde_json::from_reader(file).map_err(XCReportError::Serde)?,
+        _ => serde_yaml::from_reader(file).map_err(XCReportError::Yaml)?
+    };
+
+    let (rules, unmapped_squads) = owners::codeowners_rules(&squads_data, &teams);
+    for squad in &unmapped_squads {
+        events::warning(&format!("squad {:?} has no owners in {:?}, leaving its paths out", squad, teams_file));
+    }
+
+    let (source_name, source_kind) = match &input_options.ownership_source {
+        Some(OwnershipSource::Backstage(catalog_dir)) => (format!("the catalog-info files of {}", catalog_dir.display()), "catalog"),
+        None => {
+            let file_names: Vec<&str> = input_files
+                .iter()
+                .filter_map(|input_file| input_file.file_name().and_then(OsStr::to_str))
+                .collect();
+            (file_names.join(", "), "squads file")
+        }
+    };
+    let codeowners = format!(
+        "# Generated by `xcreport export-codeowners` from {}, edit the {} instead.\n\n{}\n",
+        source_name,
+        source_kind,
+        rules.join("\n")
+    );
+
+    match output_file {
+        Some(output_file) => {
+            std::fs::write(output_file, codeowners)
+                .map_err(XCReportError::FileIO)?;
+            println!("\nWrote {} rule(s), your CODEOWNERS file is at:\n{:?}", rules.len(), output_file);
+        },
+        None => print!("{}", codeowners)
+    }
+
+    Ok(())
+}
+
+/// The build options running only the suites that fit the `--budget` minutes, by their durations in the last runs.
+fn budgeted_build_options(build_options: &BuildOptions, report_options: &ReportOptions, minutes: u64) -> Result<BuildOptions, XCReportError> {
+    let changed_files = report_options.changed_files
+        .as_deref()
+        .map(read_changed_files)
+        .transpose()?
+        .unwrap_or_default();
+
+    let durations = shard::suite_durations(BUDGET_HISTORY_RUNS)?;
+    let suite_count = durations.len();
+    let selection = shard::select_within_budget(durations, &changed_files, minutes as f64 * 60.0);
+    if selection.suites.is_empty() {
+        return Err(XCReportError::BudgetTooSmall { minutes })
+    }
+
+    println!(
+        "Running {} of {} test suite(s), estimated to take {:.1} of the {} minute(s) budget",
+        selection.suites.len(),
+        suite_count,
+        selection.estimated_seconds / 60.0,
+        minutes
+    );
+
+    Ok(BuildOptions { only_testing: selection.suites, ..build_options.clone() })
+}
+
+/// Prints the problems of the squads file, failing when it has any.
+fn validate_mapping(input_files: &[PathBuf], input_options: &InputOptions, repo: &Path) -> Result<(), XCReportError> {
+    let squads_data = parse_squads_file(input_files, input_options, None)?;
+    let problems = mapping::validate_mapping(&squads_data, repo)?;
+
+    for (squad, pattern) in &problems.unmatched {
+        println!("✗ {:?} of squad {:?} matches no file", pattern, squad);
+    }
+
+    for duplicate in &problems.duplicates {
+        println!("✗ {:?} of squad {:?} is already listed for squad {:?}", duplicate.pattern, duplicate.squad, duplicate.first_squad);
+    }
+
+    for conflict in &problems.conflicts {
+        println!(
+            "✗ {:?} of squad {:?} matches {} file(s) owned by {:?} of squad {:?}, listed first",
+            conflict.pattern,
+            conflict.squad,
+            conflict.files,
+            conflict.owning_pattern,
+            conflict.owning_squad
+        );
+    }
+
+    match problems.count() {
+        0 => {
+            println!("Every pattern of the {} row(s) matches files of {:?}", squads_data.len(), repo);
+            Ok(())
+        },
+        problems => Err(XCReportError::InvalidMapping { problems })
+    }
+}
+
+/// Prints the arguments of every shard for CI jobs to pass to xcodebuild.
+fn print_shard_plan(shard_count: usize, history_runs: usize, format: ShardPlanFormat) -> Result<(), XCReportError> {
+    let durations = shard::suite_durations(history_runs)?;
+    let shards = shard::plan_shards(durations, shard_count);
+
+    match format {
+        ShardPlanFormat::Text => {
+            for shard in &shards {
+                println!("{}", shard.arguments.join(" "));
+            }
+        },
+        ShardPlanFormat::Json => {
+            let json = serde_json::to_string_pretty(&shards)
+                .map_err(XCReportError::Serde)?;
+            println!("{}", json);
+        }
+    }
+
+    Ok(())
+}
+
+/// Copies the reports of the run into the `--artifacts-dir`, if given.
+fn copy_artifacts(identifier: &String, report_path: &Path, test_report_paths: &[PathBuf], report_options: &ReportOptions) -> Result<(), XCReportError> {
+    let Some(artifacts_dir) = &report_options.artifacts_dir else {
+        return Ok(())
+    };
+
+    manifest::copy_artifacts(identifier, report_path, test_report_paths, artifacts_dir)?;
+    println!("\nThe reports of the run were copied to:\n{:?}", artifacts_dir);
+
+    Ok(())
+}
+
+fn print_result(
+    report_df: &DataFrame,
+    report_path: &PathBuf,
+    identifier: &String,
+    test_report_paths: &[PathBuf],
+    report_options: &ReportOptions
+) -> Result<(), XCReportError> {
+    if report_options.print_summary == PrintSummary::Json {
+        return summary::print_json(report_df, report_path, identifier, test_report_paths, report_options)
+    }
+
+    let full_report_path = full_report_path(identifier)?;
+
+    table::print_summary(report_df, report_options.rounding.precision as usize)?;
+
+    println!("\nYour report is ready at:\n{:?}", report_path);
+    println!("\nYour full report is at:\n{:?}", full_report_path);
+    println!("\nRun metadata is at:\n{:?}", metadata_path(identifier)?);
+    println!("\nProduced files are listed at:\n{:?}", artifact_manifest_path(identifier)?);
+
+    for test_report_path in test_report_paths.iter().filter(|path| path.exists()) {
+        println!("\nYour test report is at:\n{:?}", test_report_path);
+    }
+
+    for build_log_path in [build_log_path(identifier, false)?, build_log_path(identifier, true)?].iter().filter(|path| path.exists()) {
+        println!("\nThe raw xcodebuild output is at:\n{:?}", build_log_path);
+    }
+
+    let exclusions_path = exclusions_path(identifier)?;
+    if exclusions_path.exists() {
+        println!("\nExcluded files are listed at:\n{:?}", exclusions_path);
+    }
+
+    if report_options.top_uncovered.is_some() {
+        let top_uncovered_path = top_uncovered_path(identifier)?;
+        println!("\nFiles with the most uncovered lines are listed at:\n{:?}", top_uncovered_path);
+    }
+
+    if report_options.tags_file.is_some() {
+        let domain_report_path = domain_report_path(identifier)?;
+        println!("\nYour domain report is at:\n{:?}", domain_report_path);
+    }
+
+    if report_options.targets_report {
+        let targets_report_path = targets_report_path(identifier)?;
+        println!("\nYour target report is at:\n{:?}", targets_report_path);
+    }
+
+    if report_options.group_by == GroupBy::Tribe {
+        let tribe_report_path = tribe_report_path(identifier)?;
+        println!("\nYour tribe report is at:\n{:?}", tribe_report_path);
+    }
+
+    if report_options.group_by == GroupBy::PathPrefix {
+        let path_prefix_report_path = path_prefix_report_path(identifier)?;
+        println!("\nYour directory report is at:\n{:?}", path_prefix_report_path);
+    }
+
+    if report_options.changed_files.is_some() {
+        let patch_report_path = patch_report_path(identifier)?;
+        let patch_coverage = df::total_coverage(&df::read_report_csv(&patch_report_path)?)?;
+        let estimated_marker = metadata::load_metadata(identifier)
+            .and_then(|metadata| metadata.test_budget_minutes)
+            .map(|minutes| format!(" (estimated, from the tests fitting the {} minute(s) budget)", minutes))
+            .unwrap_or_default();
+        match patch_coverage {
+            Some(coverage) => println!("\nPatch coverage of the changed files is {:.*}%{}", report_options.rounding.precision as usize, coverage, estimated_marker),
+            None => println!("\nNone of the changed files has executable lines")
+        }
+        println!("\nYour patch coverage report is at:\n{:?}", patch_report_path);
+    }
+
+    if report_options.html {
+        let html_report_path = html_report_path(identifier)?.join("index.html");
+        println!("\nYour HTML report is at:\n{:?}", html_report_path);
+    }
+
+    if report_options.xlsx {
+        let xlsx_report_path = xlsx_report_path(identifier)?;
+        println!("\nYour Excel workbook is at:\n{:?}", xlsx_report_path);
+    }
+
+    if report_options.backstage_components.is_some() {
+        let backstage_path = backstage_path(identifier)?;
+        println!("\nYour Backstage scorecard metrics are at:\n{:?}", backstage_path);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_options() -> BuildOptions {
+        BuildOptions {
+            derived_data_path: None,
+            use_project_derived_data: false,
+            test_report_name: String::from("xcpretty_report.html"),
+            configuration: None,
+            compare_configuration: None,
+            compress_log: false,
+            timeout: None,
+            extract_attachments: None,
+            only_testing: vec![],
+            skip_testing: vec![],
+            budget: None,
+            split_by_test_target: false,
+            build_setting: vec![]
+        }
+    }
+
+    fn value_after<'a>(args: &'a [OsString], flag: &str) -> Option<&'a OsStr> {
+        args.iter()
+            .position(|arg| arg == flag)
+            .and_then(|index| args.get(index + 1))
+            .map(OsString::as_os_str)
+    }
+
+    #[test]
+    fn test_args_keep_schemes_and_destinations_with_spaces_and_commas_whole() {
+        let input_file = std::env::temp_dir().join(format!("xcreport-squads-{}.yaml", std::process::id()));
+        std::fs::write(&input_file, "").unwrap();
+        let destination = r"platform=iOS Simulator,name=Tester\, iPhone 15,OS=17.2";
+
+        let cli = Cli::try_parse_from([
+            OsStr::new("xcreport"),
+            OsStr::new("run"),
+            OsStr::new("--input-file"),
+            input_file.as_os_str(),
+            OsStr::new("--project-path"),
+            OsStr::new("/tmp/My App"),
+            OsStr::new("--scheme"),
+            OsStr::new("App Tests, Nightly"),
+            OsStr::new("--destination"),
+            OsStr::new(destination)
+        ]).unwrap();
+        std::fs::remove_file(&input_file).unwrap();
+
+        let Commands::Run { scheme, destination, build_options, .. } = cli.subcommand() else {
+            panic!("expected the run command")
+        };
+        assert_eq!(scheme, &vec![String::from("App Tests, Nightly")]);
+
+        let destination = destination.as_deref().unwrap();
+        let args = xcodebuild_test_args(
+            Path::new("/tmp/My App/App.xcworkspace"),
+            &scheme[0],
+            destination,
+            Path::new("/tmp/run/App Tests, Nightly.xcresult"),
+            None,
+            false,
+            build_options
+        );
+
+        assert_eq!(value_after(&args, "-workspace"), Some(OsStr::new("/tmp/My App/App.xcworkspace")));
+        assert_eq!(value_after(&args, "-scheme"), Some(OsStr::new("App Tests, Nightly")));
+        assert_eq!(value_after(&args, "-destination"), Some(OsStr::new(destination)));
+        assert_eq!(value_after(&args, "-resultBundlePath"), Some(OsStr::new("/tmp/run/App Tests, Nightly.xcresult")));
+    }
+
+    #[test]
+    fn test_args_end_with_the_test_action_and_its_options() {
+        let build_options = BuildOptions {
+            configuration: Some(String::from("Release")),
+            only_testing: vec![String::from("AppTests/LoginTests")],
+            skip_testing: vec![String::from("AppUITests")],
+            build_setting: vec![(String::from("CODE_SIGN_IDENTITY"), String::from("Apple Development"))],
+            ..build_options()
+        };
+        let args = xcodebuild_test_args(
+            Path::new("App.xcworkspace"),
+            "App",
+            "platform=iOS Simulator,name=iPhone 15",
+            Path::new("App.xcresult"),
+            Some(Path::new("/tmp/derived_data")),
+            true,
+            &build_options
+        );
+
+        assert_eq!(value_after(&args, "-configuration"), Some(OsStr::new("Release")));
+        assert_eq!(value_after(&args, "-derivedDataPath"), Some(OsStr::new("/tmp/derived_data")));
+        let action: Vec<&OsStr> = args.iter().skip_while(|arg| *arg != "clean").map(OsString::as_os_str).collect();
+        assert_eq!(action, [
+            "clean",
+            "test",
+            "-only-testing:AppTests/LoginTests",
+            "-skip-testing:AppUITests",
+            "CODE_SIGNING_REQUIRED=NO",
+            "CODE_SIGN_IDENTITY=Apple Development"
+        ]);
+    }
+
+    #[test]
+    fn test_args_keep_the_cache_of_the_project_derived_data() {
+        let build_options = BuildOptions { use_project_derived_data: true, ..build_options() };
+        let args = xcodebuild_test_args(Path::new("App.xcworkspace"), "App", "platform=macOS", Path::new("App.xcresult"), None, true, &build_options);
+
+        assert!(!args.iter().any(|arg| arg == "clean"));
+        assert!(!args.iter().any(|arg| arg == "-derivedDataPath"));
+    }
+}
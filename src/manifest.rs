@@ -0,0 +1,171 @@
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::err::XCReportError;
+use crate::fs::{
+    artifact_manifest_path, backstage_path, build_log_path, configuration_report_path, domain_report_path, exclusions_path, full_report_path, fully_covered_path, get_workdir,
+    html_report_path, metadata_path, patch_report_path, path_prefix_report_path, targets_report_path, test_split_path,
+    top_uncovered_path, tribe_report_path, xlsx_report_path
+};
+
+/// The files written by a run, saved as `artifacts.json` in the run directory.
+#[derive(Serialize)]
+pub struct ArtifactManifest {
+    pub identifier: String,
+    pub artifacts: Vec<Artifact>
+}
+
+#[derive(Serialize)]
+pub struct Artifact {
+    /// Relative to the run directory, or as given for files written outside of it.
+    pub path: PathBuf,
+    pub size: u64,
+    pub sha256: String
+}
+
+/// Lists every file of the run directory, the files inside result bundles included, followed by the
+/// `external_files` written elsewhere, e.g. the summary saved to `--output-file`.
+pub fn save_manifest(identifier: &String, external_files: &[&Path]) -> Result<PathBuf, XCReportError> {
+    let workdir = get_workdir(identifier)?;
+    let manifest_path = artifact_manifest_path(identifier)?;
+
+    let mut files: Vec<PathBuf> = vec![];
+    collect_files(&workdir, &mut files)
+        .map_err(XCReportError::FileIO)?;
+    files.retain(|path| *path != manifest_path);
+    files.sort();
+
+    let mut artifacts: Vec<Artifact> = vec![];
+
+    for path in &files {
+        let relative_path = path.strip_prefix(&workdir).unwrap_or(path);
+        artifacts.push(artifact(path, relative_path)?);
+    }
+
+    for path in external_files.iter().filter(|path| !path.starts_with(&workdir)) {
+        artifacts.push(artifact(path, path)?);
+    }
+
+    let manifest = ArtifactManifest { identifier: identifier.clone(), artifacts };
+    let json = serde_json::to_string_pretty(&manifest)
+        .map_err(XCReportError::Serde)?;
+
+    fs::write(&manifest_path, json)
+        .map_err(XCReportError::FileIO)?;
+
+    Ok(manifest_path)
+}
+
+/// Copies the reports of the run into `artifacts_dir` under stable names, for CI systems archiving a
+/// single directory: the summary as `report.<extension>`, wherever it was saved, and the other reports,
+/// the metadata and the test reports by their names in the run directory. Reports of an earlier run
+/// copied to the same directory that this run didn't write are removed.
+pub fn copy_artifacts(identifier: &String, report_path: &Path, test_report_paths: &[PathBuf], artifacts_dir: &Path) -> Result<(), XCReportError> {
+    fs::create_dir_all(artifacts_dir)
+        .map_err(XCReportError::FileIO)?;
+
+    let summary_name = format!("report.{}", report_path.extension().and_then(OsStr::to_str).unwrap_or("csv"));
+    let mut copies: Vec<(PathBuf, String)> = vec![(report_path.to_path_buf(), summary_name)];
+
+    let run_files = [
+        full_report_path(identifier)?,
+        metadata_path(identifier)?,
+        artifact_manifest_path(identifier)?,
+        fully_covered_path(identifier)?,
+        top_uncovered_path(identifier)?,
+        exclusions_path(identifier)?,
+        domain_report_path(identifier)?,
+        tribe_report_path(identifier)?,
+        targets_report_path(identifier)?,
+        path_prefix_report_path(identifier)?,
+        patch_report_path(identifier)?,
+        test_split_path(identifier)?,
+        configuration_report_path(identifier)?,
+        xlsx_report_path(identifier)?,
+        backstage_path(identifier)?,
+        html_report_path(identifier)?,
+        build_log_path(identifier, false)?,
+        build_log_path(identifier, true)?
+    ];
+    for path in run_files.into_iter().chain(test_report_paths.iter().cloned()) {
+        if let Some(name) = path.file_name().and_then(OsStr::to_str) {
+            let name = name.to_string();
+            copies.push((path, name));
+        }
+    }
+
+    for (path, name) in copies {
+        let destination = artifacts_dir.join(name);
+
+        if destination.is_dir() {
+            fs::remove_dir_all(&destination)
+                .map_err(XCReportError::FileIO)?;
+        } else if destination.exists() {
+            fs::remove_file(&destination)
+                .map_err(XCReportError::FileIO)?;
+        }
+
+        if path.is_dir() {
+            copy_dir(&path, &destination)
+                .map_err(XCReportError::FileIO)?;
+        } else if path.is_file() {
+            fs::copy(&path, &destination)
+                .map_err(XCReportError::FileIO)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn copy_dir(dir: &Path, destination: &Path) -> io::Result<()> {
+    fs::create_dir_all(destination)?;
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let Some(name) = path.file_name() else {
+            continue
+        };
+
+        if path.is_dir() {
+            copy_dir(&path, &destination.join(name))?;
+        } else {
+            fs::copy(&path, destination.join(name))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn artifact(path: &Path, listed_path: &Path) -> Result<Artifact, XCReportError> {
+    let mut file = File::open(path)
+        .map_err(XCReportError::FileIO)?;
+
+    let mut hasher = Sha256::new();
+    let size = io::copy(&mut file, &mut hasher)
+        .map_err(XCReportError::FileIO)?;
+
+    Ok(Artifact {
+        path: listed_path.to_path_buf(),
+        size,
+        sha256: format!("{:x}", hasher.finalize())
+    })
+}
+
+/// Every file below the directory, recursively.
+pub fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else if path.is_file() {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,130 @@
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use aho_corasick::AhoCorasick;
+
+use crate::data::SquadData;
+use crate::err::XCReportError;
+
+/// Problems of a squads file against the files of the repository it maps.
+#[derive(Default)]
+pub struct MappingProblems {
+    /// Squad and pattern of the rows matching no file.
+    pub unmatched: Vec<(String, String)>,
+    /// Rows repeating the pattern of an earlier row, which never match.
+    pub duplicates: Vec<Duplicate>,
+    /// Patterns of different squads matching the same files, which go to the squad listed first.
+    pub conflicts: Vec<Conflict>
+}
+
+pub struct Duplicate {
+    pub pattern: String,
+    pub squad: String,
+    pub first_squad: String
+}
+
+pub struct Conflict {
+    pub pattern: String,
+    pub squad: String,
+    pub owning_pattern: String,
+    pub owning_squad: String,
+    pub files: usize
+}
+
+impl MappingProblems {
+    pub fn count(&self) -> usize {
+        self.unmatched.len() + self.duplicates.len() + self.conflicts.len()
+    }
+}
+
+/// Matches every row of the squads file against the files of the repository, the way reports match
+/// the files of the test results: a file belongs to the first row whose pattern occurs in its
+/// absolute path. Hidden directories, e.g. `.git`, are left out.
+pub fn validate_mapping(squads: &[SquadData], repo: &Path) -> Result<MappingProblems, XCReportError> {
+    let mut problems = MappingProblems::default();
+
+    // Rows with the pattern of an earlier row are reported, then left out of the matching.
+    let mut rows: Vec<&SquadData> = vec![];
+    for squad in squads {
+        match rows.iter().find(|row| row.file_name() == squad.file_name()) {
+            Some(first) => problems.duplicates.push(Duplicate {
+                pattern: squad.file_name().clone(),
+                squad: squad.squad_name().clone(),
+                first_squad: first.squad_name().clone()
+            }),
+            None => rows.push(squad)
+        }
+    }
+
+    let automaton = AhoCorasick::new(rows.iter().map(|row| row.file_name()))
+        .map_err(XCReportError::Matcher)?;
+
+    let repo = repo.canonicalize()
+        .map_err(XCReportError::FileIO)?;
+    let mut files: Vec<PathBuf> = vec![];
+    collect_files(&repo, &mut files)?;
+
+    let mut matched_files: Vec<usize> = vec![0; rows.len()];
+    // Files of a row matched by an earlier row of another squad, by the indices of the row and the earlier one.
+    let mut overlaps: BTreeMap<(usize, usize), usize> = BTreeMap::new();
+
+    for file in &files {
+        let path = file.to_string_lossy();
+        let mut matches: Vec<usize> = automaton
+            .find_overlapping_iter(path.as_ref())
+            .map(|found| found.pattern().as_usize())
+            .collect();
+        matches.sort();
+        matches.dedup();
+
+        let Some(&owner) = matches.first() else {
+            continue
+        };
+
+        for &index in &matches {
+            matched_files[index] += 1;
+
+            if rows[index].squad_name() != rows[owner].squad_name() {
+                *overlaps.entry((index, owner)).or_default() += 1;
+            }
+        }
+    }
+
+    problems.unmatched = rows
+        .iter()
+        .zip(&matched_files)
+        .filter(|(_, count)| **count == 0)
+        .map(|(row, _)| (row.squad_name().clone(), row.file_name().clone()))
+        .collect();
+
+    problems.conflicts = overlaps
+        .into_iter()
+        .map(|((index, owner), files)| Conflict {
+            pattern: rows[index].file_name().clone(),
+            squad: rows[index].squad_name().clone(),
+            owning_pattern: rows[owner].file_name().clone(),
+            owning_squad: rows[owner].squad_name().clone(),
+            files
+        })
+        .collect();
+
+    Ok(problems)
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), XCReportError> {
+    for entry in std::fs::read_dir(dir).map_err(XCReportError::FileIO)? {
+        let path = entry.map_err(XCReportError::FileIO)?.path();
+
+        if path.file_name().and_then(OsStr::to_str).is_some_and(|name| name.starts_with('.')) {
+            continue
+        }
+
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
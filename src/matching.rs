@@ -0,0 +1,33 @@
+use aho_corasick::AhoCorasick;
+
+use crate::data::SquadData;
+use crate::err::XCReportError;
+
+/// Finds the squad a path belongs to: the first squad row, in the order of the squads file, whose
+/// pattern occurs in the path.
+///
+/// All patterns are searched in a single pass over the path, instead of trying every row in turn.
+pub struct SquadMatcher<'a> {
+    squads: &'a [SquadData],
+    automaton: AhoCorasick
+}
+
+impl<'a> SquadMatcher<'a> {
+    /// With `ignore_case`, patterns match paths whatever the case of their ASCII letters.
+    pub fn new(squads: &'a [SquadData], ignore_case: bool) -> Result<Self, XCReportError> {
+        let automaton = AhoCorasick::builder()
+            .ascii_case_insensitive(ignore_case)
+            .build(squads.iter().map(|squad| squad.file_name()))
+            .map_err(XCReportError::Matcher)?;
+
+        Ok(SquadMatcher { squads, automaton })
+    }
+
+    pub fn find(&self, path: &str) -> Option<&'a SquadData> {
+        self.automaton
+            .find_overlapping_iter(path)
+            .map(|found| found.pattern().as_usize())
+            .min()
+            .map(|index| &self.squads[index])
+    }
+}
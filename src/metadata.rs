@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use serde::{Deserialize, Serialize};
+
+use crate::err::XCReportError;
+use crate::fs::metadata_path;
+use crate::scrub::scrub;
+use crate::tools;
+
+/// Describes where a report came from, saved as `metadata.json` next to it.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct RunMetadata {
+    pub identifier: String,
+    pub timestamp: String,
+    pub commit: Option<String>,
+    pub branch: Option<String>,
+    pub xcode_version: Option<String>,
+    /// DEVELOPER_DIR of the run, set by `--xcode` or the environment.
+    #[serde(default)]
+    pub developer_dir: Option<PathBuf>,
+    pub scheme: Option<String>,
+    pub destination: Option<String>,
+    pub xcresult_files: Vec<PathBuf>,
+    /// Files left out of the full report by `--hide-fully-covered`.
+    #[serde(default)]
+    pub hidden_fully_covered_files: Option<usize>,
+    /// Minutes of tests picked by `--budget`, the coverage of the run being an estimate.
+    #[serde(default)]
+    pub test_budget_minutes: Option<u64>
+}
+
+impl RunMetadata {
+    /// Label and value pairs of the fields that are known, for report headers.
+    pub fn rows(&self) -> Vec<(&'static str, String)> {
+        let mut rows = vec![("Run", self.identifier.clone()), ("Date", self.timestamp.clone())];
+
+        let optional_rows = [
+            ("Commit", &self.commit),
+            ("Branch", &self.branch),
+            ("Xcode", &self.xcode_version),
+            ("Scheme", &self.scheme),
+            ("Destination", &self.destination)
+        ];
+
+        for (label, value) in optional_rows {
+            if let Some(value) = value {
+                rows.push((label, value.clone()));
+            }
+        }
+
+        if let Some(minutes) = self.test_budget_minutes {
+            rows.push(("Test budget", format!("{} minute(s), coverage is estimated", minutes)));
+        }
+
+        rows
+    }
+}
+
+/// Collects the run metadata, `repo_dir` being any directory inside the git repository under test.
+/// Everything except the identifier and timestamp is best-effort and left empty when unavailable.
+pub fn collect_metadata(
+    identifier: &str,
+    repo_dir: &Path,
+    scheme: Option<&str>,
+    destination: Option<&str>,
+    xcresult_files: &[PathBuf]
+) -> RunMetadata {
+    RunMetadata {
+        identifier: identifier.to_string(),
+        timestamp: chrono::offset::Local::now().to_rfc3339(),
+        commit: git_commit(repo_dir),
+        branch: git_branch(repo_dir),
+        xcode_version: xcode_version(repo_dir),
+        developer_dir: std::env::var_os("DEVELOPER_DIR").map(PathBuf::from),
+        scheme: scheme.map(str::to_string),
+        destination: destination.map(str::to_string),
+        xcresult_files: xcresult_files.to_vec(),
+        hidden_fully_covered_files: None,
+        test_budget_minutes: None
+    }
+}
+
+pub fn save_metadata(metadata: &RunMetadata) -> Result<PathBuf, XCReportError> {
+    let metadata_path = metadata_path(&metadata.identifier)?;
+
+    let json = serde_json::to_string_pretty(metadata)
+        .map_err(XCReportError::Serde)?;
+
+    std::fs::write(&metadata_path, scrub(&json))
+        .map_err(XCReportError::FileIO)?;
+
+    Ok(metadata_path)
+}
+
+/// Reads the metadata saved by the run `identifier`, if it has any.
+pub fn load_metadata(identifier: &String) -> Option<RunMetadata> {
+    let json = std::fs::read_to_string(metadata_path(identifier).ok()?).ok()?;
+
+    serde_json::from_str(&json).ok()
+}
+
+/// The `xcodebuild -version` output on a single line, e.g. `Xcode 15.2 Build version 15C500b`.
+pub fn xcode_version(current_dir: &Path) -> Option<String> {
+    command_output(tools::xcodebuild_path(), &["-version"], current_dir)
+        .map(|version| version.lines().collect::<Vec<_>>().join(" "))
+}
+
+pub fn git_commit(repo_dir: &Path) -> Option<String> {
+    command_output("git", &["rev-parse", "HEAD"], repo_dir)
+}
+
+pub fn git_branch(repo_dir: &Path) -> Option<String> {
+    command_output("git", &["rev-parse", "--abbrev-ref", "HEAD"], repo_dir)
+}
+
+/// Paths of the files changed between the merge base of `base` and HEAD, relative to the repository root.
+pub fn git_changed_files(repo_dir: &Path, base: &str) -> Option<Vec<String>> {
+    let output = Command::new("git")
+        .args(["diff", "--name-only", "--diff-filter=d", &format!("{}...HEAD", base)])
+        .current_dir(repo_dir)
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None
+    }
+
+    let changed_files = String::from_utf8(output.stdout).ok()?;
+
+    Some(changed_files.lines().map(str::to_string).collect())
+}
+
+/// Line ranges added or changed per file between the merge base of `base` and HEAD, by repository-relative path.
+pub fn git_changed_lines(repo_dir: &Path, base: &str) -> Option<HashMap<String, Vec<(usize, usize)>>> {
+    let output = Command::new("git")
+        .args(["diff", "--unified=0", "--diff-filter=d", &format!("{}...HEAD", base)])
+        .current_dir(repo_dir)
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None
+    }
+
+    let diff = String::from_utf8(output.stdout).ok()?;
+    let mut changed_lines: HashMap<String, Vec<(usize, usize)>> = HashMap::new();
+    let mut current_file: Option<String> = None;
+    // Only the file header names the file, an added line may start with `+++` too.
+    let mut in_header = false;
+
+    for line in diff.lines() {
+        if line.starts_with("diff --git ") {
+            in_header = true;
+            current_file = None;
+        } else if let Some(path) = line.strip_prefix("+++ ").filter(|_| in_header) {
+            current_file = path.strip_prefix("b/").map(str::to_string);
+        } else if let Some(hunk) = line.strip_prefix("@@ ") {
+            in_header = false;
+
+            // `@@ -12,3 +14,5 @@`, the count defaulting to 1 and being 0 when lines were only removed.
+            let (Some(file), Some(added)) = (&current_file, hunk.split_whitespace().find_map(|range| range.strip_prefix('+'))) else {
+                continue
+            };
+            let (start, count): (Option<usize>, Option<usize>) = match added.split_once(',') {
+                Some((start, count)) => (start.parse().ok(), count.parse().ok()),
+                None => (added.parse().ok(), Some(1))
+            };
+
+            if let (Some(start), Some(count)) = (start, count) {
+                if count > 0 {
+                    changed_lines.entry(file.clone()).or_default().push((start, start + count - 1));
+                }
+            }
+        }
+    }
+
+    Some(changed_lines)
+}
+
+fn command_output(program: impl AsRef<OsStr>, args: &[&str], current_dir: &Path) -> Option<String> {
+    let output = Command::new(program)
+        .args(args)
+        .current_dir(current_dir)
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None
+    }
+
+    String::from_utf8(output.stdout)
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
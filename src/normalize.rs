@@ -0,0 +1,114 @@
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsStr;
+use std::path::Path;
+use indexmap::IndexMap;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::data::SquadData;
+use crate::err::XCReportError;
+
+/// Distinct spellings in the input file that normalize to the same squad name.
+pub struct SquadNameCollision {
+    pub normalized: String,
+    pub spellings: Vec<String>
+}
+
+/// Canonical squad names by their folded spellings and aliases, resolving the squad names of other
+/// files, e.g. the tags file, to the names of the reports.
+#[derive(Default)]
+pub struct SquadNames {
+    canonical: HashMap<String, String>
+}
+
+impl SquadNames {
+    /// Resolves the aliases of the `--squad-aliases` file, if any.
+    pub fn load(aliases_file: Option<&Path>) -> Result<Self, XCReportError> {
+        match aliases_file {
+            Some(aliases_file) => Ok(SquadNames::with_aliases(&parse_aliases_file(aliases_file)?)),
+            None => Ok(SquadNames::default())
+        }
+    }
+
+    /// Resolves the aliases, given as canonical names with the other names they go by.
+    fn with_aliases(aliases: &IndexMap<String, Vec<String>>) -> Self {
+        let mut canonical: HashMap<String, String> = HashMap::new();
+
+        for (name, name_aliases) in aliases {
+            let name = normalize_squad_name(name);
+            for alias in name_aliases.iter().chain([&name]) {
+                canonical.insert(fold_squad_name(alias), name.clone());
+            }
+        }
+
+        SquadNames { canonical }
+    }
+
+    /// Adds the names of squads read from the input file, which are canonical already.
+    pub fn extend(&mut self, squads: &[SquadData]) {
+        for squad in squads {
+            self.canonical
+                .entry(fold_squad_name(squad.squad_name()))
+                .or_insert_with(|| squad.squad_name().clone());
+        }
+    }
+
+    /// The canonical name of the squad, or its normalized name when it is unknown.
+    pub fn resolve(&self, name: &str) -> String {
+        self.canonical
+            .get(&fold_squad_name(name))
+            .cloned()
+            .unwrap_or_else(|| normalize_squad_name(name))
+    }
+}
+
+/// Unicode (NFC) normalizes the name, trims it and collapses inner whitespace.
+pub fn normalize_squad_name(name: &str) -> String {
+    name.nfc()
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The normalized name, case-folded, which spellings of the same squad share.
+fn fold_squad_name(name: &str) -> String {
+    normalize_squad_name(name).to_lowercase()
+}
+
+/// Normalizes every squad name in place, resolving aliases and merging names that only differ in case
+/// into their first spelling, and reports names that were spelled in more than one way.
+pub fn normalize_squad_names(squads_data: &mut [SquadData], names: &mut SquadNames) -> Vec<SquadNameCollision> {
+    let mut spellings: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    for squad_data in squads_data.iter_mut() {
+        let raw_name = squad_data.squad_name().clone();
+        let canonical = names.canonical
+            .entry(fold_squad_name(&raw_name))
+            .or_insert_with(|| normalize_squad_name(&raw_name))
+            .clone();
+
+        let entry = spellings.entry(canonical.clone()).or_default();
+        if !entry.contains(&raw_name) {
+            entry.push(raw_name);
+        }
+
+        squad_data.set_squad_name(canonical);
+    }
+
+    spellings
+        .into_iter()
+        .filter(|(_, spellings)| spellings.len() > 1)
+        .map(|(normalized, spellings)| SquadNameCollision { normalized, spellings })
+        .collect()
+}
+
+/// Reads a yaml or json file mapping canonical squad names to the other names they go by.
+fn parse_aliases_file(filepath: &Path) -> Result<IndexMap<String, Vec<String>>, XCReportError> {
+    let file = std::fs::File::open(filepath)
+        .map_err(XCReportError::FileIO)?;
+
+    match filepath.extension().and_then(OsStr::to_str) {
+        Some("json") => serde_json::from_reader(file).map_err(XCReportError::Serde),
+        _ => serde_yaml::from_reader(file).map_err(XCReportError::Yaml)
+    }
+}
@@ -0,0 +1,122 @@
+use serde_json::json;
+
+use crate::cli::{NotifyFormat, NotifyOptions, RoundingOptions};
+use crate::err::{NotifyError, XCReportError};
+use crate::history::{self, HistoricalRun};
+use crate::metadata::RunMetadata;
+
+/// Coverage of a squad in a run and its change since the previous run.
+struct SquadChange {
+    squad: String,
+    coverage: f64,
+    /// `None` when the squad was not in the previous run.
+    change: Option<f64>
+}
+
+/// Posts the coverage of the run to the webhook, unless `--notify-min-change` is set, the gate
+/// passed and no squad moved by that much since the previous run.
+pub fn notify_run(options: &NotifyOptions, metadata: &RunMetadata, gate_passed: bool, rounding: &RoundingOptions) -> Result<(), XCReportError> {
+    let Some(webhook) = &options.notify_webhook else {
+        return Ok(())
+    };
+
+    let runs = history::load_history(rounding)?;
+    let Some(current_index) = runs.iter().position(|run| run.identifier == metadata.identifier) else {
+        return Ok(())
+    };
+    let previous = current_index.checked_sub(1).map(|index| &runs[index]);
+    let changes = squad_changes(&runs[current_index], previous)?;
+
+    // A failed gate lists every squad, the ones below the threshold may not have moved at all.
+    let moved: Vec<&SquadChange> = match options.notify_min_change {
+        Some(min_change) if gate_passed => changes
+            .iter()
+            .filter(|change| !change.change.is_some_and(|change| change.abs() < min_change))
+            .collect(),
+        _ => changes.iter().collect()
+    };
+
+    if gate_passed && moved.is_empty() {
+        println!("\nCoverage did not change enough since the previous run, skipping the notification");
+        return Ok(())
+    }
+
+    let text = message(metadata, gate_passed, &moved, rounding.precision as usize);
+    let payload = match options.notify_format {
+        NotifyFormat::Slack => json!({ "text": text }),
+        NotifyFormat::Teams => json!({ "@type": "MessageCard", "summary": "Coverage report", "text": text })
+    };
+
+    ureq::post(webhook)
+        .send_json(payload)
+        .map_err(|e| XCReportError::Notify(NotifyError::Request(Box::new(e))))?;
+
+    println!("\nSent the coverage notification");
+
+    Ok(())
+}
+
+fn squad_changes(current: &HistoricalRun, previous: Option<&HistoricalRun>) -> Result<Vec<SquadChange>, XCReportError> {
+    let previous_coverages = match previous {
+        Some(previous) => squad_coverages(previous)?,
+        None => vec![]
+    };
+
+    let changes = squad_coverages(current)?
+        .into_iter()
+        .map(|(squad, coverage)| {
+            let change = previous_coverages
+                .iter()
+                .find(|(previous_squad, _)| *previous_squad == squad)
+                .map(|(_, previous_coverage)| coverage - previous_coverage);
+
+            SquadChange { squad, coverage, change }
+        })
+        .collect();
+
+    Ok(changes)
+}
+
+fn squad_coverages(run: &HistoricalRun) -> Result<Vec<(String, f64)>, XCReportError> {
+    let squads = run.summary.column("Squad")
+        .and_then(|c| c.str())
+        .map_err(XCReportError::Polars)?;
+    let coverages = run.summary.column("Coverage %")
+        .and_then(|c| c.f64())
+        .map_err(XCReportError::Polars)?;
+
+    let coverages = squads
+        .into_iter()
+        .zip(coverages)
+        .filter_map(|(squad, coverage)| Some((squad?.to_string(), coverage?)))
+        .collect();
+
+    Ok(coverages)
+}
+
+fn message(metadata: &RunMetadata, gate_passed: bool, changes: &[&SquadChange], precision: usize) -> String {
+    let mut lines = vec![format!("Coverage of run {}", metadata.identifier)];
+
+    let revision: Vec<&str> = [&metadata.branch, &metadata.commit]
+        .into_iter()
+        .flatten()
+        .map(String::as_str)
+        .collect();
+    if !revision.is_empty() {
+        lines.push(revision.join(" @ "));
+    }
+
+    if !gate_passed {
+        lines.push(String::from("Coverage gate failed"));
+    }
+
+    for change in changes {
+        let delta = match change.change {
+            Some(delta) => format!("{:+.*}", precision, delta),
+            None => String::from("new")
+        };
+        lines.push(format!("• {}: {:.*}% ({})", change.squad, precision, change.coverage, delta));
+    }
+
+    lines.join("\n")
+}
@@ -0,0 +1,145 @@
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use indexmap::IndexMap;
+use serde::Deserialize;
+
+use crate::data::SquadData;
+use crate::err::XCReportError;
+use crate::normalize::normalize_squad_name;
+
+/// The GitHub owners of a squad in a teams file: a team like `@org/payments`, or a list of teams, users and emails.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum SquadOwners {
+    One(String),
+    Many(Vec<String>)
+}
+
+impl SquadOwners {
+    fn owners(&self) -> Vec<&str> {
+        match self {
+            SquadOwners::One(owner) => vec![owner.as_str()],
+            SquadOwners::Many(owners) => owners.iter().map(String::as_str).collect()
+        }
+    }
+}
+
+/// One squad per directory matching any of the patterns, named after the directory and owning its path
+/// relative to `root`. Directories with the same name are merged into one squad.
+pub fn infer_owners(root: &Path, patterns: &[String]) -> Result<IndexMap<String, Vec<String>>, XCReportError> {
+    let mut ownership: IndexMap<String, Vec<String>> = IndexMap::new();
+
+    for pattern in patterns {
+        for dir in matching_directories(root, pattern)? {
+            let Some(name) = dir.file_name().and_then(|name| name.to_str()) else {
+                continue
+            };
+
+            let path = format!("{}/", dir.to_string_lossy());
+            let paths = ownership.entry(normalize_squad_name(name)).or_default();
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+    }
+
+    Ok(ownership)
+}
+
+/// Directories under `root` matching `pattern`, relative to `root`. A `*` matches any part of a single
+/// path component, hidden directories only being matched by components starting with a dot.
+pub fn matching_directories(root: &Path, pattern: &str) -> Result<Vec<PathBuf>, XCReportError> {
+    let mut matches: Vec<PathBuf> = vec![PathBuf::new()];
+
+    for component in Path::new(pattern).components() {
+        let Component::Normal(component) = component else {
+            continue
+        };
+        let component = component.to_string_lossy();
+
+        let mut next: Vec<PathBuf> = vec![];
+
+        for dir in &matches {
+            if !component.contains('*') {
+                if root.join(dir).join(component.as_ref()).is_dir() {
+                    next.push(dir.join(component.as_ref()));
+                }
+                continue
+            }
+
+            let mut names: Vec<String> = fs::read_dir(root.join(dir))
+                .map_err(XCReportError::FileIO)?
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().is_dir())
+                .filter_map(|entry| entry.file_name().into_string().ok())
+                .filter(|name| !name.starts_with('.') || component.starts_with('.'))
+                .filter(|name| wildcard_match(&component, name))
+                .collect();
+            names.sort();
+
+            next.extend(names.into_iter().map(|name| dir.join(name)));
+        }
+
+        matches = next;
+    }
+
+    matches.retain(|dir| dir.components().next().is_some());
+
+    Ok(matches)
+}
+
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == name,
+        Some((prefix, rest)) => {
+            let Some(name) = name.strip_prefix(prefix) else {
+                return false
+            };
+
+            name.char_indices()
+                .map(|(index, _)| index)
+                .chain([name.len()])
+                .any(|index| wildcard_match(rest, &name[index..]))
+        }
+    }
+}
+
+/// The rules of a CODEOWNERS file giving every squad pattern to the owners of its squad, and the squads without
+/// owners in `teams`, whose patterns are left out. A path belongs to the first squad row whose pattern occurs in it,
+/// while CODEOWNERS applies the last matching rule, so the rules are written in reverse order.
+pub fn codeowners_rules(squads: &[SquadData], teams: &IndexMap<String, SquadOwners>) -> (Vec<String>, Vec<String>) {
+    let teams: IndexMap<String, &SquadOwners> = teams
+        .iter()
+        .map(|(squad, owners)| (normalize_squad_name(squad), owners))
+        .collect();
+
+    let mut rules: Vec<String> = vec![];
+    let mut unmapped_squads: Vec<String> = vec![];
+
+    for squad in squads.iter().rev() {
+        let pattern = squad.file_name().trim();
+        if pattern.is_empty() {
+            continue
+        }
+
+        match teams.get(squad.squad_name()) {
+            Some(owners) => rules.push(format!("{} {}", codeowners_pattern(pattern), owners.owners().join(" "))),
+            None if !unmapped_squads.contains(squad.squad_name()) => unmapped_squads.push(squad.squad_name().clone()),
+            None => {}
+        }
+    }
+
+    (rules, unmapped_squads)
+}
+
+/// Squad patterns match anywhere in a path, so they are matched at any depth unless they start at the root.
+fn codeowners_pattern(pattern: &str) -> String {
+    // Spaces would separate the pattern from its owners.
+    let pattern = pattern.replace(' ', "\\ ");
+
+    if pattern.starts_with('/') {
+        pattern
+    } else {
+        format!("**/{}", pattern)
+    }
+}
@@ -0,0 +1,119 @@
+use std::process::{Command, ExitStatus};
+use serde::Deserialize;
+use toml::Value;
+
+use crate::config;
+use crate::err::{ErrorCategory, XCReportError};
+
+/// Key of the array of tables of the config file listing the stages.
+const PIPELINE_KEY: &str = "pipeline";
+
+/// A stage of the pipeline: an xcreport command and what failures of it the pipeline goes on after.
+#[derive(Deserialize)]
+struct Stage {
+    /// Name printed for the stage, defaults to its command.
+    name: Option<String>,
+    /// Arguments of xcreport, starting with the command, e.g. `["generate", "--html"]`.
+    command: Vec<String>,
+    #[serde(default)]
+    continue_on_error: ContinueOnError
+}
+
+impl Stage {
+    fn name(&self) -> String {
+        self.name.clone().unwrap_or_else(|| self.command.join(" "))
+    }
+}
+
+/// `true` to go on after any failure of the stage, or the categories to go on after, e.g. `["threshold"]`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ContinueOnError {
+    Always(bool),
+    Categories(Vec<ErrorCategory>)
+}
+
+impl Default for ContinueOnError {
+    fn default() -> Self {
+        ContinueOnError::Always(false)
+    }
+}
+
+impl ContinueOnError {
+    fn allows(&self, category: ErrorCategory) -> bool {
+        match self {
+            ContinueOnError::Always(always) => *always,
+            ContinueOnError::Categories(categories) => categories.contains(&category)
+        }
+    }
+}
+
+/// How a stage of the pipeline ended.
+enum Outcome {
+    Passed,
+    Failed(ExitStatus),
+    Skipped
+}
+
+/// Runs the stages of the `[[pipeline]]` tables of the config file one after the other, each as an xcreport
+/// process given the config file too. A failed stage stops the pipeline unless its `continue_on_error`
+/// allows the failure. Fails with the first failed stage, exiting with its code, once the pipeline ended.
+pub fn run() -> Result<(), XCReportError> {
+    let config_path = config::find_config_file().ok_or(XCReportError::NoPipeline)?;
+    let stages: Vec<Stage> = match config::load_config(&config_path)?.remove(PIPELINE_KEY) {
+        Some(stages) => Value::try_into(stages).map_err(XCReportError::Config)?,
+        None => vec![]
+    };
+    if stages.is_empty() {
+        return Err(XCReportError::NoPipeline)
+    }
+    if let Some(stage) = stages.iter().find(|stage| stage.command.is_empty() || stage.command[0] == PIPELINE_KEY) {
+        return Err(XCReportError::InvalidPipelineStage { stage: stage.name() })
+    }
+
+    let executable = std::env::current_exe()
+        .map_err(XCReportError::FileIO)?;
+    let mut first_error: Option<XCReportError> = None;
+    let mut outcomes: Vec<(String, Outcome)> = vec![];
+
+    for (index, stage) in stages.iter().enumerate() {
+        println!("\nRunning pipeline stage {}: xcreport {}\n", stage.name(), stage.command.join(" "));
+
+        let status = Command::new(&executable)
+            .args(&stage.command)
+            .env("XCREPORT_CONFIG", &config_path)
+            .status()
+            .map_err(XCReportError::FileIO)?;
+        if status.success() {
+            outcomes.push((stage.name(), Outcome::Passed));
+            continue
+        }
+
+        // Killed by a signal, or an exit code of no category.
+        let category = status.code()
+            .and_then(ErrorCategory::from_exit_code)
+            .unwrap_or(ErrorCategory::Internal);
+        outcomes.push((stage.name(), Outcome::Failed(status)));
+        first_error.get_or_insert(XCReportError::PipelineStage { stage: stage.name(), status: status.to_string(), category });
+
+        if !stage.continue_on_error.allows(category) {
+            outcomes.extend(stages[index + 1..].iter().map(|stage| (stage.name(), Outcome::Skipped)));
+            break
+        }
+    }
+
+    println!("\nPipeline stages:");
+    for (name, outcome) in &outcomes {
+        let outcome = match outcome {
+            Outcome::Passed => String::from("passed"),
+            Outcome::Failed(status) => format!("failed, {}", status),
+            Outcome::Skipped => String::from("skipped")
+        };
+        println!("  {}: {}", name, outcome);
+    }
+
+    match first_error {
+        Some(e) => Err(e),
+        None => Ok(())
+    }
+}
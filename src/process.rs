@@ -0,0 +1,110 @@
+use std::io;
+use std::path::PathBuf;
+use std::process::{Child, ExitStatus};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::err::XCReportError;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Process groups and the workdir of the run in progress, cleaned up when the run is interrupted.
+static ACTIVE_RUN: Mutex<ActiveRun> = Mutex::new(ActiveRun { process_groups: vec![], workdir: None });
+
+struct ActiveRun {
+    process_groups: Vec<u32>,
+    workdir: Option<PathBuf>
+}
+
+/// Keeps the workdir registered for cleanup on interruption until dropped.
+pub struct WorkdirGuard;
+
+impl Drop for WorkdirGuard {
+    fn drop(&mut self) {
+        if let Ok(mut active_run) = ACTIVE_RUN.lock() {
+            active_run.workdir = None;
+        }
+    }
+}
+
+/// On SIGINT and SIGTERM, kills the registered process groups, removes the workdir of the run in
+/// progress and exits with status 130.
+pub fn install_interrupt_handler() -> Result<(), XCReportError> {
+    ctrlc::set_handler(|| {
+        let workdir = match ACTIVE_RUN.lock() {
+            Ok(mut active_run) => {
+                active_run.process_groups.iter().for_each(|pgid| kill_process_group(*pgid));
+                active_run.workdir.take()
+            },
+            Err(_) => None
+        };
+
+        match workdir {
+            Some(workdir) => {
+                let _ = std::fs::remove_dir_all(&workdir);
+                eprintln!("\nInterrupted, removed the incomplete run at {:?}", workdir);
+            },
+            None => eprintln!("\nInterrupted")
+        }
+
+        std::process::exit(130);
+    })
+    .map_err(XCReportError::Signal)
+}
+
+/// Registers the workdir of a run to be removed if it is interrupted before the guard is dropped.
+pub fn track_workdir(workdir: PathBuf) -> WorkdirGuard {
+    if let Ok(mut active_run) = ACTIVE_RUN.lock() {
+        active_run.workdir = Some(workdir);
+    }
+
+    WorkdirGuard
+}
+
+/// Registers the process group led by the child to be killed if the run is interrupted.
+pub fn track_process_group(child: &Child) {
+    if let Ok(mut active_run) = ACTIVE_RUN.lock() {
+        active_run.process_groups.push(child.id());
+    }
+}
+
+pub fn untrack_process_group(child: &Child) {
+    if let Ok(mut active_run) = ACTIVE_RUN.lock() {
+        active_run.process_groups.retain(|pgid| *pgid != child.id());
+    }
+}
+
+/// Waits for the child to exit, killing its process group once `timeout` elapses.
+/// Returns `None` when the child was killed.
+///
+/// The child must have been spawned as the leader of its own process group.
+pub fn wait_with_timeout(child: &mut Child, timeout: Option<Duration>) -> io::Result<Option<ExitStatus>> {
+    let Some(timeout) = timeout else {
+        return child.wait().map(Some)
+    };
+
+    let deadline = Instant::now() + timeout;
+
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status))
+        }
+
+        if Instant::now() >= deadline {
+            kill_process_group(child.id());
+            child.wait()?;
+            return Ok(None)
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Kills every process in the process group.
+fn kill_process_group(pgid: u32) {
+    // SAFETY: kill has no memory safety requirements, a negative pid addresses the process group.
+    unsafe {
+        libc::kill(-(pgid as libc::pid_t), libc::SIGKILL);
+    }
+}
@@ -0,0 +1,56 @@
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+use crate::err::{WorkspaceError, XCReportError};
+
+/// Directory levels below the project path searched for workspaces.
+const MAX_SEARCH_DEPTH: usize = 2;
+
+/// Finds the workspace to build when none is given: the only `.xcworkspace` in the project path,
+/// ignoring the ones CocoaPods generates when any other exists.
+/// Returns the path relative to the project path.
+pub fn find_workspace(project_path: &Path) -> Result<PathBuf, XCReportError> {
+    let mut workspaces: Vec<PathBuf> = vec![];
+    collect_workspaces(project_path, project_path, 0, &mut workspaces);
+    workspaces.sort();
+
+    let (pods_workspaces, mut own_workspaces): (Vec<PathBuf>, Vec<PathBuf>) = workspaces
+        .into_iter()
+        .partition(|workspace| workspace.components().any(|component| component.as_os_str() == "Pods"));
+
+    if own_workspaces.is_empty() {
+        own_workspaces = pods_workspaces;
+    }
+
+    match own_workspaces.len() {
+        0 => Err(XCReportError::Workspace(WorkspaceError::NotFound { project_path: project_path.to_path_buf() })),
+        1 => Ok(own_workspaces.remove(0)),
+        _ => Err(XCReportError::Workspace(WorkspaceError::Ambiguous { candidates: own_workspaces }))
+    }
+}
+
+fn collect_workspaces(project_path: &Path, dir: &Path, depth: usize, workspaces: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return
+    };
+
+    for path in entries.filter_map(|entry| entry.ok().map(|e| e.path())) {
+        if !path.is_dir() {
+            continue
+        }
+
+        let is_hidden = path.file_name().and_then(OsStr::to_str).is_some_and(|name| name.starts_with('.'));
+
+        match path.extension().and_then(OsStr::to_str) {
+            Some("xcworkspace") => {
+                if let Ok(relative_path) = path.strip_prefix(project_path) {
+                    workspaces.push(relative_path.to_path_buf());
+                }
+            },
+            // Every project embeds a workspace of its own, which xcodebuild can't build schemes of.
+            Some("xcodeproj") => continue,
+            _ if is_hidden || depth >= MAX_SEARCH_DEPTH => continue,
+            _ => collect_workspaces(project_path, &path, depth + 1, workspaces)
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use polars::frame::DataFrame;
+use polars::prelude::DataType;
+use serde::{Deserialize, Serialize};
+
+use crate::cli::{RatchetOptions, ReportOptions, RoundingOptions};
+use crate::df;
+use crate::err::{FilePathError, ThresholdError, XCReportError};
+use crate::index;
+
+/// Percentage points a squad may drop below its floor in a new ratchet file.
+const DEFAULT_TOLERANCE: f64 = 0.1;
+
+/// The coverage floors of the squads, committed as `coverage-ratchet.toml` and raised by `ratchet update`.
+#[derive(Serialize, Deserialize)]
+pub struct Ratchet {
+    /// Percentage points a squad may drop below its floor before the run fails, e.g. for flaky tests.
+    pub tolerance: f64,
+    /// Coverage % every squad has to keep.
+    #[serde(default)]
+    pub squads: BTreeMap<String, f64>
+}
+
+/// A squad below its ratcheted coverage.
+pub struct RatchetViolation {
+    pub squad: String,
+    pub coverage: f64,
+    pub floor: f64
+}
+
+/// Raises the floors of the ratchet file to the coverage of the latest or a given run, creating the
+/// file the first time. Floors are never lowered, and squads missing from the run keep theirs.
+pub fn update(options: &RatchetOptions) -> Result<(), XCReportError> {
+    let identifier = match &options.identifier {
+        Some(identifier) => identifier.clone(),
+        None => index::run_identifiers()?
+            .pop()
+            .ok_or(XCReportError::FilePath(FilePathError::NotFound))?
+    };
+
+    let summary = df::process_report(&df::read_full_report(&identifier)?, &RoundingOptions::default())?;
+
+    let mut ratchet = if options.ratchet_file.is_file() {
+        load_ratchet(&options.ratchet_file)?
+    } else {
+        Ratchet { tolerance: DEFAULT_TOLERANCE, squads: BTreeMap::new() }
+    };
+    if let Some(tolerance) = options.tolerance {
+        ratchet.tolerance = tolerance;
+    }
+
+    let mut raised = 0;
+    for (squad, coverage) in squad_coverages(&summary)? {
+        // Rounded down, so the floor never exceeds the coverage it was taken from.
+        let coverage = (coverage * 100.0).floor() / 100.0;
+
+        let floor = ratchet.squads.get(&squad).copied();
+        if floor.is_some_and(|floor| floor >= coverage) {
+            continue
+        }
+
+        match floor {
+            Some(floor) => println!("  {}: {:.2}% -> {:.2}%", squad, floor, coverage),
+            None => println!("  {}: {:.2}%", squad, coverage)
+        }
+        ratchet.squads.insert(squad, coverage);
+        raised += 1;
+    }
+
+    save_ratchet(&ratchet, &options.ratchet_file)?;
+    println!("Raised {} floor(s) of {:?} to the coverage of run {}", raised, options.ratchet_file, identifier);
+
+    Ok(())
+}
+
+pub fn load_ratchet(path: &Path) -> Result<Ratchet, XCReportError> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(XCReportError::FileIO)?;
+
+    toml::from_str(&contents)
+        .map_err(XCReportError::Config)
+}
+
+fn save_ratchet(ratchet: &Ratchet, path: &Path) -> Result<(), XCReportError> {
+    let contents = toml::to_string(ratchet)
+        .map_err(XCReportError::TomlSerialize)?;
+
+    std::fs::write(path, format!("# Coverage floors of the squads, raised by `xcreport ratchet update`.\n\n{}", contents))
+        .map_err(XCReportError::FileIO)
+}
+
+/// Lists the squads of the summary report whose unrounded coverage is below their floor by more than
+/// the tolerance. Squads without a floor, e.g. new ones, pass.
+pub fn violations(summary: &DataFrame, ratchet: &Ratchet) -> Result<Vec<RatchetViolation>, XCReportError> {
+    let violations = squad_coverages(summary)?
+        .into_iter()
+        .filter_map(|(squad, coverage)| {
+            let floor = *ratchet.squads.get(&squad)?;
+            (coverage < floor - ratchet.tolerance).then_some(RatchetViolation { squad, coverage, floor })
+        })
+        .collect();
+
+    Ok(violations)
+}
+
+/// Checks the squads of the run against the `--ratchet-file`, printing and failing on squads below their
+/// floor. The squad totals come from the full report, as the summary has none with `--aggregate files`.
+pub fn check(identifier: &String, report_options: &ReportOptions) -> Result<(), XCReportError> {
+    let Some(ratchet_file) = &report_options.gate.ratchet_file else {
+        return Ok(())
+    };
+
+    let precision = report_options.rounding.precision as usize;
+    let ratchet = load_ratchet(ratchet_file)?;
+    let summary = df::process_report(&df::read_full_report(identifier)?, &report_options.rounding)?;
+    let mut violations = violations(&summary, &ratchet)?;
+    if report_options.gate.gate_skip_unowned {
+        violations.retain(|violation| violation.squad != report_options.unowned_name);
+    }
+
+    if violations.is_empty() {
+        println!("\nAll squads keep the coverage ratcheted in {:?}", ratchet_file);
+        return Ok(())
+    }
+
+    println!("\nSquads below the coverage ratcheted in {:?}:", ratchet_file);
+    for violation in &violations {
+        println!("  {}: {:.*}%, ratcheted at {:.*}%", violation.squad, precision, violation.coverage, precision, violation.floor);
+    }
+
+    Err(XCReportError::Threshold(ThresholdError::Ratchet { count: violations.len(), tolerance: ratchet.tolerance }))
+}
+
+/// The unrounded coverage % of every squad of the summary report with executable lines.
+fn squad_coverages(summary: &DataFrame) -> Result<Vec<(String, f64)>, XCReportError> {
+    let squads = summary.column("Squad")
+        .and_then(|c| c.str())
+        .map_err(XCReportError::Polars)?;
+    let covered_lines = summary.column("Covered Lines")
+        .and_then(|c| c.cast(&DataType::Float64))
+        .map_err(XCReportError::Polars)?;
+    let executable_lines = summary.column("Executable Lines")
+        .and_then(|c| c.cast(&DataType::Float64))
+        .map_err(XCReportError::Polars)?;
+    let covered_lines = covered_lines.f64().map_err(XCReportError::Polars)?;
+    let executable_lines = executable_lines.f64().map_err(XCReportError::Polars)?;
+
+    let coverages = squads
+        .into_iter()
+        .zip(covered_lines.into_iter().zip(executable_lines))
+        .filter_map(|(squad, lines)| match (squad, lines) {
+            (Some(squad), (Some(covered), Some(executable))) if executable > 0.0 => {
+                Some((squad.to_string(), covered * 100.0 / executable))
+            },
+            _ => None
+        })
+        .collect();
+
+    Ok(coverages)
+}
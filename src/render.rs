@@ -0,0 +1,167 @@
+use std::ffi::OsStr;
+use std::fmt::Write as _;
+use std::io::Write;
+use std::path::Path;
+use polars::frame::DataFrame;
+use polars::prelude::{AnyValue, CsvWriter, JsonFormat, JsonWriter, SerWriter};
+
+use crate::err::XCReportError;
+use crate::html;
+
+/// Writes a report dataframe in one output format.
+pub trait Renderer {
+    /// Name the format is selected by, e.g. `csv`.
+    fn name(&self) -> &'static str;
+
+    /// Extension of the files written in the format, without the dot.
+    fn extension(&self) -> &'static str;
+
+    fn render(&self, df: &mut DataFrame, writer: &mut dyn Write) -> Result<(), XCReportError>;
+}
+
+pub struct CsvRenderer;
+
+impl Renderer for CsvRenderer {
+    fn name(&self) -> &'static str {
+        "csv"
+    }
+
+    fn extension(&self) -> &'static str {
+        "csv"
+    }
+
+    fn render(&self, df: &mut DataFrame, writer: &mut dyn Write) -> Result<(), XCReportError> {
+        CsvWriter::new(writer)
+            .finish(df)
+            .map_err(XCReportError::Polars)
+    }
+}
+
+/// An array with an object per row.
+pub struct JsonRenderer;
+
+impl Renderer for JsonRenderer {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    fn extension(&self) -> &'static str {
+        "json"
+    }
+
+    fn render(&self, df: &mut DataFrame, writer: &mut dyn Write) -> Result<(), XCReportError> {
+        JsonWriter::new(writer)
+            .with_json_format(JsonFormat::Json)
+            .finish(df)
+            .map_err(XCReportError::Polars)
+    }
+}
+
+/// A standalone page with the report as a table, styled like the HTML report.
+pub struct HtmlRenderer;
+
+impl Renderer for HtmlRenderer {
+    fn name(&self) -> &'static str {
+        "html"
+    }
+
+    fn extension(&self) -> &'static str {
+        "html"
+    }
+
+    fn render(&self, df: &mut DataFrame, writer: &mut dyn Write) -> Result<(), XCReportError> {
+        let page = html::page("Coverage report", &html::render_dataframe(df, None, &[]));
+
+        writer.write_all(page.as_bytes())
+            .map_err(XCReportError::FileIO)
+    }
+}
+
+/// A markdown table, e.g. for wikis or pull request descriptions.
+pub struct MarkdownRenderer;
+
+impl Renderer for MarkdownRenderer {
+    fn name(&self) -> &'static str {
+        "markdown"
+    }
+
+    fn extension(&self) -> &'static str {
+        "md"
+    }
+
+    fn render(&self, df: &mut DataFrame, writer: &mut dyn Write) -> Result<(), XCReportError> {
+        let names = df.get_column_names();
+        let mut table = format!("| {} |\n", names.join(" | "));
+        let _ = writeln!(table, "|{}", " --- |".repeat(names.len()));
+
+        for row in 0..df.height() {
+            let cells: Vec<String> = df.get_columns()
+                .iter()
+                .map(|column| match column.get(row) {
+                    Ok(AnyValue::Null) | Err(_) => String::new(),
+                    Ok(value) => value.to_string().trim_matches('"').replace('|', "\\|")
+                })
+                .collect();
+            let _ = writeln!(table, "| {} |", cells.join(" | "));
+        }
+
+        writer.write_all(table.as_bytes())
+            .map_err(XCReportError::FileIO)
+    }
+}
+
+/// The renderers output formats are selected from, by name or by file extension.
+pub struct Registry {
+    renderers: Vec<Box<dyn Renderer>>
+}
+
+impl Default for Registry {
+    /// The csv, json, html and markdown renderers.
+    fn default() -> Self {
+        let mut registry = Registry { renderers: vec![] };
+
+        registry.register(Box::new(CsvRenderer));
+        registry.register(Box::new(JsonRenderer));
+        registry.register(Box::new(HtmlRenderer));
+        registry.register(Box::new(MarkdownRenderer));
+
+        registry
+    }
+}
+
+impl Registry {
+    /// Adds the renderer, replacing the one registered with the same name.
+    pub fn register(&mut self, renderer: Box<dyn Renderer>) {
+        self.renderers.retain(|registered| registered.name() != renderer.name());
+        self.renderers.push(renderer);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Renderer> {
+        self.renderers
+            .iter()
+            .find(|renderer| renderer.name() == name)
+            .map(Box::as_ref)
+    }
+
+    /// The renderer of the file's extension, e.g. json for `summary.json`.
+    pub fn for_path(&self, path: &Path) -> Option<&dyn Renderer> {
+        let extension = path.extension().and_then(OsStr::to_str)?;
+
+        self.renderers
+            .iter()
+            .find(|renderer| renderer.extension().eq_ignore_ascii_case(extension))
+            .map(Box::as_ref)
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.renderers.iter().map(|renderer| renderer.name()).collect()
+    }
+}
+
+/// Writes the dataframe to the file with the renderer.
+pub fn save(df: &mut DataFrame, path: &Path, renderer: &dyn Renderer) -> Result<(), XCReportError> {
+    let mut file = std::fs::File::create(path)
+        .map_err(XCReportError::FileIO)?;
+
+    renderer.render(df, &mut file)
+}
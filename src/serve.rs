@@ -0,0 +1,227 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use polars::frame::DataFrame;
+use polars::prelude::DataType;
+use serde::Serialize;
+
+use crate::cli::RoundingOptions;
+use crate::df;
+use crate::err::XCReportError;
+use crate::events;
+use crate::fs::full_report_path;
+use crate::index;
+use crate::html::escape;
+
+/// The squad summary of the latest run, as served at `/summary.json`.
+#[derive(Serialize)]
+struct Summary {
+    identifier: String,
+    squads: Vec<SquadSummary>
+}
+
+#[derive(Serialize)]
+struct SquadSummary {
+    squad: String,
+    coverage: Option<f64>,
+    covered_lines: Option<i64>,
+    executable_lines: Option<i64>
+}
+
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: String
+}
+
+/// Serves `/summary.json` and `/badge/<squad>.svg` until interrupted, reading the latest run with a full
+/// report on every request so the numbers are always those of the latest run.
+pub fn serve(address: &str, rounding: &RoundingOptions) -> Result<(), XCReportError> {
+    let listener = TcpListener::bind(address)
+        .map_err(XCReportError::Serve)?;
+
+    println!("Serving the coverage of the latest run at http://{}/summary.json", address);
+
+    for stream in listener.incoming() {
+        let result = stream
+            .map_err(XCReportError::Serve)
+            .and_then(|stream| handle(stream, rounding));
+
+        if let Err(e) = result {
+            events::warning(&format!("could not answer a request: {}", e));
+        }
+    }
+
+    Ok(())
+}
+
+fn handle(mut stream: TcpStream, rounding: &RoundingOptions) -> Result<(), XCReportError> {
+    let mut reader = BufReader::new(&stream);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)
+        .map_err(XCReportError::Serve)?;
+
+    // The headers are not needed, but are read so the client isn't reset.
+    let mut header = String::new();
+    while reader.read_line(&mut header).map_err(XCReportError::Serve)? > 2 {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let response = match (parts.next(), parts.next()) {
+        (Some("GET"), Some(path)) => route(path, rounding)?,
+        _ => Response { status: "405 Method Not Allowed", content_type: "text/plain", body: String::from("Method not allowed\n") }
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        response.content_type,
+        response.body.len(),
+        response.body
+    )
+        .map_err(XCReportError::Serve)
+}
+
+fn route(path: &str, rounding: &RoundingOptions) -> Result<Response, XCReportError> {
+    let path = path.split('?').next().unwrap_or_default();
+
+    let not_found = Response { status: "404 Not Found", content_type: "text/plain", body: String::from("Not found\n") };
+
+    if path == "/summary.json" {
+        let Some(summary) = latest_summary(rounding)? else {
+            return Ok(not_found)
+        };
+        let body = serde_json::to_string(&summary)
+            .map_err(XCReportError::Serde)?;
+
+        return Ok(Response { status: "200 OK", content_type: "application/json", body })
+    }
+
+    let Some(squad) = path.strip_prefix("/badge/").and_then(|name| name.strip_suffix(".svg")) else {
+        return Ok(not_found)
+    };
+    let squad = percent_decode(squad);
+
+    let coverage = latest_summary(rounding)?
+        .and_then(|summary| summary.squads.into_iter().find(|summary| summary.squad == squad))
+        .and_then(|summary| summary.coverage);
+
+    let Some(coverage) = coverage else {
+        return Ok(not_found)
+    };
+
+    let body = render_badge(&squad, &format!("{:.*}%", rounding.precision as usize, coverage), badge_color(coverage));
+
+    Ok(Response { status: "200 OK", content_type: "image/svg+xml", body })
+}
+
+/// The squad summary of the most recent run with a full report.
+fn latest_summary(rounding: &RoundingOptions) -> Result<Option<Summary>, XCReportError> {
+    for identifier in index::run_identifiers()?.into_iter().rev() {
+        let full_report_path = full_report_path(&identifier)?;
+        if !full_report_path.is_file() {
+            continue
+        }
+
+        let report = df::process_report(&df::read_full_report(&identifier)?, rounding)?;
+        let squads = squad_summaries(&report)?;
+
+        return Ok(Some(Summary { identifier, squads }))
+    }
+
+    Ok(None)
+}
+
+fn squad_summaries(report: &DataFrame) -> Result<Vec<SquadSummary>, XCReportError> {
+    let squads = report.column("Squad")
+        .and_then(|c| c.str())
+        .map_err(XCReportError::Polars)?;
+    let coverages = report.column("Coverage %")
+        .and_then(|c| c.f64())
+        .map_err(XCReportError::Polars)?;
+    let covered_lines = report.column("Covered Lines")
+        .and_then(|c| c.cast(&DataType::Int64))
+        .map_err(XCReportError::Polars)?;
+    let executable_lines = report.column("Executable Lines")
+        .and_then(|c| c.cast(&DataType::Int64))
+        .map_err(XCReportError::Polars)?;
+
+    let covered_lines = covered_lines.i64().map_err(XCReportError::Polars)?;
+    let executable_lines = executable_lines.i64().map_err(XCReportError::Polars)?;
+
+    let summaries = squads
+        .into_iter()
+        .zip(coverages)
+        .zip(covered_lines.into_iter().zip(executable_lines))
+        .map(|((squad, coverage), (covered_lines, executable_lines))| SquadSummary {
+            squad: squad.unwrap_or("N/A").to_string(),
+            coverage,
+            covered_lines,
+            executable_lines
+        })
+        .collect();
+
+    Ok(summaries)
+}
+
+fn badge_color(coverage: f64) -> &'static str {
+    match coverage {
+        coverage if coverage >= 80.0 => "#4c1",
+        coverage if coverage >= 60.0 => "#dfb317",
+        _ => "#e05d44"
+    }
+}
+
+/// A flat badge with the label on grey and the value on the color, sized by a rough character width.
+fn render_badge(label: &str, value: &str, color: &str) -> String {
+    let label_width = label.chars().count() * 7 + 10;
+    let value_width = value.chars().count() * 7 + 10;
+    let width = label_width + value_width;
+
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"20\" role=\"img\" aria-label=\"{label}: {value}\">\
+<rect width=\"{label_width}\" height=\"20\" fill=\"#555\"/>\
+<rect x=\"{label_width}\" width=\"{value_width}\" height=\"20\" fill=\"{color}\"/>\
+<g fill=\"#fff\" font-family=\"Verdana,Geneva,sans-serif\" font-size=\"11\" text-anchor=\"middle\">\
+<text x=\"{label_x}\" y=\"14\">{label}</text>\
+<text x=\"{value_x}\" y=\"14\">{value}</text>\
+</g></svg>",
+        width = width,
+        label_width = label_width,
+        value_width = value_width,
+        color = color,
+        label = escape(label),
+        value = escape(value),
+        label_x = label_width as f64 / 2.0,
+        value_x = label_width as f64 + value_width as f64 / 2.0
+    )
+}
+
+/// Decodes the `%XX` escapes of a URL path segment, e.g. `Squad%20One`.
+fn percent_decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let escaped = bytes.get(index + 1..index + 3)
+            .filter(|_| bytes[index] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            },
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
@@ -0,0 +1,169 @@
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::path::Path;
+use serde::Serialize;
+
+use crate::err::XCReportError;
+use crate::events;
+use crate::history;
+use crate::metadata;
+use crate::xcresult;
+
+/// Test suites a CI job runs, with the xcodebuild arguments selecting them.
+#[derive(Serialize)]
+pub struct Shard {
+    pub index: usize,
+    pub estimated_seconds: f64,
+    pub suites: Vec<String>,
+    pub arguments: Vec<String>
+}
+
+/// Average duration of every `Bundle/Suite` over the most recent runs whose result bundles are still on disk.
+pub fn suite_durations(history_runs: usize) -> Result<Vec<(String, f64)>, XCReportError> {
+    let mut samples: BTreeMap<String, Vec<f64>> = BTreeMap::new();
+    let mut runs_read = 0;
+
+    for identifier in history::run_identifiers()?.iter().rev() {
+        if runs_read == history_runs {
+            break
+        }
+
+        let Some(metadata) = metadata::load_metadata(identifier) else {
+            continue
+        };
+
+        let mut run_durations: Vec<(String, f64)> = vec![];
+        for xcresult_file in metadata.xcresult_files.iter().filter(|path| path.exists()) {
+            match xcresult::parse_test_results(xcresult_file) {
+                Ok(test_results) => run_durations.extend(test_results.suite_durations()),
+                Err(e) => events::warning(&format!("skipping the tests of {:?}: {}", xcresult_file, e))
+            }
+        }
+
+        if run_durations.is_empty() {
+            continue
+        }
+
+        for (suite, seconds) in run_durations {
+            samples.entry(suite).or_default().push(seconds);
+        }
+        runs_read += 1;
+    }
+
+    if samples.is_empty() {
+        return Err(XCReportError::NoTestHistory { runs: history_runs })
+    }
+
+    let durations = samples
+        .into_iter()
+        .map(|(suite, seconds)| {
+            let average = seconds.iter().sum::<f64>() / seconds.len() as f64;
+            (suite, average)
+        })
+        .collect();
+
+    Ok(durations)
+}
+
+/// Assigns the longest suites first, each to the shard with the least work so far.
+///
+/// The first shard skips the suites of the other shards instead of listing its own, so it also
+/// runs the suites added since the runs the durations come from.
+pub fn plan_shards(mut durations: Vec<(String, f64)>, shard_count: usize) -> Vec<Shard> {
+    durations.sort_by(|(a_suite, a), (b_suite, b)| b.total_cmp(a).then_with(|| a_suite.cmp(b_suite)));
+
+    let mut shards: Vec<Shard> = (1..=shard_count)
+        .map(|index| Shard { index, estimated_seconds: 0.0, suites: vec![], arguments: vec![] })
+        .collect();
+
+    for (suite, seconds) in durations {
+        // min_by picks the first of equally loaded shards, keeping the plan stable.
+        let shard = shards
+            .iter_mut()
+            .min_by(|a, b| a.estimated_seconds.total_cmp(&b.estimated_seconds))
+            .expect("at least one shard");
+        shard.estimated_seconds += seconds;
+        shard.suites.push(suite);
+    }
+
+    shards.iter_mut().for_each(|shard| shard.suites.sort());
+
+    let other_suites: Vec<String> = shards
+        .iter()
+        .skip(1)
+        .flat_map(|shard| &shard.suites)
+        .cloned()
+        .collect();
+
+    for shard in &mut shards {
+        shard.arguments = if shard.index == 1 {
+            other_suites.iter().map(|suite| format!("-skip-testing:{}", suite)).collect()
+        } else {
+            shard.suites.iter().map(|suite| format!("-only-testing:{}", suite)).collect()
+        };
+    }
+
+    shards
+}
+
+/// Test suites picked to fit a time budget.
+pub struct BudgetSelection {
+    pub suites: Vec<String>,
+    pub estimated_seconds: f64
+}
+
+/// Picks the suites testing the changed files first, judged by their names, then the other suites
+/// shortest first, as long as their average durations fit the budget.
+pub fn select_within_budget(mut durations: Vec<(String, f64)>, changed_files: &[String], budget_seconds: f64) -> BudgetSelection {
+    durations.sort_by(|(a_suite, a), (b_suite, b)| {
+        relevance(b_suite, changed_files)
+            .cmp(&relevance(a_suite, changed_files))
+            .then_with(|| a.total_cmp(b))
+            .then_with(|| a_suite.cmp(b_suite))
+    });
+
+    let mut selection = BudgetSelection { suites: vec![], estimated_seconds: 0.0 };
+
+    for (suite, seconds) in durations {
+        if selection.estimated_seconds + seconds <= budget_seconds {
+            selection.estimated_seconds += seconds;
+            selection.suites.push(suite);
+        }
+    }
+
+    selection.suites.sort();
+
+    selection
+}
+
+/// How closely a `Bundle/Suite` relates to the changed files: 3 when the suite's own file changed,
+/// 2 when the file it is named after changed, e.g. `CartViewModel.swift` for `CartViewModelTests`,
+/// 1 when a file in a directory named after its bundle changed, e.g. `Payments/` for `PaymentsTests`.
+fn relevance(suite: &str, changed_files: &[String]) -> u8 {
+    let (bundle, name) = suite.split_once('/').unwrap_or(("", suite));
+    let subject = strip_test_suffix(name);
+    let module = strip_test_suffix(bundle);
+
+    let mut relevance = 0;
+    for changed_file in changed_files {
+        let path = Path::new(changed_file);
+        let stem = path.file_stem().and_then(OsStr::to_str).unwrap_or_default();
+
+        if stem == name {
+            return 3
+        } else if !subject.is_empty() && stem == subject {
+            relevance = relevance.max(2);
+        } else if !module.is_empty() && path.components().any(|component| component.as_os_str() == module) {
+            relevance = relevance.max(1);
+        }
+    }
+
+    relevance
+}
+
+fn strip_test_suffix(name: &str) -> &str {
+    ["Tests", "Test", "Spec"]
+        .iter()
+        .find_map(|suffix| name.strip_suffix(suffix))
+        .unwrap_or(name)
+}
@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+use serde::Deserialize;
+
+use crate::err::{SimctlError, XCReportError};
+use crate::events;
+use crate::simulator;
+use crate::tools;
+
+/// Destinations suggested for a destination matching no simulator.
+const MAX_SUGGESTIONS: usize = 3;
+
+#[derive(Deserialize)]
+struct DeviceList {
+    /// Devices per runtime identifier, e.g. `com.apple.CoreSimulator.SimRuntime.iOS-17-2`.
+    devices: HashMap<String, Vec<Device>>
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Device {
+    name: String,
+    udid: String,
+    state: String,
+    #[serde(default)]
+    is_available: bool
+}
+
+/// An installed simulator device.
+pub struct Simulator {
+    pub name: String,
+    pub udid: String,
+    /// Platform of the runtime, e.g. `iOS` or `watchOS`.
+    pub platform: String,
+    pub os_version: String,
+    pub booted: bool
+}
+
+impl Simulator {
+    /// The xcodebuild destination of the device, e.g. `platform=iOS Simulator,name=iPhone 15,OS=17.2`.
+    pub fn destination(&self) -> String {
+        format!("platform={} Simulator,name={},OS={}", self.platform, simulator::escape_destination_value(&self.name), self.os_version)
+    }
+}
+
+/// Shuts the simulator down when dropped, if xcreport booted it.
+pub struct BootGuard {
+    udid: Option<String>
+}
+
+impl Drop for BootGuard {
+    fn drop(&mut self) {
+        let Some(udid) = &self.udid else {
+            return
+        };
+
+        println!("Shutting down simulator {}", udid);
+        let _ = tools::xcrun()
+            .args(["simctl", "shutdown", udid])
+            .output();
+    }
+}
+
+/// Finds the available device with the name, on the OS version or on the newest runtime for
+/// `latest` or no version.
+pub fn find_device(name: &str, os_version: Option<&str>) -> Result<Simulator, XCReportError> {
+    let os_version = os_version.filter(|version| *version != "latest");
+
+    let mut candidates: Vec<Simulator> = list_devices()?
+        .into_iter()
+        .filter(|simulator| simulator.name == name)
+        .filter(|simulator| match os_version {
+            Some(version) => is_version(&simulator.os_version, version),
+            None => true
+        })
+        .collect();
+    candidates.sort_by_key(|simulator| simulator::version_key(&simulator.os_version));
+
+    candidates.pop().ok_or_else(|| XCReportError::Simctl(SimctlError::DeviceNotFound {
+        name: name.to_string(),
+        os_version: os_version.map(str::to_string)
+    }))
+}
+
+/// Finds the device an xcodebuild destination names by `id`, or by `name` and `OS`.
+pub fn find_destination_device(destination: &str) -> Result<Simulator, XCReportError> {
+    let fields = simulator::destination_fields(destination);
+
+    if let Some(udid) = simulator::field(&fields, "id") {
+        return list_devices()?
+            .into_iter()
+            .find(|simulator| simulator.udid == udid)
+            .ok_or_else(|| XCReportError::Simctl(SimctlError::DeviceNotFound { name: udid.to_string(), os_version: None }))
+    }
+
+    match simulator::field(&fields, "name") {
+        Some(name) => find_device(name, simulator::field(&fields, "OS")),
+        None => Err(XCReportError::Simctl(SimctlError::NoDevice { destination: destination.to_string() }))
+    }
+}
+
+/// Checks that an available simulator matches the `id`, or the `name` and `OS`, of a simulator
+/// destination, suggesting the closest ones otherwise. Destinations of devices, Macs or generic
+/// platforms are left to xcodebuild.
+pub fn validate_destination(destination: &str) -> Result<(), XCReportError> {
+    let fields = simulator::destination_fields(destination);
+
+    let Some(platform) = simulator::field(&fields, "platform").and_then(|p| p.strip_suffix(" Simulator")) else {
+        return Ok(())
+    };
+    let udid = simulator::field(&fields, "id");
+    let name = simulator::field(&fields, "name");
+    if udid.is_none() && name.is_none() {
+        return Ok(())
+    }
+
+    let simulators = match list_devices() {
+        Ok(simulators) => simulators,
+        Err(e) => {
+            events::warning(&format!("could not list the simulators, skipping the destination check: {}", e));
+            return Ok(())
+        }
+    };
+    let simulators: Vec<Simulator> = simulators
+        .into_iter()
+        .filter(|simulator| simulator.platform == platform)
+        .collect();
+
+    let os_version = simulator::field(&fields, "OS").filter(|version| *version != "latest");
+    let matches = |simulator: &Simulator| match (udid, name, os_version) {
+        (Some(udid), _, _) => simulator.udid == udid,
+        (None, Some(name), Some(version)) => simulator.name == name && is_version(&simulator.os_version, version),
+        (None, Some(name), None) => simulator.name == name,
+        (None, None, _) => true
+    };
+    if simulators.iter().any(matches) {
+        return Ok(())
+    }
+
+    Err(XCReportError::Simctl(SimctlError::UnknownDestination {
+        destination: destination.to_string(),
+        suggestions: name.map(|name| closest_destinations(&simulators, name)).unwrap_or_default()
+    }))
+}
+
+/// Destinations of the simulators with the closest names, newest runtimes first.
+fn closest_destinations(simulators: &[Simulator], name: &str) -> Vec<String> {
+    let max_distance = (name.chars().count() / 3).max(3);
+
+    let mut candidates: Vec<(usize, &Simulator)> = simulators
+        .iter()
+        .map(|simulator| (edit_distance(&simulator.name.to_lowercase(), &name.to_lowercase()), simulator))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect();
+    candidates.sort_by(|(distance, a), (other_distance, b)| {
+        distance
+            .cmp(other_distance)
+            .then_with(|| simulator::version_key(&b.os_version).cmp(&simulator::version_key(&a.os_version)))
+    });
+
+    let mut destinations: Vec<String> = vec![];
+    for (_, simulator) in candidates {
+        let destination = simulator.destination();
+        if !destinations.contains(&destination) {
+            destinations.push(destination);
+        }
+    }
+    destinations.truncate(MAX_SUGGESTIONS);
+
+    destinations
+}
+
+/// Levenshtein distance between two names.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// Whether a runtime version is the requested one, `17` matching `17.2` as xcodebuild does.
+fn is_version(version: &str, requested: &str) -> bool {
+    version == requested || version.starts_with(&format!("{}.", requested))
+}
+
+/// Boots the simulator and waits until it finished booting. Simulators that are already booted
+/// are left running after the run.
+pub fn boot(simulator: &Simulator) -> Result<BootGuard, XCReportError> {
+    if simulator.booted {
+        return Ok(BootGuard { udid: None })
+    }
+
+    println!("Booting simulator {} ({})", simulator.name, simulator.os_version);
+    simctl(&["boot", &simulator.udid])?;
+    let guard = BootGuard { udid: Some(simulator.udid.clone()) };
+    simctl(&["bootstatus", &simulator.udid])?;
+
+    Ok(guard)
+}
+
+/// Every available simulator device.
+pub fn list_devices() -> Result<Vec<Simulator>, XCReportError> {
+    let output = simctl(&["list", "devices", "available", "--json"])?;
+    let device_list: DeviceList = serde_json::from_slice(&output)
+        .map_err(XCReportError::Serde)?;
+
+    let simulators = device_list.devices
+        .into_iter()
+        .filter_map(|(runtime, devices)| Some((runtime_version(&runtime)?, devices)))
+        .flat_map(|((platform, os_version), devices)| {
+            devices
+                .into_iter()
+                .filter(|device| device.is_available)
+                .map(move |device| Simulator {
+                    name: device.name,
+                    udid: device.udid,
+                    platform: platform.clone(),
+                    os_version: os_version.clone(),
+                    booted: device.state == "Booted"
+                })
+        })
+        .collect();
+
+    Ok(simulators)
+}
+
+/// Platform and version of a runtime identifier, e.g. `iOS` and `17.2` for
+/// `com.apple.CoreSimulator.SimRuntime.iOS-17-2`.
+fn runtime_version(runtime: &str) -> Option<(String, String)> {
+    let (platform, version) = runtime.rsplit('.').next()?.split_once('-')?;
+    // visionOS runtimes kept their xrOS identifier.
+    let platform = if platform == "xrOS" { "visionOS" } else { platform };
+
+    Some((platform.to_string(), version.replace('-', ".")))
+}
+
+fn simctl(args: &[&str]) -> Result<Vec<u8>, XCReportError> {
+    let output = tools::xcrun()
+        .arg("simctl")
+        .args(args)
+        .output()
+        .map_err(|e| XCReportError::Simctl(SimctlError::Launch(e)))?;
+
+    if !output.status.success() {
+        return Err(XCReportError::Simctl(SimctlError::Failed {
+            command: args.join(" "),
+            desc: String::from_utf8_lossy(&output.stderr).trim().to_string()
+        }))
+    }
+
+    Ok(output.stdout)
+}
@@ -0,0 +1,207 @@
+use std::path::Path;
+use std::process::Command;
+use serde::Deserialize;
+
+use crate::err::{PreflightError, XCReportError};
+use crate::events;
+use crate::tools;
+
+/// Rosetta installs its runtime here, x86_64-only simulator runtimes can't boot without it.
+const ROSETTA_PATH: &str = "/Library/Apple/usr/share/rosetta/rosetta";
+
+#[derive(Deserialize)]
+struct RuntimeList {
+    runtimes: Vec<Runtime>
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Runtime {
+    name: String,
+    platform: Option<String>,
+    version: String,
+    #[serde(default)]
+    is_available: bool,
+    /// Only reported by Xcode 14 and later.
+    supported_architectures: Option<Vec<String>>
+}
+
+/// Checks that the simulator runtime of the destination can run on this Mac.
+///
+/// On Apple Silicon, runtimes shipped without arm64 slices only run under Rosetta: the destination
+/// is pinned to `arch=x86_64` when Rosetta is installed, otherwise the run fails before building.
+/// Returns the destination to pass to xcodebuild.
+pub fn preflight(destination: &str) -> Result<String, XCReportError> {
+    let fields = destination_fields(destination);
+
+    let Some(platform) = field(&fields, "platform").and_then(|p| p.strip_suffix(" Simulator")) else {
+        return Ok(destination.to_string())
+    };
+
+    if field(&fields, "arch").is_some() || !is_apple_silicon() {
+        return Ok(destination.to_string())
+    }
+
+    let Some(runtime_list) = list_runtimes() else {
+        events::warning("could not list the simulator runtimes, skipping the architecture preflight");
+        return Ok(destination.to_string())
+    };
+
+    let os_version = field(&fields, "OS").filter(|version| *version != "latest");
+    let Some(runtime) = find_runtime(&runtime_list.runtimes, platform, os_version) else {
+        return Ok(destination.to_string())
+    };
+
+    let Some(architectures) = &runtime.supported_architectures else {
+        return Ok(destination.to_string())
+    };
+
+    if architectures.iter().any(|arch| arch == "arm64") {
+        return Ok(destination.to_string())
+    }
+
+    if !architectures.iter().any(|arch| arch == "x86_64") {
+        return Err(XCReportError::Preflight(PreflightError::UnsupportedArchitecture {
+            runtime: runtime.name.clone(),
+            architectures: architectures.clone()
+        }))
+    }
+
+    if !Path::new(ROSETTA_PATH).exists() {
+        return Err(XCReportError::Preflight(PreflightError::RosettaNotInstalled { runtime: runtime.name.clone() }))
+    }
+
+    println!("{} has no arm64 support, running the simulator under Rosetta", runtime.name);
+
+    Ok(format!("{},arch=x86_64", destination))
+}
+
+/// Splits an xcodebuild destination like `platform=iOS Simulator,name=iPhone 15,OS=17.2` into its fields.
+/// A comma or backslash escaped by a backslash belongs to the value, e.g. `name=Tester\, iPhone`.
+pub fn destination_fields(destination: &str) -> Vec<(String, String)> {
+    let mut fields: Vec<String> = vec![];
+    let mut current = String::new();
+    let mut chars = destination.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => current.extend(chars.next()),
+            ',' => fields.push(std::mem::take(&mut current)),
+            c => current.push(c)
+        }
+    }
+    fields.push(current);
+
+    fields
+        .iter()
+        .filter_map(|field| field.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+pub fn field<'a>(fields: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    fields
+        .iter()
+        .find(|(field_key, _)| field_key == key)
+        .map(|(_, value)| value.as_str())
+}
+
+/// Escapes a value for a destination, so commas in simulator names don't split it.
+pub fn escape_destination_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace(',', "\\,")
+}
+
+/// Picks the runtime of the platform with the requested version, or the newest one when none is requested.
+fn find_runtime<'a>(runtimes: &'a [Runtime], platform: &str, os_version: Option<&str>) -> Option<&'a Runtime> {
+    let mut candidates: Vec<&Runtime> = runtimes
+        .iter()
+        .filter(|runtime| runtime.is_available)
+        .filter(|runtime| {
+            runtime.platform
+                .as_deref()
+                .map_or_else(|| runtime.name.starts_with(platform), |p| p == platform)
+        })
+        .collect();
+
+    candidates.sort_by_key(|runtime| version_key(&runtime.version));
+
+    match os_version {
+        Some(os_version) => candidates
+            .into_iter()
+            .rev()
+            .find(|runtime| runtime.version == os_version || runtime.version.starts_with(&format!("{}.", os_version))),
+        None => candidates.pop()
+    }
+}
+
+pub fn version_key(version: &str) -> Vec<u32> {
+    version
+        .split('.')
+        .map(|part| part.parse().unwrap_or_default())
+        .collect()
+}
+
+fn list_runtimes() -> Option<RuntimeList> {
+    let output = tools::xcrun()
+        .args(["simctl", "list", "runtimes", "--json"])
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None
+    }
+
+    serde_json::from_slice(&output.stdout).ok()
+}
+
+/// Whether the Mac has an Apple Silicon CPU, also when xcreport itself runs under Rosetta.
+fn is_apple_silicon() -> bool {
+    Command::new("sysctl")
+        .args(["-n", "hw.optional.arm64"])
+        .output()
+        .map(|output| output.status.success() && String::from_utf8_lossy(&output.stdout).trim() == "1")
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn destination_fields_split_on_commas() {
+        assert_eq!(
+            destination_fields("platform=iOS Simulator,name=iPhone 15 Pro,OS=17.2"),
+            fields(&[("platform", "iOS Simulator"), ("name", "iPhone 15 Pro"), ("OS", "17.2")])
+        );
+    }
+
+    #[test]
+    fn destination_fields_keep_escaped_commas_and_backslashes() {
+        assert_eq!(
+            destination_fields(r"platform=iOS Simulator,name=Tester\, iPhone\\Pad,OS=latest"),
+            fields(&[("platform", "iOS Simulator"), ("name", r"Tester, iPhone\Pad"), ("OS", "latest")])
+        );
+    }
+
+    #[test]
+    fn destination_fields_trim_and_skip_fields_without_a_value() {
+        assert_eq!(
+            destination_fields(" platform = macOS , arch=arm64,,id"),
+            fields(&[("platform", "macOS"), ("arch", "arm64")])
+        );
+    }
+
+    #[test]
+    fn escaped_values_read_back_unchanged() {
+        for name in ["iPhone 15", "Tester, iPhone", r"CI\Runner, 2", r"trailing\"] {
+            let destination = format!("platform=iOS Simulator,name={},OS=17.2", escape_destination_value(name));
+
+            assert_eq!(field(&destination_fields(&destination), "name"), Some(name));
+            assert_eq!(field(&destination_fields(&destination), "OS"), Some("17.2"));
+        }
+    }
+}
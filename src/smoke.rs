@@ -0,0 +1,108 @@
+use std::path::Path;
+use std::process::Command;
+
+use crate::df;
+use crate::doctor;
+use crate::err::XCReportError;
+use crate::fs::smoke_path;
+use crate::simctl;
+use crate::simulator;
+use crate::tools;
+
+/// The sample package and the squads file the smoke test runs, by their paths in the smoke directory.
+const SAMPLE_FILES: [(&str, &str); 5] = [
+    ("Greeting/Package.swift", include_str!("../smoke/Greeting/Package.swift")),
+    ("Greeting/Sources/Greeting/Greeter.swift", include_str!("../smoke/Greeting/Sources/Greeting/Greeter.swift")),
+    ("Greeting/Tests/GreetingTests/GreeterTests.swift", include_str!("../smoke/Greeting/Tests/GreetingTests/GreeterTests.swift")),
+    ("Smoke.xcworkspace/contents.xcworkspacedata", include_str!("../smoke/Smoke.xcworkspace/contents.xcworkspacedata")),
+    ("squads.yml", include_str!("../smoke/squads.yml"))
+];
+
+/// Squad of the sample sources in the squads file.
+const SAMPLE_SQUAD: &str = "Smoke";
+
+/// Checks the prerequisites, then runs this xcreport binary on the sample package and checks that its
+/// report has the sample squad partly covered, as one of its two methods is left untested.
+pub fn smoke() -> Result<(), XCReportError> {
+    println!("xcreport {}\n", env!("CARGO_PKG_VERSION"));
+    doctor::doctor()?;
+
+    let smoke_dir = smoke_path()?;
+    write_sample(&smoke_dir)?;
+
+    let destination = newest_ios_simulator()?;
+    let report_path = smoke_dir.join("report.csv");
+
+    println!("\nRunning the sample package on {}\n", destination);
+
+    let status = Command::new(std::env::current_exe().map_err(XCReportError::FileIO)?)
+        .arg("run")
+        .arg("--input-file").arg(smoke_dir.join("squads.yml"))
+        .arg("--project-path").arg(&smoke_dir)
+        .arg("--workspace").arg("Smoke.xcworkspace")
+        .arg("--scheme").arg("Greeting")
+        .arg("--destination").arg(&destination)
+        .arg("--output-file").arg(&report_path)
+        .arg("--xcrun-path").arg(tools::xcrun_path())
+        .arg("--xcodebuild-path").arg(tools::xcodebuild_path())
+        .status()
+        .map_err(XCReportError::FileIO)?;
+
+    if !status.success() {
+        return Err(XCReportError::Smoke { problem: format!("the run of the sample package exited with {}", status) })
+    }
+
+    let report = df::read_report_csv(&report_path)?;
+    let squads = report.column("Squad")
+        .and_then(|c| c.str())
+        .map_err(XCReportError::Polars)?;
+    let coverages = report.column("Coverage %")
+        .and_then(|c| c.f64())
+        .map_err(XCReportError::Polars)?;
+
+    let coverage = squads
+        .into_iter()
+        .zip(coverages)
+        .find(|(squad, _)| *squad == Some(SAMPLE_SQUAD))
+        .and_then(|(_, coverage)| coverage);
+
+    match coverage {
+        Some(coverage) if coverage > 0.0 && coverage < 100.0 => {
+            println!("\nThe sample squad is {}% covered, xcreport works on this machine", coverage);
+            Ok(())
+        },
+        Some(coverage) => Err(XCReportError::Smoke { problem: format!("expected the sample squad to be partly covered, got {}%", coverage) }),
+        None => Err(XCReportError::Smoke { problem: format!("the report has no coverage for the {} squad", SAMPLE_SQUAD) })
+    }
+}
+
+/// Writes the sample files over the ones of an earlier smoke test, leaving out its report.
+fn write_sample(smoke_dir: &Path) -> Result<(), XCReportError> {
+    if smoke_dir.exists() {
+        std::fs::remove_dir_all(smoke_dir)
+            .map_err(XCReportError::FileIO)?;
+    }
+
+    for (path, contents) in SAMPLE_FILES {
+        let path = smoke_dir.join(path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(XCReportError::FileIO)?;
+        }
+
+        std::fs::write(path, contents)
+            .map_err(XCReportError::FileIO)?;
+    }
+
+    Ok(())
+}
+
+/// The destination of the iOS simulator with the newest runtime.
+fn newest_ios_simulator() -> Result<String, XCReportError> {
+    simctl::list_devices()?
+        .into_iter()
+        .filter(|simulator| simulator.platform == "iOS")
+        .max_by_key(|simulator| simulator::version_key(&simulator.os_version))
+        .map(|simulator| simulator.destination())
+        .ok_or_else(|| XCReportError::Smoke { problem: String::from("no iOS simulator is available") })
+}
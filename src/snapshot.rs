@@ -0,0 +1,107 @@
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+
+use crate::cli::{Aggregate, GroupBy, ReportOptions, RoundingOptions};
+use crate::data::{FileFilter, SquadData, TargetFilter, XCodeBuildReport};
+use crate::err::{VerifyError, XCReportError};
+use crate::exclude::ExclusionRule;
+use crate::fs::inputs_path;
+
+/// Everything the reports of a run are computed from, saved as `inputs.json` so `verify` can
+/// compute them again.
+#[derive(Serialize, Deserialize)]
+pub struct ReportInputs {
+    /// Merged coverage of the result bundles, with repo-relative paths when `--repo-root` is set.
+    pub coverage: XCodeBuildReport,
+    pub squads: Vec<SquadData>,
+    pub exclusion_rules: Vec<ExclusionRule>,
+    /// Executed tests per squad, with `--test-metrics`.
+    pub squad_tests: Option<Vec<(String, u64)>>,
+    /// Squad and domain pairs, with `--tags-file`.
+    pub squad_domains: Option<Vec<(String, String)>>,
+    /// Squad and tribe pairs, with `--group-by tribe`. Missing from the inputs of runs made before tribes existed.
+    #[serde(default)]
+    pub squad_tribes: Option<Vec<(String, String)>>,
+    /// Coverage % of every squad in the previous runs, with `--volatility-runs`.
+    pub squad_history: Option<Vec<(String, Vec<f64>)>>,
+    /// Paths of the changed files, with `--changed-files`.
+    #[serde(default)]
+    pub changed_files: Option<Vec<String>>,
+    pub settings: ReportSettings
+}
+
+/// The options the report files depend on.
+#[derive(Serialize, Deserialize)]
+pub struct ReportSettings {
+    pub rounding: RoundingOptions,
+    pub aggregate: Aggregate,
+    pub file_threshold: f64,
+    pub top_uncovered: Option<u32>,
+    #[serde(default = "default_unowned_name")]
+    pub unowned_name: String,
+    /// Missing from the inputs of runs made before target filters existed.
+    #[serde(default)]
+    pub target_filter: TargetFilter,
+    #[serde(default)]
+    pub targets_report: bool,
+    /// Directory levels of the path prefix report, with `--group-by path-prefix`.
+    #[serde(default)]
+    pub path_prefix_depth: Option<u32>,
+    #[serde(default)]
+    pub hide_fully_covered: bool,
+    #[serde(default)]
+    pub file_filter: FileFilter,
+    #[serde(default)]
+    pub ignore_path_case: bool
+}
+
+/// The name of the unowned squad before it could be changed.
+fn default_unowned_name() -> String {
+    String::from("N/A")
+}
+
+impl From<&ReportOptions> for ReportSettings {
+    fn from(report_options: &ReportOptions) -> Self {
+        ReportSettings {
+            rounding: report_options.rounding,
+            aggregate: report_options.aggregate,
+            file_threshold: report_options.file_threshold,
+            top_uncovered: report_options.top_uncovered,
+            unowned_name: report_options.unowned_name.clone(),
+            target_filter: TargetFilter {
+                include: report_options.include_target.clone(),
+                exclude: report_options.exclude_target.clone()
+            },
+            targets_report: report_options.targets_report,
+            path_prefix_depth: (report_options.group_by == GroupBy::PathPrefix).then_some(report_options.depth),
+            hide_fully_covered: report_options.hide_fully_covered,
+            file_filter: FileFilter {
+                min_executable_lines: report_options.min_executable_lines,
+                include_paths: report_options.include_path.clone(),
+                exclude_paths: report_options.exclude_path.clone()
+            },
+            ignore_path_case: report_options.ignore_path_case
+        }
+    }
+}
+
+pub fn save_inputs(inputs: &ReportInputs, identifier: &String) -> Result<PathBuf, XCReportError> {
+    let inputs_path = inputs_path(identifier)?;
+
+    let json = serde_json::to_string(inputs)
+        .map_err(XCReportError::Serde)?;
+
+    std::fs::write(&inputs_path, json)
+        .map_err(XCReportError::FileIO)?;
+
+    Ok(inputs_path)
+}
+
+pub fn load_inputs(identifier: &String) -> Result<ReportInputs, XCReportError> {
+    let Ok(json) = std::fs::read_to_string(inputs_path(identifier)?) else {
+        return Err(XCReportError::Verify(VerifyError::MissingInputs { identifier: identifier.clone() }))
+    };
+
+    serde_json::from_str(&json)
+        .map_err(XCReportError::Serde)
+}
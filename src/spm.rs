@@ -0,0 +1,137 @@
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+use serde::Deserialize;
+
+use crate::data::{Target, TargetFile, XCodeBuildReport};
+use crate::err::{CommandExecutionError, XCReportError};
+use crate::tools;
+
+/// Files llvm-cov leaves out of the coverage: the checked out dependencies and the tests.
+const IGNORED_FILES_REGEX: &str = r"(\.build|Tests)/";
+
+#[derive(Deserialize)]
+struct PackageDescription {
+    name: String
+}
+
+/// The output of `llvm-cov export -summary-only`.
+#[derive(Deserialize)]
+struct CoverageExport {
+    data: Vec<ExportData>
+}
+
+#[derive(Deserialize)]
+struct ExportData {
+    files: Vec<ExportFile>
+}
+
+#[derive(Deserialize)]
+struct ExportFile {
+    filename: String,
+    summary: ExportSummary
+}
+
+#[derive(Deserialize)]
+struct ExportSummary {
+    lines: LineSummary
+}
+
+#[derive(Deserialize)]
+struct LineSummary {
+    count: usize,
+    covered: usize
+}
+
+/// Runs the tests of the package with code coverage, streaming their output.
+pub fn run_tests(package_path: &Path) -> Result<(), XCReportError> {
+    let status = Command::new("swift")
+        .args(["test", "--enable-code-coverage", "--package-path"])
+        .arg(package_path)
+        .status()
+        .map_err(|e| XCReportError::CommandExecution(CommandExecutionError::Swift(e)))?;
+
+    if !status.success() {
+        return Err(XCReportError::CommandExecution(CommandExecutionError::NonZeroExit {
+            desc: format!("swift test exited with {}", status)
+        }))
+    }
+
+    Ok(())
+}
+
+/// Exports the coverage profile of the last `swift test --enable-code-coverage` with llvm-cov, one
+/// target per `Sources/<Target>` directory.
+pub fn export_coverage(package_path: &Path) -> Result<XCodeBuildReport, XCReportError> {
+    let description: PackageDescription = serde_json::from_slice(&swift(&["package", "describe", "--type", "json"], package_path)?)
+        .map_err(XCReportError::Serde)?;
+    let bin_path = PathBuf::from(String::from_utf8_lossy(&swift(&["build", "--show-bin-path"], package_path)?).trim());
+
+    let profdata_path = bin_path.join("codecov").join("default.profdata");
+    let test_bundle_name = format!("{}PackageTests", description.name);
+    let mut test_binary = bin_path.join(format!("{}.xctest", test_bundle_name));
+    // On macOS the tests are a bundle with the binary inside.
+    if test_binary.is_dir() {
+        test_binary = test_binary.join("Contents").join("MacOS").join(&test_bundle_name);
+    }
+
+    let output = tools::xcrun()
+        .args(["llvm-cov", "export", "-summary-only", "-instr-profile"])
+        .arg(&profdata_path)
+        .arg(format!("-ignore-filename-regex={}", IGNORED_FILES_REGEX))
+        .arg(&test_binary)
+        .output()
+        .map_err(|e| XCReportError::CommandExecution(CommandExecutionError::XCRun(e)))?;
+
+    if !output.status.success() {
+        let desc = String::from_utf8_lossy(&output.stderr).to_string();
+        return Err(XCReportError::CommandExecution(CommandExecutionError::NonZeroExit { desc }))
+    }
+
+    let export: CoverageExport = serde_json::from_slice(&output.stdout)
+        .map_err(XCReportError::Serde)?;
+
+    let mut targets: BTreeMap<String, Vec<TargetFile>> = BTreeMap::new();
+    for file in export.data.into_iter().flat_map(|data| data.files) {
+        let target = source_target(&file.filename).unwrap_or_else(|| description.name.clone());
+        targets
+            .entry(target)
+            .or_default()
+            .push(TargetFile::new(file.filename, file.summary.lines.covered, file.summary.lines.count));
+    }
+
+    let mut report = XCodeBuildReport::default();
+    for (name, files) in targets {
+        report.push_target(Target::new(name, files));
+    }
+
+    Ok(report)
+}
+
+/// The target of a source file, by the directory following `Sources` in its path.
+fn source_target(path: &str) -> Option<String> {
+    let mut components = Path::new(path).components();
+
+    components.find(|component| *component == Component::Normal("Sources".as_ref()))?;
+
+    match components.next()? {
+        Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+        _ => None
+    }
+}
+
+fn swift(args: &[&str], package_path: &Path) -> Result<Vec<u8>, XCReportError> {
+    let output = Command::new("swift")
+        .args(args)
+        .arg("--package-path")
+        .arg(package_path)
+        .output()
+        .map_err(|e| XCReportError::CommandExecution(CommandExecutionError::Swift(e)))?;
+
+    if !output.status.success() {
+        let desc = String::from_utf8_lossy(&output.stderr).to_string();
+        return Err(XCReportError::CommandExecution(CommandExecutionError::NonZeroExit { desc }))
+    }
+
+    Ok(output.stdout)
+}
@@ -0,0 +1,116 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Write};
+use std::os::fd::FromRawFd;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use polars::frame::DataFrame;
+use polars::prelude::{JsonFormat, JsonWriter, SerWriter};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::cli::ReportOptions;
+use crate::df;
+use crate::err::XCReportError;
+use crate::fs::{
+    artifact_manifest_path, backstage_path, build_log_path, configuration_report_path, domain_report_path, exclusions_path, full_report_path, html_report_path,
+    metadata_path, patch_report_path, path_prefix_report_path, targets_report_path, top_uncovered_path, tribe_report_path,
+    xlsx_report_path
+};
+
+/// The stdout of the process, kept aside for the JSON summary once `redirect_stdout` moved stdout to stderr.
+static SUMMARY_STDOUT: OnceLock<Mutex<File>> = OnceLock::new();
+
+/// The result of a run printed with `--print-summary json`.
+#[derive(Serialize)]
+struct JsonSummary {
+    identifier: String,
+    /// Coverage % of all lines of the report, `None` without executable lines.
+    overall_coverage: Option<f64>,
+    /// The rows of the summary report, keyed by its column names.
+    squads: Value,
+    /// Paths of the reports the run wrote, by their kind.
+    artifacts: BTreeMap<&'static str, PathBuf>,
+    test_reports: Vec<PathBuf>
+}
+
+/// Points stdout at stderr for the rest of the process, the output of xcodebuild and the other tools
+/// included, keeping the original stdout for the JSON summary alone.
+pub fn redirect_stdout() -> Result<(), XCReportError> {
+    io::stdout().flush()
+        .map_err(XCReportError::FileIO)?;
+
+    let stdout = unsafe { libc::dup(libc::STDOUT_FILENO) };
+    if stdout < 0 || unsafe { libc::dup2(libc::STDERR_FILENO, libc::STDOUT_FILENO) } < 0 {
+        return Err(XCReportError::FileIO(io::Error::last_os_error()))
+    }
+
+    let _ = SUMMARY_STDOUT.set(Mutex::new(unsafe { File::from_raw_fd(stdout) }));
+
+    Ok(())
+}
+
+/// Prints the result of the run as a single line of JSON, for scripts, e.g. `| jq .overall_coverage`.
+pub fn print_json(
+    report_df: &DataFrame,
+    report_path: &Path,
+    identifier: &String,
+    test_report_paths: &[PathBuf],
+    report_options: &ReportOptions
+) -> Result<(), XCReportError> {
+    let mut squads: Vec<u8> = vec![];
+    JsonWriter::new(&mut squads)
+        .with_json_format(JsonFormat::Json)
+        .finish(&mut report_df.clone())
+        .map_err(XCReportError::Polars)?;
+
+    // The summary has no line totals with `--aggregate files`, the full report always has.
+    let overall_coverage = df::total_coverage(&df::read_full_report(identifier)?)?
+        .map(|coverage| df::round_value(coverage, &report_options.rounding));
+
+    let mut artifacts = BTreeMap::from([
+        ("report", report_path.to_path_buf()),
+        ("full_report", full_report_path(identifier)?),
+        ("metadata", metadata_path(identifier)?),
+        ("manifest", artifact_manifest_path(identifier)?)
+    ]);
+    let optional_reports = [
+        ("exclusions", exclusions_path(identifier)?),
+        ("top_uncovered", top_uncovered_path(identifier)?),
+        ("domain_report", domain_report_path(identifier)?),
+        ("targets_report", targets_report_path(identifier)?),
+        ("tribe_report", tribe_report_path(identifier)?),
+        ("path_prefix_report", path_prefix_report_path(identifier)?),
+        ("patch_report", patch_report_path(identifier)?),
+        ("html", html_report_path(identifier)?.join("index.html")),
+        ("xlsx", xlsx_report_path(identifier)?),
+        ("backstage", backstage_path(identifier)?),
+        ("configurations", configuration_report_path(identifier)?),
+        ("build_log", build_log_path(identifier, false)?),
+        ("compressed_build_log", build_log_path(identifier, true)?)
+    ];
+    artifacts.extend(optional_reports.into_iter().filter(|(_, path)| path.exists()));
+    if let Some(artifacts_dir) = &report_options.artifacts_dir {
+        artifacts.insert("artifacts_dir", artifacts_dir.clone());
+    }
+
+    let summary = JsonSummary {
+        identifier: identifier.clone(),
+        overall_coverage,
+        squads: serde_json::from_slice(&squads).map_err(XCReportError::Serde)?,
+        artifacts,
+        test_reports: test_report_paths.iter().filter(|path| path.exists()).cloned().collect()
+    };
+    let json = serde_json::to_string(&summary)
+        .map_err(XCReportError::Serde)?;
+
+    let written = match SUMMARY_STDOUT.get() {
+        Some(stdout) => {
+            let mut stdout = stdout.lock().unwrap_or_else(|e| e.into_inner());
+            writeln!(stdout, "{}", json).and_then(|_| stdout.flush())
+        },
+        None => writeln!(io::stdout(), "{}", json)
+    };
+
+    written.map_err(XCReportError::FileIO)
+}
@@ -0,0 +1,205 @@
+use std::io::IsTerminal;
+use crossterm::style::{Color, Stylize};
+use polars::frame::DataFrame;
+use polars::prelude::{DataType, Series};
+
+use crate::err::XCReportError;
+
+/// Coverage % from which the console table shows a squad in green, and yellow below it.
+const GOOD_COVERAGE: f64 = 80.0;
+/// Coverage % from which the console table shows a squad in yellow, and red below it.
+const FAIR_COVERAGE: f64 = 60.0;
+
+/// Prints the summary as an aligned table, coloring the coverage of every squad when stdout is a terminal
+/// and NO_COLOR is not set.
+pub fn print_summary(summary: &DataFrame, precision: usize) -> Result<(), XCReportError> {
+    let colored = colored_output();
+
+    let headers: Vec<String> = summary.get_column_names().iter().map(|name| name.to_string()).collect();
+    let columns = summary.get_columns();
+    let cells = columns
+        .iter()
+        .map(|column| column_cells(column, precision))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let widths: Vec<usize> = headers
+        .iter()
+        .zip(&cells)
+        .map(|(header, column_cells)| {
+            column_cells
+                .iter()
+                .map(|cell| cell.chars().count())
+                .chain([header.chars().count()])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+
+    let border = |left: &str, fill: &str, middle: &str, right: &str| {
+        let segments: Vec<String> = widths.iter().map(|width| fill.repeat(width + 2)).collect();
+        format!("{}{}{}", left, segments.join(middle), right)
+    };
+
+    println!();
+    println!("{}", border("┌", "─", "┬", "┐"));
+    let header_cells: Vec<String> = headers
+        .iter()
+        .zip(&widths)
+        .map(|(header, width)| format!(" {:<width$} ", header, width = width))
+        .collect();
+    println!("│{}│", header_cells.join("│"));
+    println!("{}", border("╞", "═", "╪", "╡"));
+
+    for row in 0..summary.height() {
+        let row_cells: Vec<String> = columns
+            .iter()
+            .zip(&cells)
+            .zip(&widths)
+            .map(|((column, column_cells), width)| {
+                let cell = &column_cells[row];
+                let padded = if column.dtype().is_numeric() {
+                    format!(" {:>width$} ", cell, width = width)
+                } else {
+                    format!(" {:<width$} ", cell, width = width)
+                };
+
+                match coverage_at(column, row).filter(|_| colored) {
+                    Some(coverage) if coverage >= GOOD_COVERAGE => padded.green().to_string(),
+                    Some(coverage) if coverage >= FAIR_COVERAGE => padded.yellow().to_string(),
+                    Some(_) => padded.red().to_string(),
+                    None => padded
+                }
+            })
+            .collect();
+        println!("│{}│", row_cells.join("│"));
+    }
+
+    println!("{}", border("└", "─", "┴", "┘"));
+
+    Ok(())
+}
+
+/// Prints the coverage change of every squad between two reports, with a green arrow when it rose and
+/// a red one when it fell, followed by the files that appeared in or disappeared from the head report.
+pub fn print_delta(squad_delta: &DataFrame, file_delta: &DataFrame, precision: usize) -> Result<(), XCReportError> {
+    let colored = colored_output();
+    let coverage = |value: Option<f64>| value.map(|value| format!("{:.*}%", precision, value)).unwrap_or_else(|| String::from("—"));
+
+    let squads = string_values(squad_delta, "Squad")?;
+    let base_coverages = float_values(squad_delta, "Base Coverage %")?;
+    let head_coverages = float_values(squad_delta, "Head Coverage %")?;
+    let deltas = float_values(squad_delta, "Delta")?;
+
+    let squad_width = squads.iter().map(|squad| squad.chars().count()).max().unwrap_or_default();
+    let base_cells: Vec<String> = base_coverages.iter().map(|value| coverage(*value)).collect();
+    let base_width = base_cells.iter().map(|cell| cell.chars().count()).max().unwrap_or_default();
+
+    println!("\nSquad coverage changes:");
+    for (((squad, base_cell), head_coverage), delta) in squads.iter().zip(&base_cells).zip(&head_coverages).zip(&deltas) {
+        let (change, color) = match (delta, head_coverage) {
+            (Some(delta), _) if *delta > 0.0 => (format!("▲ +{:.*}", precision, delta), Some(Color::Green)),
+            (Some(delta), _) if *delta < 0.0 => (format!("▼ {:.*}", precision, delta), Some(Color::Red)),
+            (Some(_), _) => (String::from("="), None),
+            (None, Some(_)) => (String::from("new"), Some(Color::Green)),
+            (None, None) => (String::from("removed"), Some(Color::Red))
+        };
+        let change = match color.filter(|_| colored) {
+            Some(color) => change.with(color).to_string(),
+            None => change
+        };
+
+        println!(
+            "  {:<squad_width$}  {:>base_width$} → {}  {}",
+            squad, base_cell, coverage(*head_coverage), change,
+            squad_width = squad_width,
+            base_width = base_width
+        );
+    }
+
+    let filepaths = string_values(file_delta, "Filepath")?;
+    let file_squads = string_values(file_delta, "Squad")?;
+    let file_base_coverages = float_values(file_delta, "Base Coverage %")?;
+    let file_head_coverages = float_values(file_delta, "Head Coverage %")?;
+
+    let mut new_files: Vec<String> = vec![];
+    let mut removed_files: Vec<String> = vec![];
+    let mut changed_files = 0;
+    for (((filepath, squad), base_coverage), head_coverage) in filepaths.iter().zip(&file_squads).zip(&file_base_coverages).zip(&file_head_coverages) {
+        match (base_coverage, head_coverage) {
+            (None, Some(_)) => new_files.push(format!("+ {} ({}) {}", filepath, squad, coverage(*head_coverage))),
+            (Some(_), None) => removed_files.push(format!("- {} ({}) {}", filepath, squad, coverage(*base_coverage))),
+            _ => changed_files += 1
+        }
+    }
+
+    for (title, files, color) in [("New files", &new_files, Color::Green), ("Removed files", &removed_files, Color::Red)] {
+        if files.is_empty() {
+            continue
+        }
+
+        println!("\n{}:", title);
+        for file in files {
+            if colored {
+                println!("  {}", file.as_str().with(color));
+            } else {
+                println!("  {}", file);
+            }
+        }
+    }
+
+    if changed_files > 0 {
+        println!("\n{} file(s) changed coverage", changed_files);
+    }
+
+    Ok(())
+}
+
+/// Whether the console output is colored: stdout is a terminal and NO_COLOR is not set.
+fn colored_output() -> bool {
+    std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+}
+
+fn string_values(df: &DataFrame, column: &str) -> Result<Vec<String>, XCReportError> {
+    let values = df.column(column)
+        .and_then(|c| c.str())
+        .map_err(XCReportError::Polars)?;
+
+    Ok(values.into_iter().map(|value| value.unwrap_or_default().to_string()).collect())
+}
+
+fn float_values(df: &DataFrame, column: &str) -> Result<Vec<Option<f64>>, XCReportError> {
+    let values = df.column(column)
+        .and_then(|c| c.cast(&DataType::Float64))
+        .map_err(XCReportError::Polars)?;
+    let values = values.f64()
+        .map_err(XCReportError::Polars)?;
+
+    Ok(values.into_iter().collect())
+}
+
+/// The values of the column as printed, floats rounded to the precision and missing values left blank.
+fn column_cells(column: &Series, precision: usize) -> Result<Vec<String>, XCReportError> {
+    if let Ok(values) = column.f64() {
+        let cells = values
+            .into_iter()
+            .map(|value| value.map(|value| format!("{:.*}", precision, value)).unwrap_or_default())
+            .collect();
+        return Ok(cells)
+    }
+
+    let values = column.cast(&DataType::String)
+        .map_err(XCReportError::Polars)?;
+    let values = values.str()
+        .map_err(XCReportError::Polars)?;
+
+    Ok(values.into_iter().map(|value| value.unwrap_or_default().to_string()).collect())
+}
+
+/// The coverage % of the row, when the column is the one colored.
+fn coverage_at(column: &Series, row: usize) -> Option<f64> {
+    if column.name() != "Coverage %" {
+        return None
+    }
+
+    column.f64().ok()?.get(row)
+}
@@ -0,0 +1,48 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+
+static TOOLS: OnceLock<Tools> = OnceLock::new();
+
+/// The Xcode binaries xcreport runs, replaceable for custom toolchains or wrappers.
+struct Tools {
+    xcrun: PathBuf,
+    xcodebuild: PathBuf,
+    /// Arguments added to the `xccov view --report` invocation.
+    xccov_args: Vec<String>
+}
+
+pub fn init(xcrun: Option<&Path>, xcodebuild: Option<&Path>, xccov_args: &[String]) {
+    let _ = TOOLS.set(Tools {
+        xcrun: xcrun.map_or_else(|| PathBuf::from("xcrun"), Path::to_path_buf),
+        xcodebuild: xcodebuild.map_or_else(|| PathBuf::from("xcodebuild"), Path::to_path_buf),
+        xccov_args: xccov_args.to_vec()
+    });
+}
+
+/// The `--xcrun-path`, `xcrun` from PATH by default.
+pub fn xcrun_path() -> &'static Path {
+    TOOLS.get().map_or(Path::new("xcrun"), |tools| tools.xcrun.as_path())
+}
+
+/// The `--xcodebuild-path`, `xcodebuild` from PATH by default.
+pub fn xcodebuild_path() -> &'static Path {
+    TOOLS.get().map_or(Path::new("xcodebuild"), |tools| tools.xcodebuild.as_path())
+}
+
+pub fn xccov_args() -> &'static [String] {
+    TOOLS.get().map_or(&[][..], |tools| tools.xccov_args.as_slice())
+}
+
+pub fn xcrun() -> Command {
+    Command::new(xcrun_path())
+}
+
+pub fn xcodebuild() -> Command {
+    Command::new(xcodebuild_path())
+}
+
+/// `plutil`, which ships with macOS rather than with Xcode, so it is run from PATH.
+pub fn plutil() -> Command {
+    Command::new("plutil")
+}
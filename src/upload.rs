@@ -0,0 +1,184 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use md5::{Digest, Md5};
+use serde_json::{json, Map, Value};
+
+use crate::cli::UploadService;
+use crate::data::LineCoverage;
+use crate::err::{UploadError, XCReportError};
+use crate::events;
+
+const CODECOV_URL: &str = "https://codecov.io";
+const COVERALLS_URL: &str = "https://coveralls.io";
+
+/// Line coverage of a source file, as uploaded to a coverage service.
+pub struct SourceCoverage {
+    /// Repo-relative path shown by the service.
+    pub name: String,
+    /// Build machine path the source is read from.
+    pub source_path: PathBuf,
+    pub lines: Vec<LineCoverage>
+}
+
+/// Commit, branch and credentials of an upload.
+pub struct Upload<'a> {
+    pub service: UploadService,
+    pub service_url: Option<&'a str>,
+    pub token: &'a str,
+    pub commit: &'a str,
+    pub branch: Option<&'a str>
+}
+
+impl Upload<'_> {
+    /// Uploads the coverage and returns the link to it the service responded with.
+    pub fn send(&self, files: &[SourceCoverage]) -> Result<String, XCReportError> {
+        if files.is_empty() {
+            return Err(XCReportError::Upload(UploadError::NoCoverage))
+        }
+
+        match self.service {
+            UploadService::Codecov => self.send_to_codecov(files),
+            UploadService::Coveralls => self.send_to_coveralls(files)
+        }
+    }
+
+    /// Reserves an upload with the v4 API, then puts the report to the storage URL it returns.
+    fn send_to_codecov(&self, files: &[SourceCoverage]) -> Result<String, XCReportError> {
+        let base_url = self.service_url.unwrap_or(CODECOV_URL).trim_end_matches('/');
+
+        let mut request = ureq::post(&format!("{}/upload/v4", base_url))
+            .set("Accept", "text/plain")
+            .query("package", "xcreport")
+            .query("token", self.token)
+            .query("commit", self.commit);
+
+        if let Some(branch) = self.branch {
+            request = request.query("branch", branch);
+        }
+
+        let reservation = request
+            .call()
+            .map_err(|e| XCReportError::Upload(UploadError::Request(Box::new(e))))?
+            .into_string()
+            .map_err(XCReportError::FileIO)?;
+
+        // The first line links to the commit, the second one is where the report goes.
+        let mut reservation_lines = reservation.lines();
+        let (Some(report_url), Some(storage_url)) = (reservation_lines.next(), reservation_lines.next()) else {
+            return Err(XCReportError::Upload(UploadError::UnexpectedResponse { body: reservation }))
+        };
+
+        let report = format!("# path=coverage.json\n{}\n<<<<<< EOF\n", codecov_json(files));
+
+        ureq::put(storage_url.trim())
+            .set("Content-Type", "text/plain")
+            .send_string(&report)
+            .map_err(|e| XCReportError::Upload(UploadError::Request(Box::new(e))))?;
+
+        Ok(report_url.trim().to_string())
+    }
+
+    /// Posts the job as the `json_file` part of a multipart form, the only upload the jobs API accepts.
+    fn send_to_coveralls(&self, files: &[SourceCoverage]) -> Result<String, XCReportError> {
+        let base_url = self.service_url.unwrap_or(COVERALLS_URL).trim_end_matches('/');
+        let job = coveralls_json(self, files)?;
+
+        let boundary = format!("xcreport-{:x}", Md5::digest(job.as_bytes()));
+        let body = format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"json_file\"; filename=\"coveralls.json\"\r\nContent-Type: application/json\r\n\r\n{job}\r\n--{boundary}--\r\n"
+        );
+
+        let response: Value = ureq::post(&format!("{}/api/v1/jobs", base_url))
+            .set("Content-Type", &format!("multipart/form-data; boundary={}", boundary))
+            .send_string(&body)
+            .map_err(|e| XCReportError::Upload(UploadError::Request(Box::new(e))))?
+            .into_json()
+            .map_err(XCReportError::FileIO)?;
+
+        match response["url"].as_str() {
+            Some(url) => Ok(url.to_string()),
+            None => Err(XCReportError::Upload(UploadError::UnexpectedResponse { body: response.to_string() }))
+        }
+    }
+}
+
+/// Codecov's JSON format, mapping every executable line number to its hit count.
+fn codecov_json(files: &[SourceCoverage]) -> Value {
+    let coverage: Map<String, Value> = files
+        .iter()
+        .map(|file| {
+            let lines: Map<String, Value> = file.lines
+                .iter()
+                .filter_map(|line| line.hits().map(|hits| (line.line().to_string(), json!(hits))))
+                .collect();
+
+            (file.name.clone(), Value::Object(lines))
+        })
+        .collect();
+
+    json!({ "coverage": coverage })
+}
+
+/// Coveralls' job format, with a hit count or null for every line of the source and the MD5 of the source.
+/// Files whose source can't be read are skipped, Coveralls rejects them without a digest.
+fn coveralls_json(upload: &Upload, files: &[SourceCoverage]) -> Result<String, XCReportError> {
+    let mut source_files: Vec<Value> = vec![];
+
+    for file in files {
+        let source = match std::fs::read(&file.source_path) {
+            Ok(source) => source,
+            Err(e) => {
+                events::warning(&format!("skipping {:?}, its source could not be read: {}", file.name, e));
+                continue
+            }
+        };
+
+        let line_count = file.lines.iter().map(LineCoverage::line).max().unwrap_or_default();
+        let mut coverage: Vec<Option<usize>> = vec![None; line_count];
+        for line in &file.lines {
+            if let Some(hits) = line.line().checked_sub(1).and_then(|index| coverage.get_mut(index)) {
+                *hits = line.hits();
+            }
+        }
+
+        source_files.push(json!({
+            "name": file.name,
+            "source_digest": format!("{:x}", Md5::digest(&source)),
+            "coverage": coverage
+        }));
+    }
+
+    let job = json!({
+        "repo_token": upload.token,
+        "service_name": "xcreport",
+        "git": {
+            "head": { "id": upload.commit },
+            "branch": upload.branch
+        },
+        "source_files": source_files
+    });
+
+    serde_json::to_string(&job).map_err(XCReportError::Serde)
+}
+
+/// Merges the archives of several bundles into the coverage of every file, relative to `repo_root` when given.
+pub fn source_coverage(archives: Vec<HashMap<String, Vec<LineCoverage>>>, repo_root: Option<&Path>) -> Vec<SourceCoverage> {
+    let mut bundles_by_path: BTreeMap<String, Vec<Vec<LineCoverage>>> = BTreeMap::new();
+
+    for (path, lines) in archives.into_iter().flatten() {
+        bundles_by_path.entry(path).or_default().push(lines);
+    }
+
+    bundles_by_path
+        .into_iter()
+        .map(|(path, bundles)| {
+            let source_path = PathBuf::from(&path);
+            let name = repo_root
+                .and_then(|repo_root| source_path.strip_prefix(repo_root).ok())
+                .map(|relative_path| relative_path.to_string_lossy().into_owned())
+                .unwrap_or(path);
+
+            SourceCoverage { name, source_path, lines: LineCoverage::merge(bundles) }
+        })
+        .collect()
+}
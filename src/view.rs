@@ -0,0 +1,387 @@
+use std::io::{stdout, Stdout};
+use std::path::Path;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::ExecutableCommand;
+use polars::prelude::*;
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, Borders, Paragraph, Row, Table, TableState};
+
+use crate::df;
+use crate::err::XCReportError;
+
+/// A file of the full report.
+struct FileRow {
+    path: String,
+    squad: String,
+    covered_lines: u64,
+    executable_lines: u64
+}
+
+impl FileRow {
+    fn coverage(&self) -> f64 {
+        percentage(self.covered_lines, self.executable_lines)
+    }
+
+    fn uncovered_lines(&self) -> u64 {
+        self.executable_lines - self.covered_lines.min(self.executable_lines)
+    }
+}
+
+/// The files of a squad summed up.
+struct SquadRow {
+    name: String,
+    files: usize,
+    covered_lines: u64,
+    executable_lines: u64
+}
+
+impl SquadRow {
+    fn coverage(&self) -> f64 {
+        percentage(self.covered_lines, self.executable_lines)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum SortKey {
+    Name,
+    Coverage,
+    UncoveredLines
+}
+
+impl SortKey {
+    fn next(self) -> SortKey {
+        match self {
+            SortKey::Name => SortKey::Coverage,
+            SortKey::Coverage => SortKey::UncoveredLines,
+            SortKey::UncoveredLines => SortKey::Name
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            SortKey::Name => "name",
+            SortKey::Coverage => "coverage",
+            SortKey::UncoveredLines => "uncovered lines"
+        }
+    }
+}
+
+enum Screen {
+    Squads,
+    /// The files of the squad at the index.
+    Files(usize)
+}
+
+struct App {
+    files: Vec<FileRow>,
+    squads: Vec<SquadRow>,
+    screen: Screen,
+    sort_key: SortKey,
+    descending: bool,
+    search: String,
+    searching: bool,
+    squad_state: TableState,
+    file_state: TableState
+}
+
+impl App {
+    fn new(files: Vec<FileRow>) -> App {
+        let mut squads: Vec<SquadRow> = vec![];
+        for file in &files {
+            let squad = match squads.iter_mut().find(|squad| squad.name == file.squad) {
+                Some(squad) => squad,
+                None => {
+                    squads.push(SquadRow { name: file.squad.clone(), files: 0, covered_lines: 0, executable_lines: 0 });
+                    squads.last_mut().expect("squad was just pushed")
+                }
+            };
+            squad.files += 1;
+            squad.covered_lines += file.covered_lines;
+            squad.executable_lines += file.executable_lines;
+        }
+
+        let mut app = App {
+            files,
+            squads,
+            screen: Screen::Squads,
+            sort_key: SortKey::Name,
+            descending: false,
+            search: String::new(),
+            searching: false,
+            squad_state: TableState::default(),
+            file_state: TableState::default()
+        };
+        app.sort_squads();
+        app.squad_state.select((!app.squads.is_empty()).then_some(0));
+        app
+    }
+
+    fn sort_squads(&mut self) {
+        let (sort_key, descending) = (self.sort_key, self.descending);
+        self.squads.sort_by(|a, b| {
+            let ordering = match sort_key {
+                SortKey::Name => a.name.cmp(&b.name),
+                SortKey::Coverage => a.coverage().total_cmp(&b.coverage()),
+                SortKey::UncoveredLines => (a.executable_lines - a.covered_lines.min(a.executable_lines))
+                    .cmp(&(b.executable_lines - b.covered_lines.min(b.executable_lines)))
+            };
+            if descending { ordering.reverse() } else { ordering }
+        });
+    }
+
+    /// The files of the open squad matching the search, in the chosen order.
+    fn visible_files(&self) -> Vec<&FileRow> {
+        let Screen::Files(squad_index) = self.screen else {
+            return vec![]
+        };
+        let squad = &self.squads[squad_index].name;
+        let search = self.search.to_lowercase();
+
+        let mut files: Vec<&FileRow> = self.files
+            .iter()
+            .filter(|file| file.squad == *squad)
+            .filter(|file| search.is_empty() || file.path.to_lowercase().contains(&search))
+            .collect();
+
+        files.sort_by(|a, b| {
+            let ordering = match self.sort_key {
+                SortKey::Name => a.path.cmp(&b.path),
+                SortKey::Coverage => a.coverage().total_cmp(&b.coverage()),
+                SortKey::UncoveredLines => a.uncovered_lines().cmp(&b.uncovered_lines())
+            };
+            if self.descending { ordering.reverse() } else { ordering }
+        });
+
+        files
+    }
+
+    /// Handles a key press, returning false when the browser should close.
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            return false
+        }
+
+        if self.searching {
+            match key.code {
+                KeyCode::Enter | KeyCode::Esc => self.searching = false,
+                KeyCode::Backspace => {
+                    self.search.pop();
+                },
+                KeyCode::Char(c) => self.search.push(c),
+                _ => {}
+            }
+            self.file_state.select((!self.visible_files().is_empty()).then_some(0));
+            return true
+        }
+
+        match key.code {
+            KeyCode::Char('q') => return false,
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::PageDown => self.move_selection(20),
+            KeyCode::PageUp => self.move_selection(-20),
+            KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => {
+                if let (Screen::Squads, Some(index)) = (&self.screen, self.squad_state.selected()) {
+                    self.screen = Screen::Files(index);
+                    self.search.clear();
+                    self.file_state.select((!self.visible_files().is_empty()).then_some(0));
+                }
+            },
+            KeyCode::Esc | KeyCode::Backspace | KeyCode::Left | KeyCode::Char('h') => {
+                if let Screen::Files(index) = self.screen {
+                    self.screen = Screen::Squads;
+                    self.squad_state.select(Some(index));
+                }
+            },
+            KeyCode::Char('s') => self.resort(self.sort_key.next(), self.descending),
+            KeyCode::Char('r') => self.resort(self.sort_key, !self.descending),
+            KeyCode::Char('/') if matches!(self.screen, Screen::Files(_)) => self.searching = true,
+            _ => {}
+        }
+
+        true
+    }
+
+    /// Sorts again, keeping the open squad selected.
+    fn resort(&mut self, sort_key: SortKey, descending: bool) {
+        let open_squad = match self.screen {
+            Screen::Files(index) => Some(index),
+            Screen::Squads => self.squad_state.selected()
+        }
+        .map(|index| self.squads[index].name.clone());
+
+        self.sort_key = sort_key;
+        self.descending = descending;
+        self.sort_squads();
+
+        let index = open_squad.and_then(|name| self.squads.iter().position(|squad| squad.name == name));
+        match self.screen {
+            Screen::Files(_) => {
+                self.screen = Screen::Files(index.unwrap_or_default());
+                self.file_state.select((!self.visible_files().is_empty()).then_some(0));
+            },
+            Screen::Squads => self.squad_state.select(index)
+        }
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let (state, len) = match self.screen {
+            Screen::Squads => (&mut self.squad_state, self.squads.len()),
+            Screen::Files(_) => {
+                let len = self.visible_files().len();
+                (&mut self.file_state, len)
+            }
+        };
+
+        if len == 0 {
+            return
+        }
+
+        let selected = state.selected().unwrap_or_default() as isize;
+        state.select(Some((selected + delta).clamp(0, len as isize - 1) as usize));
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [table_area, help_area] = *Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(1)])
+            .split(frame.size())
+        else {
+            return
+        };
+
+        let order = format!("sorted by {}{}", self.sort_key.label(), if self.descending { ", descending" } else { "" });
+        let highlight = Style::default().add_modifier(Modifier::REVERSED);
+
+        match self.screen {
+            Screen::Squads => {
+                let rows: Vec<Row> = self.squads
+                    .iter()
+                    .map(|squad| Row::new(vec![
+                        squad.name.clone(),
+                        squad.files.to_string(),
+                        squad.covered_lines.to_string(),
+                        squad.executable_lines.to_string(),
+                        format!("{:.2}%", squad.coverage())
+                    ]).style(coverage_style(squad.coverage())))
+                    .collect();
+                let widths = [Constraint::Min(20), Constraint::Length(8), Constraint::Length(14), Constraint::Length(17), Constraint::Length(11)];
+                let table = Table::new(rows, widths)
+                    .header(Row::new(vec!["Squad", "Files", "Covered Lines", "Executable Lines", "Coverage %"]).bold())
+                    .block(Block::default().borders(Borders::ALL).title(format!(" Squads, {} ", order)))
+                    .highlight_style(highlight);
+                frame.render_stateful_widget(table, table_area, &mut self.squad_state);
+            },
+            Screen::Files(index) => {
+                let title = match self.search.is_empty() {
+                    true => format!(" {}, {} ", self.squads[index].name, order),
+                    false => format!(" {}, {}, matching {:?} ", self.squads[index].name, order, self.search)
+                };
+                let rows: Vec<Row> = self.visible_files()
+                    .into_iter()
+                    .map(|file| Row::new(vec![
+                        file.path.clone(),
+                        file.covered_lines.to_string(),
+                        file.executable_lines.to_string(),
+                        format!("{:.2}%", file.coverage())
+                    ]).style(coverage_style(file.coverage())))
+                    .collect();
+                let widths = [Constraint::Min(30), Constraint::Length(14), Constraint::Length(17), Constraint::Length(11)];
+                let table = Table::new(rows, widths)
+                    .header(Row::new(vec!["Filepath", "Covered Lines", "Executable Lines", "Coverage %"]).bold())
+                    .block(Block::default().borders(Borders::ALL).title(title))
+                    .highlight_style(highlight);
+                frame.render_stateful_widget(table, table_area, &mut self.file_state);
+            }
+        }
+
+        let help = match (&self.screen, self.searching) {
+            (_, true) => format!("Search: {}▏ enter/esc done", self.search),
+            (Screen::Squads, false) => String::from("↑↓ move  enter open  s sort  r reverse  q quit"),
+            (Screen::Files(_), false) => String::from("↑↓ move  esc back  / search  s sort  r reverse  q quit")
+        };
+        frame.render_widget(Paragraph::new(help).dim(), help_area);
+    }
+}
+
+/// Puts the terminal back the way it was, also when browsing fails.
+struct TerminalGuard;
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = terminal::disable_raw_mode();
+        let _ = stdout().execute(LeaveAlternateScreen);
+    }
+}
+
+/// Browses the squads of a full report and their files until the user quits.
+pub fn browse(full_report_path: &Path) -> Result<(), XCReportError> {
+    let files = read_files(&df::read_report_csv(full_report_path)?)?;
+    let mut app = App::new(files);
+
+    terminal::enable_raw_mode().map_err(XCReportError::FileIO)?;
+    let _guard = TerminalGuard;
+    stdout().execute(EnterAlternateScreen).map_err(XCReportError::FileIO)?;
+    let mut terminal: Terminal<CrosstermBackend<Stdout>> = Terminal::new(CrosstermBackend::new(stdout()))
+        .map_err(XCReportError::FileIO)?;
+
+    loop {
+        terminal.draw(|frame| app.draw(frame)).map_err(XCReportError::FileIO)?;
+
+        if let Event::Key(key) = event::read().map_err(XCReportError::FileIO)? {
+            if key.kind == KeyEventKind::Press && !app.handle_key(key) {
+                return Ok(())
+            }
+        }
+    }
+}
+
+fn read_files(full_report: &DataFrame) -> Result<Vec<FileRow>, XCReportError> {
+    let paths = full_report.column("Filepath")
+        .and_then(|c| c.str())
+        .map_err(XCReportError::Polars)?;
+    let squads = full_report.column("Squad")
+        .and_then(|c| c.str())
+        .map_err(XCReportError::Polars)?;
+    let covered_lines = full_report.column("Covered Lines")
+        .and_then(|c| c.cast(&DataType::UInt64))
+        .map_err(XCReportError::Polars)?;
+    let executable_lines = full_report.column("Executable Lines")
+        .and_then(|c| c.cast(&DataType::UInt64))
+        .map_err(XCReportError::Polars)?;
+
+    let covered_lines = covered_lines.u64().map_err(XCReportError::Polars)?;
+    let executable_lines = executable_lines.u64().map_err(XCReportError::Polars)?;
+
+    let files = paths
+        .into_iter()
+        .zip(squads)
+        .zip(covered_lines.into_iter().zip(executable_lines))
+        .map(|((path, squad), (covered_lines, executable_lines))| FileRow {
+            path: path.unwrap_or_default().to_string(),
+            squad: squad.unwrap_or_default().to_string(),
+            covered_lines: covered_lines.unwrap_or_default(),
+            executable_lines: executable_lines.unwrap_or_default()
+        })
+        .collect();
+
+    Ok(files)
+}
+
+fn percentage(covered_lines: u64, executable_lines: u64) -> f64 {
+    if executable_lines == 0 {
+        return 0.0
+    }
+
+    covered_lines as f64 * 100.0 / executable_lines as f64
+}
+
+/// Red below 50%, yellow below 80%, green otherwise.
+fn coverage_style(coverage: f64) -> Style {
+    match coverage {
+        coverage if coverage < 50.0 => Style::default().fg(Color::Red),
+        coverage if coverage < 80.0 => Style::default().fg(Color::Yellow),
+        _ => Style::default().fg(Color::Green)
+    }
+}
@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::time::{Duration, Instant};
+use notify::{RecursiveMode, Watcher};
+
+use crate::err::XCReportError;
+use crate::scrub::scrub;
+
+/// Watches `dir` for new `.xcresult` bundles and calls `on_bundle` for each one once
+/// no more changes were seen inside it for `settle_time`.
+pub fn watch_bundles<F>(dir: &Path, settle_time: Duration, mut on_bundle: F) -> Result<(), XCReportError>
+where
+    F: FnMut(&Path) -> Result<(), XCReportError>
+{
+    let (sender, receiver) = channel();
+    let mut watcher = notify::recommended_watcher(sender)
+        .map_err(XCReportError::Watch)?;
+
+    watcher
+        .watch(dir, RecursiveMode::Recursive)
+        .map_err(XCReportError::Watch)?;
+
+    println!("Watching {:?} for new .xcresult bundles", dir);
+
+    let mut pending: HashMap<PathBuf, Instant> = HashMap::new();
+
+    loop {
+        match receiver.recv_timeout(settle_time) {
+            Ok(Ok(event)) => {
+                for bundle in event.paths.iter().filter_map(|path| bundle_root(path)) {
+                    pending.insert(bundle, Instant::now());
+                }
+            },
+            Ok(Err(e)) => eprintln!("Watch error: {}", e),
+            Err(RecvTimeoutError::Timeout) => {},
+            Err(RecvTimeoutError::Disconnected) => return Ok(())
+        }
+
+        let settled: Vec<PathBuf> = pending
+            .iter()
+            .filter(|(_, last_change)| last_change.elapsed() >= settle_time)
+            .map(|(bundle, _)| bundle.clone())
+            .collect();
+
+        for bundle in settled {
+            pending.remove(&bundle);
+
+            if !bundle.try_exists().unwrap_or_default() {
+                continue
+            }
+
+            println!("\nGenerating report for {:?}", bundle);
+            if let Err(e) = on_bundle(&bundle) {
+                eprintln!("Failed to generate report for {:?}: {}", bundle, scrub(&e.to_string()));
+            }
+        }
+    }
+}
+
+fn bundle_root(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .find(|ancestor| ancestor.extension() == Some(OsStr::new("xcresult")))
+        .map(Path::to_path_buf)
+}
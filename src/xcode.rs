@@ -0,0 +1,89 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::err::{XCodeError, XCReportError};
+use crate::simulator;
+
+/// Where Xcode installations are looked for when Spotlight finds none.
+const APPLICATIONS_DIR: &str = "/Applications";
+
+/// An installed Xcode.
+struct Installation {
+    version: String,
+    /// The Developer directory inside the app, as DEVELOPER_DIR expects it.
+    developer_dir: PathBuf
+}
+
+/// Resolves `--xcode`, an Xcode app or Developer directory path, or a version like `15` or `15.2`,
+/// to the Developer directory to set DEVELOPER_DIR to. A version picks the newest matching install.
+pub fn resolve_developer_dir(xcode: &str) -> Result<PathBuf, XCReportError> {
+    let path = Path::new(xcode);
+    if path.is_dir() {
+        let developer_dir = path.join("Contents/Developer");
+        return Ok(if developer_dir.is_dir() { developer_dir } else { path.to_path_buf() })
+    }
+
+    let mut installations = installations();
+    installations.sort_by_key(|installation| simulator::version_key(&installation.version));
+
+    let matching = installations
+        .iter()
+        .rev()
+        .find(|installation| installation.version == xcode || installation.version.starts_with(&format!("{}.", xcode)));
+
+    match matching {
+        Some(installation) => Ok(installation.developer_dir.clone()),
+        None => Err(XCReportError::XCode(XCodeError::NotFound {
+            xcode: xcode.to_string(),
+            installed: installations.into_iter().map(|installation| installation.version).collect()
+        }))
+    }
+}
+
+/// Every Xcode Spotlight knows of, or in /Applications when Spotlight is disabled, with its version.
+fn installations() -> Vec<Installation> {
+    let mut apps: Vec<PathBuf> = command_output("mdfind", &["kMDItemCFBundleIdentifier == 'com.apple.dt.Xcode'"])
+        .map(|output| output.lines().map(PathBuf::from).collect())
+        .unwrap_or_default();
+
+    if apps.is_empty() {
+        apps = std::fs::read_dir(APPLICATIONS_DIR)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.path())
+                    .filter(|path| {
+                        path.file_name()
+                            .and_then(|name| name.to_str())
+                            .is_some_and(|name| name.starts_with("Xcode") && name.ends_with(".app"))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+    }
+
+    apps
+        .into_iter()
+        .filter_map(|app| {
+            let info_plist = app.join("Contents/Info.plist");
+            let version = command_output("plutil", &["-extract", "CFBundleShortVersionString", "raw", "-o", "-", info_plist.to_str()?])?;
+            Some(Installation { version, developer_dir: app.join("Contents/Developer") })
+        })
+        .collect()
+}
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None
+    }
+
+    String::from_utf8(output.stdout)
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
@@ -0,0 +1,392 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::process::Output;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::cache;
+use crate::data::{LineCoverage, Target, TargetFile, TestResults, XCodeBuildReport};
+use crate::err::{CommandExecutionError, FilePathError, XCReportError, XCResultError};
+use crate::events;
+use crate::fs::safe_file_name;
+use crate::tools;
+
+/// Reads the coverage with `xccov`, reusing the output cached for an unchanged bundle when `use_cache` is set.
+pub fn parse_xcresult_json(xcresult_file: &Path, use_cache: bool) -> Result<XCodeBuildReport, XCReportError> {
+
+    if !&xcresult_file.try_exists().unwrap_or_default() {
+        return Err(XCReportError::FilePath(FilePathError::NotFound))
+    }
+
+    // Extra xccov arguments may change the output, which the cache doesn't account for.
+    let use_cache = use_cache && tools::xccov_args().is_empty();
+
+    if use_cache {
+        let cached_report = cache::cached_coverage_json(xcresult_file)
+            .and_then(|json_report| parse_coverage_report(json_report.as_bytes()).ok());
+
+        if let Some(targets) = cached_report {
+            return Ok(targets)
+        }
+    }
+
+    let xcrun_output = tools::xcrun()
+        .args([
+            "xccov",
+            "view",
+            "--report",
+            "--json"
+        ])
+        .args(tools::xccov_args())
+        .arg(xcresult_file)
+        .output()
+        .map_err(|e| XCReportError::CommandExecution(CommandExecutionError::XCRun(e)))?;
+
+    if !xcrun_output.status.success() {
+        let desc = String::from_utf8_lossy(&xcrun_output.stderr).to_string();
+        return Err(XCReportError::CommandExecution(CommandExecutionError::NonZeroExit { desc }))
+    }
+
+    let json_report = String::from_utf8(xcrun_output.stdout)
+        .map_err(XCReportError::UTF8)?;
+
+    let targets = parse_coverage_report(json_report.as_bytes())?;
+
+    if use_cache {
+        cache::save_coverage_json(xcresult_file, &json_report)?;
+    }
+
+    Ok(targets)
+}
+
+/// Reads the output of `xccov view --report --json`, ignoring the fields it doesn't know. Targets and
+/// files that can't be read are left out of the report with a warning naming them, instead of losing
+/// the coverage of all the others, unless none of them can be read.
+fn parse_coverage_report(json: &[u8]) -> Result<XCodeBuildReport, XCReportError> {
+    let report: Value = serde_json::from_slice(json)
+        .map_err(XCReportError::Serde)?;
+
+    let Some(targets) = report["targets"].as_array() else {
+        return Err(XCReportError::XCResult(XCResultError::MalformedReport {
+            location: String::from("the top level"),
+            desc: String::from("no list of targets")
+        }))
+    };
+
+    let mut coverage = XCodeBuildReport::default();
+    let mut first_error: Option<XCResultError> = None;
+
+    for (index, target) in targets.iter().enumerate() {
+        match serde_json::from_value::<Target>(target.clone()) {
+            Ok(target) => coverage.push_target(target),
+            Err(_) => {
+                if let Some(target) = readable_part(index, target, &mut first_error) {
+                    coverage.push_target(target);
+                }
+            }
+        }
+    }
+
+    match first_error {
+        Some(error) if coverage.targets().iter().all(|target| target.files().is_empty()) => Err(XCReportError::XCResult(error)),
+        _ => Ok(coverage)
+    }
+}
+
+/// The files of a malformed target that can be read, warning about the others.
+fn readable_part(index: usize, target: &Value, first_error: &mut Option<XCResultError>) -> Option<Target> {
+    let mut skip = |location: String, desc: String| {
+        events::warning(&format!("leaving {} out of the coverage report: {}", location, desc));
+        first_error.get_or_insert(XCResultError::MalformedReport { location, desc });
+    };
+
+    let Some(name) = target["name"].as_str() else {
+        skip(format!("target #{}", index + 1), String::from("it has no name"));
+        return None
+    };
+    let Some(files) = target["files"].as_array() else {
+        skip(format!("target {}", name), String::from("its files are not a list"));
+        return None
+    };
+
+    let mut target_files: Vec<TargetFile> = vec![];
+    for (file_index, file) in files.iter().enumerate() {
+        match serde_json::from_value::<TargetFile>(file.clone()) {
+            Ok(file) => target_files.push(file),
+            Err(e) => {
+                let file_name = file["path"].as_str().map_or_else(|| format!("#{}", file_index + 1), |path| format!("{:?}", path));
+                skip(format!("file {} of target {}", file_name, name), e.to_string());
+            }
+        }
+    }
+
+    Some(Target::new(name.to_string(), target_files))
+}
+
+/// Format version of a result bundle, from its Info.plist, e.g. 3.53 for the bundles of Xcode 16.
+#[derive(Deserialize, Clone, Copy)]
+pub struct BundleVersion {
+    major: u32,
+    minor: u32
+}
+
+impl Display for BundleVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// The format version of the bundle, `None` when its Info.plist can't be read, e.g. for a merged bundle
+/// being written.
+pub fn bundle_version(xcresult_file: &Path) -> Option<BundleVersion> {
+    let output = tools::plutil()
+        .args(["-extract", "version", "json", "-o", "-"])
+        .arg(xcresult_file.join("Info.plist"))
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None
+    }
+
+    serde_json::from_slice(&output.stdout).ok()
+}
+
+pub fn parse_test_results(xcresult_file: &Path) -> Result<TestResults, XCReportError> {
+    let xcrun_output = tools::xcrun()
+        .args([
+            "xcresulttool",
+            "get",
+            "test-results",
+            "tests",
+            "--path",
+            xcresult_file.to_str().unwrap()
+        ])
+        .output()
+        .map_err(|e| XCReportError::CommandExecution(CommandExecutionError::XCRun(e)))?;
+
+    let json_results = String::from_utf8(xcrun_output.stdout)
+        .map_err(XCReportError::UTF8)?;
+
+    let test_results: TestResults = serde_json::from_str(&json_results)
+        .map_err(XCReportError::Serde)?;
+
+    Ok(test_results)
+}
+
+pub fn parse_line_coverage(xcresult_file: &Path, file_path: &str) -> Result<Vec<LineCoverage>, XCReportError> {
+    let xcrun_output = tools::xcrun()
+        .args([
+            "xccov",
+            "view",
+            "--archive",
+            "--json",
+            "--file",
+            file_path,
+            xcresult_file.to_str().unwrap()
+        ])
+        .output()
+        .map_err(|e| XCReportError::CommandExecution(CommandExecutionError::XCRun(e)))?;
+
+    let json_archive = String::from_utf8(xcrun_output.stdout)
+        .map_err(XCReportError::UTF8)?;
+
+    let archive: HashMap<String, Vec<LineCoverage>> = serde_json::from_str(&json_archive)
+        .map_err(XCReportError::Serde)?;
+
+    Ok(archive.into_values().flatten().collect())
+}
+
+/// Line coverage of every file in the bundle, keyed by the build machine path of the file.
+pub fn parse_archive(xcresult_file: &Path) -> Result<HashMap<String, Vec<LineCoverage>>, XCReportError> {
+    let xcrun_output = tools::xcrun()
+        .args([
+            "xccov",
+            "view",
+            "--archive",
+            "--json",
+            xcresult_file.to_str().unwrap()
+        ])
+        .output()
+        .map_err(|e| XCReportError::CommandExecution(CommandExecutionError::XCRun(e)))?;
+
+    let json_archive = String::from_utf8(xcrun_output.stdout)
+        .map_err(XCReportError::UTF8)?;
+
+    serde_json::from_str(&json_archive)
+        .map_err(XCReportError::Serde)
+}
+
+/// Reads the coverage by exporting the bundle's coverage reports with xcresulttool and
+/// viewing the exported `.xccovreport` files, for bundles `xccov` fails to read directly.
+pub fn parse_xcresult_with_xcresulttool(xcresult_file: &Path, export_dir: &Path) -> Result<XCodeBuildReport, XCReportError> {
+    if !&xcresult_file.try_exists().unwrap_or_default() {
+        return Err(XCReportError::FilePath(FilePathError::NotFound))
+    }
+
+    let root_output = run_xcresulttool(&["get", "--format", "json", "--path", xcresult_file.to_str().unwrap()])?;
+    let root: Value = serde_json::from_slice(&root_output.stdout)
+        .map_err(XCReportError::Serde)?;
+
+    let report_ids: Vec<&str> = root["actions"]["_values"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|action| action["actionResult"]["coverage"]["reportRef"]["id"]["_value"].as_str())
+        .collect();
+
+    if report_ids.is_empty() {
+        return Err(XCReportError::XCResult(XCResultError::NoCoverage))
+    }
+
+    let mut reports: Vec<XCodeBuildReport> = vec![];
+
+    for (index, report_id) in report_ids.into_iter().enumerate() {
+        let export_path = export_dir.join(format!("coverage_{}.xccovreport", index));
+
+        run_xcresulttool(&[
+            "export",
+            "--type",
+            "file",
+            "--path",
+            xcresult_file.to_str().unwrap(),
+            "--id",
+            report_id,
+            "--output-path",
+            export_path.to_str().unwrap()
+        ])?;
+
+        let xcrun_output = tools::xcrun()
+            .args([
+                "xccov",
+                "view",
+                "--report",
+                "--json"
+            ])
+            .args(tools::xccov_args())
+            .arg(&export_path)
+            .output()
+            .map_err(|e| XCReportError::CommandExecution(CommandExecutionError::XCRun(e)))?;
+
+        reports.push(parse_coverage_report(&xcrun_output.stdout)?);
+    }
+
+    Ok(XCodeBuildReport::merge(reports))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AttachmentManifestEntry {
+    test_identifier: String,
+    attachments: Vec<ExportedAttachment>
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportedAttachment {
+    exported_file_name: String,
+    suggested_human_readable_name: Option<String>
+}
+
+/// Exports the attachments of the failed tests, e.g. screenshots, into a directory per test under
+/// `output_dir`, and the diagnostics of the run, crash logs included, under `output_dir/diagnostics`.
+/// Needs the `export attachments` command of Xcode 16. Returns the number of attachments.
+pub fn export_failure_attachments(xcresult_file: &Path, output_dir: &Path) -> Result<usize, XCReportError> {
+    let bundle_name = xcresult_file.file_stem().and_then(|name| name.to_str()).unwrap_or("result");
+    let export_dir = output_dir.join(format!(".{}_export", bundle_name));
+
+    run_xcresulttool_command(&[
+        "export",
+        "attachments",
+        "--path",
+        xcresult_file.to_str().unwrap(),
+        "--output-path",
+        export_dir.to_str().unwrap(),
+        "--only-failures"
+    ])?;
+
+    let manifest = std::fs::read(export_dir.join("manifest.json"))
+        .map_err(XCReportError::FileIO)?;
+    let manifest: Vec<AttachmentManifestEntry> = serde_json::from_slice(&manifest)
+        .map_err(XCReportError::Serde)?;
+
+    let mut count = 0;
+    for entry in manifest {
+        let test_dir = entry.test_identifier
+            .split('/')
+            .map(safe_file_name)
+            .fold(output_dir.to_path_buf(), |dir, component| dir.join(component));
+        std::fs::create_dir_all(&test_dir)
+            .map_err(XCReportError::FileIO)?;
+
+        for attachment in entry.attachments {
+            let file_name = safe_file_name(attachment.suggested_human_readable_name.as_deref().unwrap_or(&attachment.exported_file_name));
+            let mut destination = test_dir.join(&file_name);
+            if destination.exists() {
+                destination = test_dir.join(format!("{}_{}", count, file_name));
+            }
+
+            std::fs::rename(export_dir.join(&attachment.exported_file_name), destination)
+                .map_err(XCReportError::FileIO)?;
+            count += 1;
+        }
+    }
+
+    std::fs::remove_dir_all(&export_dir)
+        .map_err(XCReportError::FileIO)?;
+
+    let diagnostics_dir = output_dir.join("diagnostics").join(bundle_name);
+    run_xcresulttool_command(&[
+        "export",
+        "diagnostics",
+        "--path",
+        xcresult_file.to_str().unwrap(),
+        "--output-path",
+        diagnostics_dir.to_str().unwrap()
+    ])?;
+
+    Ok(count)
+}
+
+fn run_xcresulttool_command(args: &[&str]) -> Result<Output, XCReportError> {
+    let output = tools::xcrun()
+        .arg("xcresulttool")
+        .args(args)
+        .output()
+        .map_err(|e| XCReportError::CommandExecution(CommandExecutionError::XCRun(e)))?;
+
+    if !output.status.success() {
+        let desc = String::from_utf8_lossy(&output.stderr).to_string();
+        return Err(XCReportError::CommandExecution(CommandExecutionError::NonZeroExit { desc }))
+    }
+
+    Ok(output)
+}
+
+/// Runs an xcresulttool `get`/`export` subcommand through the object API, which Xcode 16 and
+/// later only expose as `<subcommand> object --legacy`.
+fn run_xcresulttool(args: &[&str]) -> Result<Output, XCReportError> {
+    let run = |legacy: bool| {
+        let mut command = tools::xcrun();
+        command.arg("xcresulttool").arg(args[0]);
+        if legacy {
+            command.args(["object", "--legacy"]);
+        }
+
+        command
+            .args(&args[1..])
+            .output()
+            .map_err(|e| XCReportError::CommandExecution(CommandExecutionError::XCRun(e)))
+    };
+
+    let output = run(true)?;
+    let output = if output.status.success() { output } else { run(false)? };
+
+    if !output.status.success() {
+        let desc = String::from_utf8_lossy(&output.stderr).to_string();
+        return Err(XCReportError::CommandExecution(CommandExecutionError::NonZeroExit { desc }))
+    }
+
+    Ok(output)
+}
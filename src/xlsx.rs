@@ -0,0 +1,135 @@
+use std::collections::HashSet;
+use std::path::Path;
+use polars::prelude::*;
+use rust_xlsxwriter::{ConditionalFormat3ColorScale, ConditionalFormatType, Format, Workbook, Worksheet};
+
+use crate::err::XCReportError;
+
+const SUMMARY_SHEET: &str = "Summary";
+
+/// Excel rejects longer sheet names.
+const MAX_SHEET_NAME_LENGTH: usize = 31;
+
+/// Writes a workbook with the summary report on the first sheet and the files of every squad on a sheet of its own.
+pub fn save_xlsx_report(summary: &DataFrame, full_report: &DataFrame, path: &Path) -> Result<(), XCReportError> {
+    let mut workbook = Workbook::new();
+
+    let worksheet = workbook.add_worksheet();
+    worksheet.set_name(SUMMARY_SHEET).map_err(XCReportError::Xlsx)?;
+    write_dataframe(worksheet, summary)?;
+    add_coverage_scale(worksheet, summary, "Coverage %", 100.0)?;
+
+    let squads = full_report
+        .column("Squad")
+        .and_then(|squads| squads.unique_stable())
+        .map_err(XCReportError::Polars)?;
+
+    let mut sheet_names = HashSet::from([SUMMARY_SHEET.to_lowercase()]);
+
+    for squad in squads.str().map_err(XCReportError::Polars)?.into_iter().flatten() {
+        let squad_files = full_report
+            .clone()
+            .lazy()
+            .filter(col("Squad").eq(lit(squad)))
+            .drop_columns(["Squad"])
+            .sort_by_exprs(vec![col("Line Coverage"), col("Filepath")], vec![false, false], true, true)
+            .collect()
+            .map_err(XCReportError::Polars)?;
+
+        let worksheet = workbook.add_worksheet();
+        worksheet.set_name(sheet_name(squad, &mut sheet_names)).map_err(XCReportError::Xlsx)?;
+        write_dataframe(worksheet, &squad_files)?;
+
+        if let Some(index) = squad_files.get_column_index("Line Coverage") {
+            let percent = Format::new().set_num_format("0.00%");
+            worksheet.set_column_format(index as u16, &percent).map_err(XCReportError::Xlsx)?;
+        }
+
+        add_coverage_scale(worksheet, &squad_files, "Line Coverage", 1.0)?;
+    }
+
+    workbook.save(path).map_err(XCReportError::Xlsx)
+}
+
+/// Writes the header and the rows of the data frame, leaving null cells empty.
+fn write_dataframe(worksheet: &mut Worksheet, df: &DataFrame) -> Result<(), XCReportError> {
+    let header = Format::new().set_bold();
+
+    for (column_index, series) in df.get_columns().iter().enumerate() {
+        let column_index = column_index as u16;
+        worksheet
+            .write_string_with_format(0, column_index, series.name(), &header)
+            .map_err(XCReportError::Xlsx)?;
+
+        for (row_index, value) in series.iter().enumerate() {
+            let row_index = row_index as u32 + 1;
+
+            match value {
+                AnyValue::Null => continue,
+                AnyValue::String(value) => worksheet.write_string(row_index, column_index, value),
+                AnyValue::Boolean(value) => worksheet.write_boolean(row_index, column_index, value),
+                value => match value.extract::<f64>() {
+                    Some(value) => worksheet.write_number(row_index, column_index, value),
+                    None => worksheet.write_string(row_index, column_index, value.to_string())
+                }
+            }
+            .map_err(XCReportError::Xlsx)?;
+        }
+    }
+
+    worksheet.set_freeze_panes(1, 0).map_err(XCReportError::Xlsx)?;
+    worksheet.autofit();
+
+    Ok(())
+}
+
+/// Colors the coverage column from red at no coverage over yellow to green at full coverage.
+fn add_coverage_scale(worksheet: &mut Worksheet, df: &DataFrame, column: &str, full_coverage: f64) -> Result<(), XCReportError> {
+    let Some(index) = df.get_column_index(column) else {
+        return Ok(())
+    };
+
+    if df.height() == 0 {
+        return Ok(())
+    }
+
+    let scale = ConditionalFormat3ColorScale::new()
+        .set_minimum(ConditionalFormatType::Number, 0)
+        .set_midpoint(ConditionalFormatType::Number, full_coverage / 2.0)
+        .set_maximum(ConditionalFormatType::Number, full_coverage)
+        .set_minimum_color("#F8696B")
+        .set_midpoint_color("#FFEB84")
+        .set_maximum_color("#63BE7B");
+
+    worksheet
+        .add_conditional_format(1, index as u16, df.height() as u32, index as u16, &scale)
+        .map_err(XCReportError::Xlsx)?;
+
+    Ok(())
+}
+
+/// Makes a valid, unique sheet name from the squad name.
+fn sheet_name(squad: &str, used: &mut HashSet<String>) -> String {
+    let base: String = squad
+        .chars()
+        .map(|c| if matches!(c, '[' | ']' | ':' | '*' | '?' | '/' | '\\') { '_' } else { c })
+        .collect::<String>()
+        .trim_matches('\'')
+        .chars()
+        .take(MAX_SHEET_NAME_LENGTH)
+        .collect();
+    let base = if base.trim().is_empty() { String::from("Squad") } else { base };
+
+    let mut name = base.clone();
+    let mut suffix = 2;
+
+    // Excel compares sheet names case-insensitively.
+    while !used.insert(name.to_lowercase()) {
+        let suffix_text = format!(" ({})", suffix);
+        let prefix: String = base.chars().take(MAX_SHEET_NAME_LENGTH - suffix_text.len()).collect();
+        name = format!("{}{}", prefix, suffix_text);
+        suffix += 1;
+    }
+
+    name
+}